@@ -0,0 +1,147 @@
+//! Measures parsing, building and rendering derivation trees and solving for deeply nested expressions.
+//! Everything runs on a thread with a 1 MiB stack, the default stack size of Wasm, so any recursion per level would overflow it.
+//! The expressions are parsed from their source text, so the whole pipeline of `solve_with_config` is covered.
+//!
+//! Run it with `cargo bench --bench deep_expressions`.
+
+use std::time::{Duration, Instant};
+
+use fmfp::{
+    parse_with_config, solve_with_config,
+    typers::{
+        config::Config,
+        tree::{Tree, TypeInference},
+        utils::mathjax::MathJax,
+    },
+};
+
+/// Stack size of the benchmark thread, this is the default stack size of a Wasm module.
+const STACK_SIZE: usize = 1 << 20;
+
+/// Writes `\f -> \x -> (f (f ... (f x)))` with `n` applications.
+fn application_chain(n: usize) -> String {
+    let mut expr = "x".to_string();
+    for _ in 0..n {
+        expr = format!("(f {})", expr);
+    }
+    format!("(\\f -> (\\x -> {}))", expr)
+}
+
+/// Writes `(1, (1, ... (1, 1)))` with `n` tuples.
+fn tuple_chain(n: usize) -> String {
+    let mut expr = "1".to_string();
+    for _ in 0..n {
+        expr = format!("(1, {})", expr);
+    }
+    expr
+}
+
+fn count_nodes(tree: &Tree) -> usize {
+    let mut stack = vec![tree];
+    let mut count = 0;
+    while let Some(tree) = stack.pop() {
+        count += 1;
+        stack.extend(tree.constraints.iter());
+    }
+    count
+}
+
+fn per_node(duration: Duration, nodes: usize) -> String {
+    format!("{:.0} ns", duration.as_nanos() as f64 / nodes as f64)
+}
+
+fn bench(name: &str, source: fn(usize) -> String, sizes: &[usize], config: &Config) {
+    println!(
+        "{:<20} {:>8} {:>12} {:>12} {:>12} {:>12} {:>14} {:>16} {:>12} {:>12}",
+        name,
+        "nodes",
+        "parse",
+        "infer",
+        "per node",
+        "render",
+        "output",
+        "per output byte",
+        "solve",
+        "no steps"
+    );
+    for &n in sizes {
+        let input = source(n);
+
+        let start = Instant::now();
+        let ast = parse_with_config(&input, config)
+            .unwrap_or_else(|e| panic!("parsing {} levels failed: {}", n, e));
+        let parse = start.elapsed();
+
+        let start = Instant::now();
+        let (tree, _constraints) =
+            TypeInference::infer_with_config(ast, config).expect("inference failed");
+        let infer = start.elapsed();
+        let nodes = count_nodes(&tree);
+
+        // The judgment of every node contains its whole subexpression, so the output grows quadratically with the depth.
+        // Rendering is linear in the size of the output.
+        let start = Instant::now();
+        let output = tree.to_mathjax();
+        let render = start.elapsed();
+
+        // The whole pipeline once more, including the solver and rendering its steps.
+        // Every substitute step records the goal, which grows up to the size of the result, so the trace is quadratic in the depth.
+        let start = Instant::now();
+        let parsed = solve_with_config(&input, config);
+        let solve = start.elapsed();
+        assert!(parsed.solution.is_some(), "solving failed");
+        assert_eq!(parsed.solution.and_then(|s| s.result_error), None);
+
+        // Without recording the steps the union-find solver is linear, what is left grows with the quadratic output of the tree
+        let fast = Config {
+            record_steps: false,
+            ..*config
+        };
+        let start = Instant::now();
+        let parsed = solve_with_config(&input, &fast);
+        let no_steps = start.elapsed();
+        assert_eq!(parsed.solution.and_then(|s| s.result_error), None);
+
+        println!(
+            "{:<20} {:>8} {:>12.2?} {:>12.2?} {:>12} {:>12.2?} {:>14} {:>16} {:>12.2?} {:>12.2?}",
+            "",
+            nodes,
+            parse,
+            infer,
+            per_node(infer, nodes),
+            render,
+            output.len(),
+            per_node(render, output.len()),
+            solve,
+            no_steps,
+        );
+    }
+    println!();
+}
+
+fn run() {
+    // The default limits keep the browser responsive and reject expressions nested deeper than 500 levels.
+    let config = Config {
+        max_input_length: usize::MAX,
+        max_ast_depth: usize::MAX,
+        max_type_vars: usize::MAX,
+        max_constraints: usize::MAX,
+        max_solver_steps: usize::MAX,
+        max_output_length: usize::MAX,
+        ..Config::default()
+    };
+    let sizes = [500, 1_000, 2_000, 4_000, 8_000];
+    bench("application chain", application_chain, &sizes, &config);
+    // Every substitute step of the nested tuples keeps the goal before and after it, which is a tuple as deep as the expression.
+    // The trace of 8000 levels takes more than 5 GB, so the tuples stop at 4000.
+    bench("nested tuples", tuple_chain, &sizes[..4], &config);
+}
+
+fn main() {
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("could not spawn the benchmark thread")
+        .join()
+        .expect("the benchmark thread panicked");
+}
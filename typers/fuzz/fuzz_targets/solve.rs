@@ -0,0 +1,29 @@
+#![no_main]
+
+use std::time::{Duration, Instant};
+
+use fmfp::solve;
+use libfuzzer_sys::fuzz_target;
+
+/// Upper bound for a single run of the whole pipeline. Anything slower freezes the web page.
+const MAX_RUNTIME: Duration = Duration::from_secs(2);
+
+fuzz_target!(|data: &[u8]| {
+    // `solve` is called with the content of a text field, so it only ever sees valid UTF-8.
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    // Any panic inside the pipeline is reported as a crash by libfuzzer.
+    let start = Instant::now();
+    let _parsed = solve(input);
+    let elapsed = start.elapsed();
+
+    assert!(
+        elapsed < MAX_RUNTIME,
+        "solving {:?} took {:?}, which exceeds the limit of {:?}",
+        input,
+        elapsed,
+        MAX_RUNTIME
+    );
+});
@@ -1,10 +1,26 @@
 pub mod typers;
 
-use std::cmp;
-
+use serde::Serialize;
+use tsify::Tsify;
 use typers::{
-    parser::MiniHaskellParser, rules::RuleExpr, solver::solve_constraints, tree::TypeInference,
-    utils::mathjax::MathJax,
+    config::Config,
+    engines::{self, Comparison},
+    grading::{self, Grade, TreeGrade},
+    holes::{self, Holes},
+    parser::{AstNode, MiniHaskellParser},
+    rules::{Origin, RuleExpr},
+    slicing::{self, ErrorSlice},
+    solver::{self, Solution, StrategyComparison},
+    tree::{Tree, TypeInference},
+    type_parser::TypeParser,
+    utils::{
+        dot::{self, Dot, DotGraph},
+        json::{Export, SCHEMA_VERSION},
+        latex,
+        mathjax::MathJax,
+        typst,
+        wasm::{from_js, AstNodeJs, ConstraintsJs, RuleExprJs, TreeJs, TypeExprJs},
+    },
 };
 
 use typers::rules::TypeExpr;
@@ -20,16 +36,331 @@ pub struct Parsed {
     pub tree: Option<String>,             // The AST in a format which can be rendered by MathJax.s
     pub constraints_error: Option<String>, // Error message if the constraints could not be generated.
     pub constraints: Option<Vec<String>>, // The constraints in a format which can be rendered by MathJax.
+    pub constraint_origins: Option<Vec<String>>, // The typing rule and the subexpression which generated each constraint, for MathJax.
     pub constraints_without_trivial: Option<Vec<String>>, // The constraints without the trivial constraints in a format which can be rendered by MathJax.
     pub solution: Option<SolutionTS>, // The solution in a format which can be rendered by MathJax.
+    pub holes: Option<Vec<String>>, // The judgment of every typed hole after solving, empty if the solver failed, for MathJax.
+}
+
+/// Exports the derivation tree, the constraints and the step by step solution of the expression as a standalone LaTeX document.
+#[wasm_bindgen]
+pub fn solve_to_latex(input: &str) -> Result<String, String> {
+    solve_to_latex_with_config(input, &Config::default())
+}
+
+/// Like `solve_to_latex`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn solve_to_latex_with_config(input: &str, config: &Config) -> Result<String, String> {
+    let run = run_pipeline(input, config)?;
+    Ok(latex::document(
+        &run.ast,
+        &run.tree,
+        &run.constraints,
+        &run.solution,
+    ))
+}
+
+/// Exports the derivation tree, the constraints and the step by step solution of the expression as a standalone Typst document.
+#[wasm_bindgen]
+pub fn solve_to_typst(input: &str) -> Result<String, String> {
+    solve_to_typst_with_config(input, &Config::default())
+}
+
+/// Like `solve_to_typst`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn solve_to_typst_with_config(input: &str, config: &Config) -> Result<String, String> {
+    let run = run_pipeline(input, config)?;
+    Ok(typst::document(
+        &run.ast,
+        &run.tree,
+        &run.constraints,
+        &run.solution,
+    ))
+}
+
+/// Exports the AST, the derivation tree or the constraint graph of the expression in the DOT language of Graphviz.
+#[wasm_bindgen]
+pub fn solve_to_dot(input: &str, graph: DotGraph) -> Result<String, String> {
+    solve_to_dot_with_config(input, graph, &Config::default())
+}
+
+/// Like `solve_to_dot`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn solve_to_dot_with_config(
+    input: &str,
+    graph: DotGraph,
+    config: &Config,
+) -> Result<String, String> {
+    let run = run_pipeline(input, config)?;
+    Ok(match graph {
+        DotGraph::Ast => run.ast.to_dot(),
+        DotGraph::Tree => run.tree.to_dot(),
+        DotGraph::Constraints => dot::constraint_graph(&run.solution.simplified_rules(), 0),
+    })
+}
+
+/// Exports the AST, the derivation tree, the constraints and the solution of the expression as JSON.
+/// The format is described by the versioned schema in `schema/fmfp.v2.schema.json`.
+#[wasm_bindgen]
+pub fn solve_to_json(input: &str) -> Result<String, String> {
+    solve_to_json_with_config(input, &Config::default())
+}
+
+/// Like `solve_to_json`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn solve_to_json_with_config(input: &str, config: &Config) -> Result<String, String> {
+    export(input, config)?.to_json(config)
+}
+
+/// Runs the pipeline and collects its results for the JSON export, use `Export::to_json_pretty` for indented JSON.
+pub fn export(input: &str, config: &Config) -> Result<Export, String> {
+    let run = run_pipeline(input, config)?;
+    Ok(Export {
+        version: SCHEMA_VERSION,
+        input: input.to_string(),
+        ast: run.ast,
+        tree: run.tree,
+        constraints: run.constraints,
+        solution: run.solution,
+    })
+}
+
+/// Parses the expression into its abstract syntax tree.
+#[wasm_bindgen]
+pub fn parse(input: &str) -> Result<AstNode, String> {
+    parse_with_config(input, &Config::default())
+}
+
+/// Like `parse`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn parse_with_config(input: &str, config: &Config) -> Result<AstNode, String> {
+    let parsed = MiniHaskellParser::parse_str_with_config(input, config)?;
+    MiniHaskellParser::build_ast(parsed)
+}
+
+/// The derivation tree of an expression together with the constraints collected while building it
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct Inference {
+    pub tree: Tree,
+    pub constraints: Vec<(TypeExpr, TypeExpr)>,
+}
+
+/// Builds the derivation tree of an AST returned by `parse` and collects the constraints on its types.
+#[wasm_bindgen]
+pub fn infer(ast: AstNodeJs) -> Result<Inference, String> {
+    infer_with_config(ast, &Config::default())
+}
+
+/// Like `infer`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn infer_with_config(ast: AstNodeJs, config: &Config) -> Result<Inference, String> {
+    let ast: AstNode = from_js(&ast, config)?;
+    let (tree, constraints) = TypeInference::infer_with_config(ast, config)?;
+    Ok(Inference { tree, constraints })
+}
+
+/// Solves the constraints returned by `infer` step by step, the result is the type of `goal_var`.
+/// The type of the whole expression is `t0`.
+#[wasm_bindgen]
+pub fn solve_constraints(constraints: ConstraintsJs, goal_var: usize) -> Result<Solution, String> {
+    solve_constraints_with_config(constraints, goal_var, &Config::default())
+}
+
+/// Like `solve_constraints`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn solve_constraints_with_config(
+    constraints: ConstraintsJs,
+    goal_var: usize,
+    config: &Config,
+) -> Result<Solution, String> {
+    let constraints: Vec<(TypeExpr, TypeExpr)> = from_js(&constraints, config)?;
+    Ok(solver::solve_equations_with_config(
+        constraints,
+        goal_var,
+        config,
+    ))
+}
+
+/// Renders an AST returned by `parse` for MathJax.
+#[wasm_bindgen]
+pub fn ast_to_mathjax(ast: AstNodeJs) -> Result<String, String> {
+    Ok(from_js::<AstNode>(&ast, &Config::default())?.to_mathjax())
+}
+
+/// Renders a type for MathJax.
+#[wasm_bindgen]
+pub fn type_to_mathjax(typ: TypeExprJs) -> Result<String, String> {
+    Ok(from_js::<TypeExpr>(&typ, &Config::default())?.to_mathjax())
+}
+
+/// Renders a rule for MathJax.
+#[wasm_bindgen]
+pub fn rule_to_mathjax(rule: RuleExprJs) -> Result<String, String> {
+    Ok(from_js::<RuleExpr>(&rule, &Config::default())?.to_mathjax())
+}
+
+/// Renders a derivation tree returned by `infer` for MathJax, as a single proof tree.
+#[wasm_bindgen]
+pub fn tree_to_mathjax(tree: TreeJs) -> Result<String, String> {
+    Ok(from_js::<Tree>(&tree, &Config::default())?.to_mathjax())
+}
+
+/// Grades the constraints and the final type a student derived for the expression.
+/// The constraints are written like `t0 = t1 -> t2`, one per line or separated by `;`.
+/// A missing final type means the student claims that the expression has no type.
+/// Both are compared with the results of the pipeline up to renaming of the type variables.
+#[wasm_bindgen]
+pub fn grade(input: &str, constraints: &str, final_type: Option<String>) -> Result<Grade, String> {
+    grade_with_config(input, constraints, final_type, &Config::default())
+}
+
+/// Like `grade`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn grade_with_config(
+    input: &str,
+    constraints: &str,
+    final_type: Option<String>,
+    config: &Config,
+) -> Result<Grade, String> {
+    let run = run_pipeline(input, config)?;
+    let student = TypeParser::parse_constraints_with_config(constraints, config)
+        .map_err(|e| format!("the constraints could not be parsed: {}", e))?;
+    let student_type = match final_type {
+        Some(typ) => Some(
+            TypeParser::parse_type_with_config(&typ, config)
+                .map_err(|e| format!("the final type could not be parsed: {}", e))?,
+        ),
+        None => None,
+    };
+    let expected_type = match &run.solution.result {
+        Some(Ok(rule)) => Some(*rule.rhs.clone()),
+        _ => None,
+    };
+    Ok(grading::grade(
+        &run.constraints,
+        expected_type.as_ref(),
+        &student,
+        student_type.as_ref(),
+    ))
+}
+
+/// Checks a derivation tree a student wrote for the expression and reports the first node which misapplies its rule.
+/// Every line holds one judgment like `[App] x: t1 |- (f x) :: t0`, premises are indented further than their conclusion.
+#[wasm_bindgen]
+pub fn grade_tree(input: &str, derivation: &str) -> Result<TreeGrade, String> {
+    grade_tree_with_config(input, derivation, &Config::default())
+}
+
+/// Like `grade_tree`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn grade_tree_with_config(
+    input: &str,
+    derivation: &str,
+    config: &Config,
+) -> Result<TreeGrade, String> {
+    let ast = parse_with_config(input, config)?;
+    let derivation = TypeParser::parse_derivation_with_config(derivation, config)?;
+    Ok(grading::check_derivation(&ast, &derivation))
+}
+
+/// Infers the type of the expression with every engine, so their traces can be shown side by side.
+/// All engines find the same principal type, up to renaming of its variables.
+#[wasm_bindgen]
+pub fn compare_engines(input: &str) -> Result<Comparison, String> {
+    compare_engines_with_config(input, &Config::default())
+}
+
+/// Like `compare_engines`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn compare_engines_with_config(input: &str, config: &Config) -> Result<Comparison, String> {
+    let ast = parse_with_config(input, config)?;
+    Ok(engines::compare_engines(&ast, config))
+}
+
+/// Solves the constraints of the expression with every solver strategy, so the traces can be compared step by step
+#[wasm_bindgen]
+pub fn compare_strategies(input: &str) -> Result<StrategyComparison, String> {
+    compare_strategies_with_config(input, &Config::default())
+}
+
+/// Like `compare_strategies`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn compare_strategies_with_config(
+    input: &str,
+    config: &Config,
+) -> Result<StrategyComparison, String> {
+    let ast = parse_with_config(input, config)?;
+    let (_, constraints) = TypeInference::infer_with_config(ast, config)?;
+    Ok(solver::compare_strategies(constraints, 0, config))
+}
+
+/// Finds the constraints of an expression without a type which contradict each other and the subexpressions which generated them.
+/// Fails if the expression has a type.
+#[wasm_bindgen]
+pub fn slice_error(input: &str) -> Result<ErrorSlice, String> {
+    slice_error_with_config(input, &Config::default())
+}
+
+/// Like `slice_error`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn slice_error_with_config(input: &str, config: &Config) -> Result<ErrorSlice, String> {
+    let run = run_pipeline(input, config)?;
+    slicing::slice_error(&run.tree, &run.constraints, &run.origins)
+        .ok_or_else(|| "the constraints can be solved, there is no type error".to_string())
+}
+
+/// Reports the type and the context every typed hole `_` or `_name` of the expression needs, after solving its constraints.
+/// Fails if the expression has no type.
+#[wasm_bindgen]
+pub fn find_holes(input: &str) -> Result<Holes, String> {
+    find_holes_with_config(input, &Config::default())
+}
+
+/// Like `find_holes`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn find_holes_with_config(input: &str, config: &Config) -> Result<Holes, String> {
+    let run = run_pipeline(input, config)?;
+    holes::find_holes(&run.tree, &run.solution)
+}
+
+/// The results of all stages of the pipeline
+pub struct Pipeline {
+    pub ast: AstNode,
+    pub tree: Tree,
+    pub constraints: Vec<(TypeExpr, TypeExpr)>,
+    /// The node of the tree which generated each constraint
+    pub origins: Vec<Origin>,
+    pub solution: Solution,
+}
+
+/// Runs the whole pipeline without converting the results for web assembly, the first error aborts it
+pub fn run_pipeline(input: &str, config: &Config) -> Result<Pipeline, String> {
+    let parsed = MiniHaskellParser::parse_str_with_config(input, config)?;
+    let ast = MiniHaskellParser::build_ast(parsed)?;
+    let (tree, constraints, origins) = TypeInference::infer_with_origins(ast.clone(), config)?;
+    let solution = solver::solve_equations_with_origins(constraints.clone(), &origins, 0, config);
+    Ok(Pipeline {
+        ast,
+        tree,
+        constraints,
+        origins,
+        solution,
+    })
 }
 
 #[wasm_bindgen]
 pub fn solve(input: &str) -> Parsed {
+    solve_with_config(input, &Config::default())
+}
+
+/// Like `solve`, with the limits and options of the `Config`.
+#[wasm_bindgen]
+pub fn solve_with_config(input: &str, config: &Config) -> Parsed {
     let mut result = Parsed::default();
 
     // Parse the input string
-    let parsed = MiniHaskellParser::parse_str(input);
+    let parsed = MiniHaskellParser::parse_str_with_config(input, config);
     let parsed = match parsed {
         Ok(parsed) => parsed,
         Err(e) => {
@@ -38,7 +369,6 @@ pub fn solve(input: &str) -> Parsed {
         }
     };
 
-    dbg!("CHECKPOINT: 1");
     // build the AST from the parsed input.
     let ast = MiniHaskellParser::build_ast(parsed);
 
@@ -50,14 +380,13 @@ pub fn solve(input: &str) -> Parsed {
             return result;
         }
     };
-    dbg!("CHECKPOINT: 2");
 
     // Generate the type constraints from the AST.
-    let typ_inference = TypeInference::infer(ast.clone());
+    let typ_inference = TypeInference::infer_with_origins(ast.clone(), config);
 
     // Tree contains the derivation tree for the expression.
-    // constraints contains all the constraints that were generated while building the tree.
-    let (tree, constraints) = match typ_inference {
+    // constraints contains all the constraints that were generated while building the tree, origins the node each of them comes from.
+    let (tree, constraints, origins) = match typ_inference {
         Ok(typ_inference) => typ_inference,
         Err(e) => {
             result.constraints_error = Some(e.to_string());
@@ -67,7 +396,20 @@ pub fn solve(input: &str) -> Parsed {
 
     result.tree = Some(tree.to_mathjax());
 
-    dbg!("CHECKPOINT: 3");
+    let nodes = tree.preorder();
+    result.constraint_origins = Some(
+        origins
+            .iter()
+            .map(|origin| {
+                format!(
+                    "from the {} node for \\({}\\)",
+                    origin.rule,
+                    nodes[origin.node].expr.0.to_mathjax()
+                )
+            })
+            .collect(),
+    );
+
     // All the constraints found in the tree.
     result.constraints = Some(
         constraints
@@ -77,11 +419,12 @@ pub fn solve(input: &str) -> Parsed {
     );
 
     // Remove the trivial constraints from the constraints.
-    let constraints_without_trivial = constraints
+    let (constraints_without_trivial, origins): (Vec<_>, Vec<_>) = constraints
         .iter()
-        .filter(|(a, b)| a != b)
         .cloned()
-        .collect::<Vec<_>>();
+        .zip(origins)
+        .filter(|((a, b), _)| a != b)
+        .unzip();
 
     result.constraints_without_trivial = Some(
         constraints_without_trivial
@@ -90,77 +433,15 @@ pub fn solve(input: &str) -> Parsed {
             .collect::<Vec<_>>(),
     );
 
-    // Because the algorithm can only solve constraints of the form `t_i = [TypeExpr]` we need to convert the constraints `(TypeExpr, TypeExpr)`.
-    // If the constraints are already in the correct form, we can just use them.
-    // If a constraint `(TypeExpr_1, TypeExpr_2)` is not in the correct form, we need to convert them.
-    // This is done by introducing a new `t_i` and adding the constraints `t_i = TypeExpr_1` and `t_i = TypeExpr_2`.
-
-    // Get the maximum variable used in the construction
-    let mut maximum = get_max_var(constraints_without_trivial.clone()) + 1;
-
-    let mut new_constraints = Vec::<RuleExpr>::new();
-
-    for (a, b) in constraints_without_trivial {
-        if a == b {
-            continue;
-        }
-
-        match (a, b) {
-            // check if the rule is duplicated
-            (TypeExpr::Var(x), b) => {
-                // Normal form
-                if new_constraints
-                    .iter()
-                    .any(|rule| *rule.rhs == b && rule.var == x)
-                {
-                    continue;
-                }
-                new_constraints.push(RuleExpr {
-                    var: x,
-                    rhs: Box::new(b.clone()),
-                });
-            }
-            (a, TypeExpr::Var(x)) => {
-                // Almost normal form, we need to swap the variables.
-                if new_constraints
-                    .iter()
-                    .any(|rule| *rule.rhs == a && rule.var == x)
-                {
-                    continue;
-                }
-                new_constraints.push(RuleExpr {
-                    var: x,
-                    rhs: Box::new(a.clone()),
-                });
-            }
-            (a, b) => {
-                new_constraints.push(RuleExpr {
-                    var: maximum,
-                    rhs: Box::new(a.clone()),
-                });
-                new_constraints.push(RuleExpr {
-                    var: maximum,
-                    rhs: Box::new(b.clone()),
-                });
-                maximum += 1;
-            }
-        }
-    }
-
-    dbg!("CHECKPOINT: 4");
     // Solve the constraints
-    let solution = solve_constraints(new_constraints, 0);
+    let solution =
+        solver::solve_equations_with_origins(constraints_without_trivial, &origins, 0, config);
 
+    result.holes = Some(
+        holes::find_holes(&tree, &solution)
+            .map(|holes| holes.holes.iter().map(|hole| hole.to_mathjax()).collect())
+            .unwrap_or_default(),
+    );
     result.solution = Some(solution.into());
     result
 }
-
-fn get_max_var(constraints: Vec<(TypeExpr, TypeExpr)>) -> usize {
-    constraints.into_iter().fold(0, |mut max, (a, b)| {
-        let res = a.all_vars().into_iter().max();
-        max = cmp::max(max, res.unwrap_or(0));
-        let res = b.all_vars().into_iter().max();
-        max = cmp::max(max, res.unwrap_or(0));
-        max
-    })
-}
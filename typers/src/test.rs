@@ -1,7 +1,306 @@
-use fmfp::solve;
+use clap::{Parser, ValueEnum};
+use fmfp::{
+    compare_engines_with_config, compare_strategies_with_config, export, grade_tree_with_config,
+    grade_with_config, run_pipeline,
+    typers::{
+        config::{Config, Generation, SolverStrategy},
+        holes, slicing,
+        utils::{
+            dot::{self, Dot},
+            latex,
+            mathjax::MathJax,
+            text::{render_tree, TextOptions},
+            typst,
+        },
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// A proof tree for the terminal, followed by the constraints and the result
+    Text,
+    /// A standalone LaTeX document
+    Latex,
+    /// A standalone Typst document
+    Typst,
+    /// The derivation tree as it is sent to the web interface
+    Mathjax,
+    /// All results as JSON, following the schema in `schema/fmfp.v2.schema.json`
+    Json,
+    /// The AST as a Graphviz graph
+    DotAst,
+    /// The derivation tree as a Graphviz graph
+    DotTree,
+    /// The dependency graph of the type variables after simplifying, as a Graphviz graph
+    DotConstraints,
+    /// The traces of all inference algorithms one after another, in MathJax notation
+    Engines,
+    /// The steps of the solver with every order, and where they differ from the default order
+    Orders,
+}
+
+/// How the derivation tree introduces type variables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Strategy {
+    /// Fresh variables for every abstraction and tuple
+    AlwaysFresh,
+    /// Reuse function and tuple types which are already known
+    ReuseStructure,
+    /// Number the variables like the exercise solutions
+    CourseConvention,
+}
+
+/// In which order the solver simplifies the rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Order {
+    /// Compare two rules, then remove a simple rule
+    Alternate,
+    /// Remove all simple rules first
+    RemoveFirst,
+    /// Compare all rules with the same left hand side first
+    AccumulateFirst,
+    /// Work on the variable with the lowest number first
+    LowestVariable,
+}
+
+/// Infers the type of a MiniHaskell expression and shows its derivation
+#[derive(Debug, Parser)]
+#[command(name = "FMFP")]
+struct Args {
+    /// The expression, for example "(\x -> (x + 1))"
+    expression: String,
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// How the derivation tree introduces type variables
+    #[arg(short, long, value_enum, default_value_t = Strategy::ReuseStructure)]
+    strategy: Strategy,
+    /// In which order the solver simplifies the rules
+    #[arg(short, long, value_enum, default_value_t = Order::Alternate)]
+    order: Order,
+    /// Maximum width of the proof tree, defaults to the width of the terminal
+    #[arg(short, long)]
+    width: Option<usize>,
+    /// Only use ASCII characters in the proof tree
+    #[arg(long)]
+    ascii: bool,
+    /// Indent the JSON output
+    #[arg(long)]
+    pretty: bool,
+    /// Grade the constraints in this file, one per line like `t0 = t1 -> t2`, instead of printing the derivation
+    #[arg(long, value_name = "FILE")]
+    grade: Option<String>,
+    /// The final type the student claims, without it the student claims that the expression has no type
+    #[arg(long, value_name = "TYPE", requires = "grade")]
+    final_type: Option<String>,
+    /// Check the derivation tree in this file, one judgment per line like `[App] x: t1 |- (f x) :: t0`
+    #[arg(long, value_name = "FILE", conflicts_with = "grade")]
+    grade_tree: Option<String>,
+}
 
 fn main() {
-    let input = "\\x -> if ((snd x) 1) then \\y -> ((fst x) y) else \\z -> (iszero ((z +1) * 3))";
+    let args = Args::parse();
+    let config = Config {
+        generation: match args.strategy {
+            Strategy::AlwaysFresh => Generation::AlwaysFresh,
+            Strategy::ReuseStructure => Generation::ReuseStructure,
+            Strategy::CourseConvention => Generation::CourseConvention,
+        },
+        strategy: match args.order {
+            Order::Alternate => SolverStrategy::Alternate,
+            Order::RemoveFirst => SolverStrategy::RemoveFirst,
+            Order::AccumulateFirst => SolverStrategy::AccumulateFirst,
+            Order::LowestVariable => SolverStrategy::LowestVariable,
+        },
+        ..Config::default()
+    };
+
+    if let Some(path) = &args.grade_tree {
+        match grade_tree_with_config(&args.expression, &read(path), &config) {
+            Ok(grade) => match grade.mistake {
+                Some(mistake) => println!(
+                    "line {}: {:?}: {}",
+                    mistake.line, mistake.kind, mistake.message
+                ),
+                None => println!("correct"),
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = &args.grade {
+        match grade_with_config(
+            &args.expression,
+            &read(path),
+            args.final_type.clone(),
+            &config,
+        ) {
+            Ok(grade) => println!("{}", grade),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let run = match run_pipeline(&args.expression, &config) {
+        Ok(run) => run,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match args.format {
+        Format::Text => {
+            // The terminal width is only known if the shell exports it
+            let width = args
+                .width
+                .or_else(|| std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()));
+            let options = TextOptions {
+                max_width: Some(width.unwrap_or(100)),
+                ascii: args.ascii,
+            };
+            println!("{}", render_tree(&run.tree, &options));
+            println!("Constraints:");
+            let nodes = run.tree.preorder();
+            for ((a, b), origin) in run.constraints.iter().zip(&run.origins) {
+                println!(
+                    "  {} = {}  from [{}] {}",
+                    a, b, origin.rule, nodes[origin.node].expr.0
+                );
+            }
+            match &run.solution.result {
+                Some(Ok(rule)) => {
+                    println!("\nResult: {}", rule);
+                    match run.solution.verify(&run.constraints) {
+                        Ok(()) => println!("Verified: every constraint holds"),
+                        Err(e) => println!("Verification failed: {}", e),
+                    }
+                    if let Ok(found) = holes::find_holes(&run.tree, &run.solution) {
+                        for hole in &found.holes {
+                            let gamma: Vec<String> = hole
+                                .gamma
+                                .iter()
+                                .map(|(var, typ)| format!("{}: {}", var, typ))
+                                .collect();
+                            println!(
+                                "Hole _{} :: {}  with {}",
+                                hole.name,
+                                hole.typ,
+                                gamma.join(", ")
+                            );
+                        }
+                    }
+                }
+                Some(Err(e)) => {
+                    println!("\nError: {}", e);
+                    if let Some(slice) =
+                        slicing::slice_error(&run.tree, &run.constraints, &run.origins)
+                    {
+                        println!("These constraints contradict each other:");
+                        for ((a, b), expr) in slice.constraints.iter().zip(&slice.expressions) {
+                            match expr {
+                                Some(expr) => println!("  {} = {}  from {}", a, b, expr),
+                                None => println!("  {} = {}", a, b),
+                            }
+                        }
+                    }
+                }
+                None => (),
+            }
+        }
+        Format::Latex => print!(
+            "{}",
+            latex::document(&run.ast, &run.tree, &run.constraints, &run.solution)
+        ),
+        Format::Typst => print!(
+            "{}",
+            typst::document(&run.ast, &run.tree, &run.constraints, &run.solution)
+        ),
+        Format::Mathjax => println!("{}", run.tree.to_mathjax()),
+        Format::Json => match export(&args.expression, &config).and_then(|export| {
+            if args.pretty {
+                export.to_json_pretty(&config)
+            } else {
+                export.to_json(&config)
+            }
+        }) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        Format::DotAst => print!("{}", run.ast.to_dot()),
+        Format::DotTree => print!("{}", run.tree.to_dot()),
+        Format::DotConstraints => print!(
+            "{}",
+            dot::constraint_graph(&run.solution.simplified_rules(), 0)
+        ),
+        Format::Orders => match compare_strategies_with_config(&args.expression, &config) {
+            Ok(comparison) => {
+                for run in &comparison.runs {
+                    println!("{}:", run.strategy);
+                    for (i, step) in run.steps.iter().enumerate() {
+                        let marker = if run.first_difference == Some(i) {
+                            ">"
+                        } else {
+                            " "
+                        };
+                        println!("{} {:>3}. {}", marker, i + 1, step.replace('\n', " "));
+                    }
+                    match &run.result {
+                        Some(Ok(rule)) => println!("Result: {}\n", rule),
+                        Some(Err(e)) => println!("Error: {}\n", e),
+                        None => println!(),
+                    }
+                }
+                if !comparison.same_result {
+                    println!("The orders give different results!");
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        Format::Engines => match compare_engines_with_config(&args.expression, &config) {
+            Ok(comparison) => {
+                for run in &comparison.runs {
+                    println!("{}:", run.engine);
+                    for step in &run.trace {
+                        println!("{}{}", "  ".repeat(step.depth + 1), step.text);
+                    }
+                    match &run.result {
+                        Ok(typ) => println!("Result: {}\n", typ),
+                        Err(e) => println!("Error: {}\n", e),
+                    }
+                }
+                if !comparison.agree {
+                    println!("The engines disagree!");
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+    }
+}
 
-    let _parsed = solve(input);
+// reads a file given on the command line, exits if it cannot be read
+fn read(path: &str) -> String {
+    match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
 }
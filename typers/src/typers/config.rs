@@ -0,0 +1,115 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Caps on the resources a single run of the pipeline may use, and the options of the run. Every `_with_config` function takes one.
+/// Exceeding a cap aborts the run with an error as soon as it happens, instead of freezing the browser tab or overflowing the stack.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Maximum number of characters of the input expression
+    pub max_input_length: usize,
+    /// Maximum nesting depth of the expression, this bounds the recursion of the parser and the tree building
+    pub max_ast_depth: usize,
+    /// Maximum number of type variables created while building the derivation tree
+    pub max_type_vars: usize,
+    /// Maximum number of constraints, both while building the tree and while solving
+    pub max_constraints: usize,
+    /// Maximum number of steps the solver may take
+    pub max_solver_steps: usize,
+    /// Maximum number of bytes of a JSON export, the derivation tree repeats the subexpression at every node
+    pub max_output_length: usize,
+    /// Whether the solver records its steps, without them the much faster union-find solver is used
+    pub record_steps: bool,
+    /// How the derivation tree introduces type variables, this decides the numbers of the variables
+    pub generation: Generation,
+    /// In which order the solver simplifies the rules, this only changes the steps and not the result
+    pub strategy: SolverStrategy,
+}
+
+/// How the rules for abstractions and tuples introduce type variables while building the derivation tree.
+/// All strategies number the variables depth first from left to right, in the order the rules are applied,
+/// so the same expression always gives the same tree.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Generation {
+    /// Every abstraction and tuple gets fresh variables and a constraint, even if its type already has the right form
+    AlwaysFresh,
+    /// An abstraction or tuple whose type already is a function or a tuple uses its parts instead of fresh variables
+    #[default]
+    ReuseStructure,
+    /// Like `ReuseStructure`, but the conclusion of a tuple keeps the type its parent expects, like every other rule does,
+    /// which is how the trees of the exercise solutions are written
+    CourseConvention,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_input_length: 20_000,
+            max_ast_depth: 500,
+            max_type_vars: 10_000,
+            max_constraints: 2_000,
+            max_solver_steps: 10_000,
+            max_output_length: 50_000_000,
+            record_steps: true,
+            generation: Generation::ReuseStructure,
+            strategy: SolverStrategy::Alternate,
+        }
+    }
+}
+
+/// Which step the solver takes next while simplifying the rules.
+/// Every strategy keeps simplifying until no two rules have the same left hand side and no rule has the form `tX = tY`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SolverStrategy {
+    /// Compare two rules, then remove a simple rule and repeat, falling back to the other kind of step when one is not possible
+    #[default]
+    Alternate,
+    /// Remove all rules of the form `tX = tY` before comparing any rules
+    RemoveFirst,
+    /// Compare all rules with the same left hand side before removing any rule
+    AccumulateFirst,
+    /// Work on the rules of the variable with the lowest number first, removing before comparing
+    LowestVariable,
+}
+
+#[wasm_bindgen]
+impl Config {
+    /// Creates a configuration with the default limits.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Config {
+        Config::default()
+    }
+}
+
+impl Config {
+    /// Checks that the input is short enough and that its brackets are not nested too deeply.
+    /// Every compound expression needs its own pair of brackets, so the bracket depth bounds the depth of the AST.
+    pub fn check_input(&self, input: &str) -> Result<(), String> {
+        let length = input.chars().count();
+        if length > self.max_input_length {
+            return Err(format!(
+                "the input has {} characters, but at most {} are allowed",
+                length, self.max_input_length
+            ));
+        }
+
+        let mut depth = 0usize;
+        for c in input.chars() {
+            match c {
+                '(' => {
+                    depth += 1;
+                    if depth > self.max_ast_depth {
+                        return Err(format!(
+                            "the expression is nested more than {} levels deep",
+                            self.max_ast_depth
+                        ));
+                    }
+                }
+                ')' => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,575 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use tsify::Tsify;
+
+use crate::typers::{
+    config::Config, parser::AstNode, rules::TypeExpr, solver, tree::TypeInference, unify::Unifier,
+    utils::mathjax::MathJax, validate::apply_substitution,
+};
+
+/// A line of the explanation of an engine, indented by the depth of the subexpression it is about
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct TraceStep {
+    pub depth: usize,
+    /// The explanation, formatted for MathJax
+    pub text: String,
+}
+
+/// What an engine found out about an expression
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct EngineRun {
+    /// The name of the engine
+    pub engine: String,
+    /// The type of the expression, or why it has none
+    pub result: Result<TypeExpr, String>,
+    /// How the engine arrived at the result
+    pub trace: Vec<TraceStep>,
+}
+
+/// An algorithm which infers the type of an expression and explains how it did so
+pub trait Engine {
+    /// The name shown above the trace
+    fn name(&self) -> &'static str;
+    /// Infers the type of the expression within the limits of the `Config`
+    fn run(&self, ast: &AstNode, config: &Config) -> EngineRun;
+}
+
+/// Builds the derivation tree, collects its constraints and solves them step by step, like the web interface does
+pub struct ConstraintEngine;
+
+/// Algorithm W, which infers the types of the subexpressions bottom up and returns a substitution with every type
+pub struct AlgorithmW;
+
+/// Algorithm J, which works like Algorithm W, but collects all equations in one global union-find structure instead of composing substitutions
+pub struct AlgorithmJ;
+
+/// Algorithm M, which passes the expected type of every subexpression top down, so a mistake is found at the first subexpression which contradicts it
+pub struct AlgorithmM;
+
+/// All engines, in the order they are shown side by side
+pub fn engines() -> Vec<Box<dyn Engine>> {
+    vec![
+        Box::new(ConstraintEngine),
+        Box::new(AlgorithmW),
+        Box::new(AlgorithmJ),
+        Box::new(AlgorithmM),
+    ]
+}
+
+/// The runs of all engines on the same expression
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct Comparison {
+    pub runs: Vec<EngineRun>,
+    /// Whether all engines found the same type up to renaming of its variables, or none of them found a type
+    pub agree: bool,
+}
+
+/// Runs all engines on the expression
+pub fn compare_engines(ast: &AstNode, config: &Config) -> Comparison {
+    let runs: Vec<EngineRun> = engines()
+        .iter()
+        .map(|engine| engine.run(ast, config))
+        .collect();
+    let types: Vec<Option<TypeExpr>> = runs
+        .iter()
+        .map(|run| run.result.as_ref().ok().map(canonical))
+        .collect();
+    let agree = types.windows(2).all(|pair| pair[0] == pair[1]);
+    Comparison { runs, agree }
+}
+
+/// Renames the variables of the type to `t0`, `t1`, ... in the order they appear,
+/// so the types found by different engines can be compared
+pub fn canonical(typ: &TypeExpr) -> TypeExpr {
+    // collects the variables from left to right
+    fn collect(typ: &TypeExpr, names: &mut HashMap<usize, TypeExpr>) {
+        match typ {
+            TypeExpr::Function(a, b) | TypeExpr::Tuple(a, b) => {
+                collect(a, names);
+                collect(b, names);
+            }
+            TypeExpr::Var(x) => {
+                let next = names.len();
+                names.entry(*x).or_insert(TypeExpr::Var(next));
+            }
+            TypeExpr::Bool | TypeExpr::Int => (),
+        }
+    }
+    let mut names = HashMap::new();
+    collect(typ, &mut names);
+    apply_substitution(typ, &names)
+}
+
+impl Engine for ConstraintEngine {
+    fn name(&self) -> &'static str {
+        "Constraint solver"
+    }
+
+    fn run(&self, ast: &AstNode, config: &Config) -> EngineRun {
+        let mut trace = vec![];
+        let result =
+            TypeInference::infer_with_config(ast.clone(), config).and_then(|(_, constraints)| {
+                for (a, b) in constraints.iter().filter(|(a, b)| a != b) {
+                    trace.push(TraceStep {
+                        depth: 0,
+                        text: format!("Constraint \\({} = {}\\)", a.to_mathjax(), b.to_mathjax()),
+                    });
+                }
+                let solution = solver::solve_equations_with_config(constraints, 0, config);
+                trace.extend(solution.steps().iter().map(|step| TraceStep {
+                    depth: 0,
+                    text: step.describe(),
+                }));
+
+                match solution.result {
+                    Some(Ok(rule)) => Ok(*rule.rhs),
+                    Some(Err(e)) => Err(e),
+                    None => Err("the solver did not finish".to_string()),
+                }
+            });
+        EngineRun {
+            engine: self.name().to_string(),
+            result,
+            trace,
+        }
+    }
+}
+
+/// The state all algorithms share: fresh variables, the trace and the limits
+struct Run<'a> {
+    config: &'a Config,
+    next_var: usize,
+    steps: usize,
+    trace: Vec<TraceStep>,
+}
+
+impl<'a> Run<'a> {
+    fn new(config: &'a Config) -> Self {
+        Run {
+            config,
+            next_var: 0,
+            steps: 0,
+            trace: vec![],
+        }
+    }
+
+    fn fresh(&mut self) -> Result<TypeExpr, String> {
+        if self.next_var >= self.config.max_type_vars {
+            return Err(format!(
+                "the algorithm needs more than {} type variables",
+                self.config.max_type_vars
+            ));
+        }
+        self.next_var += 1;
+        Ok(TypeExpr::Var(self.next_var - 1))
+    }
+
+    fn explain(&mut self, depth: usize, text: String) -> Result<(), String> {
+        self.steps += 1;
+        if self.steps > self.config.max_solver_steps {
+            return Err(format!(
+                "the algorithm did not finish within {} steps, aborting ...",
+                self.config.max_solver_steps
+            ));
+        }
+        self.trace.push(TraceStep { depth, text });
+        Ok(())
+    }
+
+    fn check_depth(&self, depth: usize) -> Result<(), String> {
+        if depth > self.config.max_ast_depth {
+            return Err(format!(
+                "the expression is nested more than {} levels deep",
+                self.config.max_ast_depth
+            ));
+        }
+        Ok(())
+    }
+
+    fn finish(self, engine: &dyn Engine, result: Result<TypeExpr, String>) -> EngineRun {
+        EngineRun {
+            engine: engine.name().to_string(),
+            result,
+            trace: self.trace,
+        }
+    }
+}
+
+/// What the algorithms learned about the type variables so far
+trait Knowledge {
+    /// Adds the equation `a = b`, returns an explanation of what was learned
+    fn unify(&mut self, a: &TypeExpr, b: &TypeExpr) -> Result<String, String>;
+    /// The type with everything known about its variables filled in
+    fn apply(&mut self, typ: &TypeExpr) -> Result<TypeExpr, String>;
+}
+
+/// An idempotent substitution, extended with the most general unifier of every equation
+#[derive(Default)]
+struct Substitution(HashMap<usize, TypeExpr>);
+
+impl Knowledge for Substitution {
+    fn unify(&mut self, a: &TypeExpr, b: &TypeExpr) -> Result<String, String> {
+        let (a, b) = (self.apply(a)?, self.apply(b)?);
+        let mut unifier: Vec<(usize, TypeExpr)> = vec![];
+        let mut work = vec![(a.clone(), b.clone())];
+        while let Some((x, y)) = work.pop() {
+            let known: HashMap<usize, TypeExpr> = unifier.iter().cloned().collect();
+            match (
+                apply_substitution(&x, &known),
+                apply_substitution(&y, &known),
+            ) {
+                (TypeExpr::Var(x), TypeExpr::Var(y)) if x == y => (),
+                (TypeExpr::Var(x), other) | (other, TypeExpr::Var(x)) => {
+                    if other.all_vars().contains(&x) {
+                        return Err(format!(
+                            "\\(t_{{{}}}\\) occurs in \\({}\\), so the type would have to contain itself",
+                            x,
+                            other.to_mathjax()
+                        ));
+                    }
+                    let binding = HashMap::from([(x, other.clone())]);
+                    for (_, typ) in unifier.iter_mut() {
+                        *typ = apply_substitution(typ, &binding);
+                    }
+                    unifier.push((x, other));
+                }
+                (TypeExpr::Function(a1, a2), TypeExpr::Function(b1, b2))
+                | (TypeExpr::Tuple(a1, a2), TypeExpr::Tuple(b1, b2)) => {
+                    work.push((*a2, *b2));
+                    work.push((*a1, *b1));
+                }
+                (TypeExpr::Int, TypeExpr::Int) | (TypeExpr::Bool, TypeExpr::Bool) => (),
+                (x, y) => {
+                    return Err(format!(
+                        "impossible to unify \\({}\\) and \\({}\\)",
+                        x.to_mathjax(),
+                        y.to_mathjax()
+                    ))
+                }
+            }
+        }
+
+        // compose the unifier with the substitution so far, it stays idempotent
+        let unifier_map: HashMap<usize, TypeExpr> = unifier.iter().cloned().collect();
+        for typ in self.0.values_mut() {
+            *typ = apply_substitution(typ, &unifier_map);
+        }
+        self.0.extend(unifier_map);
+
+        let bindings: Vec<String> = unifier
+            .iter()
+            .map(|(x, typ)| format!("t_{{{}}} \\mapsto {}", x, typ.to_mathjax()))
+            .collect();
+        Ok(format!(
+            "unify \\({}\\) with \\({}\\): \\([{}]\\)",
+            a.to_mathjax(),
+            b.to_mathjax(),
+            bindings.join(", ")
+        ))
+    }
+
+    fn apply(&mut self, typ: &TypeExpr) -> Result<TypeExpr, String> {
+        Ok(apply_substitution(typ, &self.0))
+    }
+}
+
+impl Knowledge for Unifier {
+    fn unify(&mut self, a: &TypeExpr, b: &TypeExpr) -> Result<String, String> {
+        Unifier::unify(self, a, b)?;
+        // resolving types only terminates without cycles, so they are rejected right away
+        self.check_cycles()?;
+        Ok(format!(
+            "unify \\({}\\) with \\({}\\)",
+            a.to_mathjax(),
+            b.to_mathjax()
+        ))
+    }
+
+    fn apply(&mut self, typ: &TypeExpr) -> Result<TypeExpr, String> {
+        self.resolve(typ)
+    }
+}
+
+/// A unit of work of the bottom up algorithms, the explicit stack replaces recursion
+enum Task<'a> {
+    // infer the type of the subexpression in the context
+    Enter(&'a AstNode, HashMap<String, TypeExpr>, usize),
+    // the types of all subexpressions are known, combine them, an abstraction remembers the type of its parameter
+    Exit(&'a AstNode, Option<TypeExpr>, usize),
+}
+
+// Infers the type bottom up, the types of the subexpressions are combined once all of them are known.
+// The knowledge is shared by all subexpressions, which is what composing the substitutions in Algorithm W amounts to.
+fn bottom_up<K: Knowledge>(
+    name: &str,
+    ast: &AstNode,
+    knowledge: &mut K,
+    run: &mut Run,
+) -> Result<TypeExpr, String> {
+    let mut tasks = vec![Task::Enter(ast, HashMap::new(), 0)];
+    let mut types: Vec<TypeExpr> = vec![];
+    while let Some(task) = tasks.pop() {
+        match task {
+            Task::Enter(ast, gamma, depth) => {
+                run.check_depth(depth)?;
+                let typ = match ast {
+                    AstNode::Var(var) => match gamma.get(var) {
+                        Some(typ) => knowledge.apply(typ)?,
+                        None => return Err(format!("{} not found!", var)),
+                    },
+                    AstNode::Int(_) => TypeExpr::Int,
+                    AstNode::True | AstNode::False => TypeExpr::Bool,
+                    // a hole can have any type
+                    AstNode::Hole(_) => run.fresh()?,
+                    AstNode::Abs { var, body } => {
+                        let param = run.fresh()?;
+                        let mut inner = gamma;
+                        inner.insert(var.clone(), param.clone());
+                        tasks.push(Task::Exit(ast, Some(param), depth));
+                        tasks.push(Task::Enter(body, inner, depth + 1));
+                        continue;
+                    }
+                    _ => {
+                        tasks.push(Task::Exit(ast, None, depth));
+                        for child in ast.children().into_iter().rev() {
+                            tasks.push(Task::Enter(child, gamma.clone(), depth + 1));
+                        }
+                        continue;
+                    }
+                };
+                run.explain(
+                    depth,
+                    format!(
+                        "{}: \\({} : {}\\)",
+                        name,
+                        ast.to_mathjax(),
+                        typ.to_mathjax()
+                    ),
+                )?;
+                types.push(typ);
+            }
+            Task::Exit(ast, param, depth) => {
+                let children = types.split_off(types.len() - ast.children().len());
+                let mut unify = |a: &TypeExpr, b: &TypeExpr, run: &mut Run| -> Result<(), String> {
+                    let text = knowledge
+                        .unify(a, b)
+                        .map_err(|e| format!("\\({}\\): {}", ast.to_mathjax(), e))?;
+                    run.explain(depth + 1, text)
+                };
+                let typ = match ast {
+                    AstNode::Abs { .. } => {
+                        let param = param.ok_or_else(|| {
+                            format!("the parameter of \\({}\\) has no type", ast.to_mathjax())
+                        })?;
+                        TypeExpr::Function(Box::new(param), Box::new(children[0].clone()))
+                    }
+                    AstNode::App { .. } => {
+                        let result = run.fresh()?;
+                        let function = TypeExpr::Function(
+                            Box::new(children[1].clone()),
+                            Box::new(result.clone()),
+                        );
+                        unify(&children[0], &function, run)?;
+                        result
+                    }
+                    AstNode::IsZero(_) => {
+                        unify(&children[0], &TypeExpr::Int, run)?;
+                        TypeExpr::Bool
+                    }
+                    AstNode::BinOp { .. } => {
+                        unify(&children[0], &TypeExpr::Int, run)?;
+                        unify(&children[1], &TypeExpr::Int, run)?;
+                        TypeExpr::Int
+                    }
+                    AstNode::IfThenElse { .. } => {
+                        unify(&children[0], &TypeExpr::Bool, run)?;
+                        unify(&children[1], &children[2], run)?;
+                        children[1].clone()
+                    }
+                    AstNode::Tuple { .. } => TypeExpr::Tuple(
+                        Box::new(children[0].clone()),
+                        Box::new(children[1].clone()),
+                    ),
+                    AstNode::Fst(_) | AstNode::Snd(_) => {
+                        let first = run.fresh()?;
+                        let second = run.fresh()?;
+                        let tuple =
+                            TypeExpr::Tuple(Box::new(first.clone()), Box::new(second.clone()));
+                        unify(&children[0], &tuple, run)?;
+                        match ast {
+                            AstNode::Fst(_) => first,
+                            _ => second,
+                        }
+                    }
+                    AstNode::Var(_)
+                    | AstNode::Int(_)
+                    | AstNode::True
+                    | AstNode::False
+                    | AstNode::Hole(_) => return Err("leaves have no premises".to_string()),
+                };
+                let typ = knowledge.apply(&typ)?;
+                run.explain(
+                    depth,
+                    format!(
+                        "{}: \\({} : {}\\)",
+                        name,
+                        ast.to_mathjax(),
+                        typ.to_mathjax()
+                    ),
+                )?;
+                types.push(typ);
+            }
+        }
+    }
+    let typ = types.pop().ok_or("no type was inferred".to_string())?;
+    knowledge.apply(&typ)
+}
+
+impl Engine for AlgorithmW {
+    fn name(&self) -> &'static str {
+        "Algorithm W"
+    }
+
+    fn run(&self, ast: &AstNode, config: &Config) -> EngineRun {
+        let mut run = Run::new(config);
+        let result = bottom_up(
+            "\\(\\mathcal{W}\\)",
+            ast,
+            &mut Substitution::default(),
+            &mut run,
+        );
+        run.finish(self, result)
+    }
+}
+
+impl Engine for AlgorithmJ {
+    fn name(&self) -> &'static str {
+        "Algorithm J"
+    }
+
+    fn run(&self, ast: &AstNode, config: &Config) -> EngineRun {
+        let mut run = Run::new(config);
+        let result = bottom_up("\\(\\mathcal{J}\\)", ast, &mut Unifier::default(), &mut run);
+        run.finish(self, result)
+    }
+}
+
+impl Engine for AlgorithmM {
+    fn name(&self) -> &'static str {
+        "Algorithm M"
+    }
+
+    fn run(&self, ast: &AstNode, config: &Config) -> EngineRun {
+        let mut run = Run::new(config);
+        let result = top_down(ast, &mut Substitution::default(), &mut run);
+        run.finish(self, result)
+    }
+}
+
+// Infers the type top down: every subexpression gets the type it is expected to have and unifies it with what it is.
+// All work happens before the subexpressions are visited, so the tasks only need to enter them.
+fn top_down(
+    ast: &AstNode,
+    knowledge: &mut Substitution,
+    run: &mut Run,
+) -> Result<TypeExpr, String> {
+    let goal = run.fresh()?;
+    let mut tasks = vec![(ast, HashMap::<String, TypeExpr>::new(), goal.clone(), 0)];
+    while let Some((ast, gamma, expected, depth)) = tasks.pop() {
+        run.check_depth(depth)?;
+        run.explain(
+            depth,
+            format!(
+                "\\(\\mathcal{{M}}\\): \\({}\\) is expected to have the type \\({}\\)",
+                ast.to_mathjax(),
+                knowledge.apply(&expected)?.to_mathjax()
+            ),
+        )?;
+        let mut unify = |a: &TypeExpr, b: &TypeExpr, run: &mut Run| -> Result<(), String> {
+            let text = knowledge
+                .unify(a, b)
+                .map_err(|e| format!("\\({}\\): {}", ast.to_mathjax(), e))?;
+            run.explain(depth + 1, text)
+        };
+        // the premises with the types they are expected to have, from left to right
+        let premises: Vec<(&AstNode, HashMap<String, TypeExpr>, TypeExpr)> = match ast {
+            AstNode::Var(var) => {
+                let typ = gamma.get(var).ok_or(format!("{} not found!", var))?;
+                unify(&expected, typ, run)?;
+                vec![]
+            }
+            AstNode::Int(_) => {
+                unify(&expected, &TypeExpr::Int, run)?;
+                vec![]
+            }
+            AstNode::True | AstNode::False => {
+                unify(&expected, &TypeExpr::Bool, run)?;
+                vec![]
+            }
+            // a hole accepts whatever type is expected
+            AstNode::Hole(_) => vec![],
+            AstNode::Abs { var, body } => {
+                let param = run.fresh()?;
+                let result = run.fresh()?;
+                let function =
+                    TypeExpr::Function(Box::new(param.clone()), Box::new(result.clone()));
+                unify(&expected, &function, run)?;
+                let mut inner = gamma.clone();
+                inner.insert(var.clone(), param);
+                vec![(body.as_ref(), inner, result)]
+            }
+            AstNode::App { fun, arg } => {
+                let param = run.fresh()?;
+                let function =
+                    TypeExpr::Function(Box::new(param.clone()), Box::new(expected.clone()));
+                vec![
+                    (fun.as_ref(), gamma.clone(), function),
+                    (arg.as_ref(), gamma.clone(), param),
+                ]
+            }
+            AstNode::IsZero(expr) => {
+                unify(&expected, &TypeExpr::Bool, run)?;
+                vec![(expr.as_ref(), gamma.clone(), TypeExpr::Int)]
+            }
+            AstNode::BinOp { lhs, rhs, .. } => {
+                unify(&expected, &TypeExpr::Int, run)?;
+                vec![
+                    (lhs.as_ref(), gamma.clone(), TypeExpr::Int),
+                    (rhs.as_ref(), gamma.clone(), TypeExpr::Int),
+                ]
+            }
+            AstNode::IfThenElse { cond, then, else_ } => vec![
+                (cond.as_ref(), gamma.clone(), TypeExpr::Bool),
+                (then.as_ref(), gamma.clone(), expected.clone()),
+                (else_.as_ref(), gamma.clone(), expected.clone()),
+            ],
+            AstNode::Tuple { fst, snd } => {
+                let first = run.fresh()?;
+                let second = run.fresh()?;
+                let tuple = TypeExpr::Tuple(Box::new(first.clone()), Box::new(second.clone()));
+                unify(&expected, &tuple, run)?;
+                vec![
+                    (fst.as_ref(), gamma.clone(), first),
+                    (snd.as_ref(), gamma.clone(), second),
+                ]
+            }
+            AstNode::Fst(expr) | AstNode::Snd(expr) => {
+                let other = run.fresh()?;
+                let tuple = match ast {
+                    AstNode::Fst(_) => TypeExpr::Tuple(Box::new(expected.clone()), Box::new(other)),
+                    _ => TypeExpr::Tuple(Box::new(other), Box::new(expected.clone())),
+                };
+                vec![(expr.as_ref(), gamma.clone(), tuple)]
+            }
+        };
+        tasks.extend(
+            premises
+                .into_iter()
+                .rev()
+                .map(|(ast, gamma, expected)| (ast, gamma, expected, depth + 1)),
+        );
+    }
+    knowledge.apply(&goal)
+}
@@ -0,0 +1,624 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use tsify::Tsify;
+
+use super::{
+    parser::AstNode,
+    rules::TypeExpr,
+    tree::Tree,
+    type_parser::Derivation,
+    utils::mathjax::{annotated_tree, Annotations, MathJax},
+};
+
+/// The result of comparing a student's constraints and final type with the expected ones.
+/// The student may name the type variables differently, constraints are compared after renaming them.
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct Grade {
+    /// Constraints of the student which are expected, together with the expected constraint they match
+    pub correct: Vec<((TypeExpr, TypeExpr), (TypeExpr, TypeExpr))>,
+    /// Constraints of the student about the same variables as an expected constraint, but which state something else
+    pub wrong: Vec<((TypeExpr, TypeExpr), (TypeExpr, TypeExpr))>,
+    /// Expected constraints the student did not give
+    pub missing: Vec<(TypeExpr, TypeExpr)>,
+    /// Constraints of the student which do not belong to any expected constraint
+    pub extra: Vec<(TypeExpr, TypeExpr)>,
+    /// The renaming of the student's type variables to the expected ones
+    pub renaming: BTreeMap<usize, usize>,
+    /// The type of the expression, `None` if it has no type
+    pub expected_type: Option<TypeExpr>,
+    /// Whether the final type of the student is the expected one up to renaming, or both agree that there is no type
+    pub type_correct: bool,
+}
+
+impl Grade {
+    /// Whether everything the student submitted is correct
+    pub fn is_correct(&self) -> bool {
+        self.wrong.is_empty()
+            && self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.type_correct
+    }
+}
+
+/// A bijection between the type variables of the student and the expected ones
+#[derive(Debug, Clone, Default)]
+struct Renaming {
+    forward: HashMap<usize, usize>,
+    backward: HashMap<usize, usize>,
+}
+
+impl Renaming {
+    /// The variables which have to be added, so each type of the student becomes the expected one it is paired with.
+    /// Returns `None` if the types differ in more than the names of their variables.
+    fn extension<'a>(
+        &self,
+        mut stack: Vec<(&'a TypeExpr, &'a TypeExpr)>,
+    ) -> Option<Vec<(usize, usize)>> {
+        let mut added = Renaming::default();
+        while let Some(pair) = stack.pop() {
+            match pair {
+                (TypeExpr::Var(x), TypeExpr::Var(y)) => {
+                    let forward = self.forward.get(x).or_else(|| added.forward.get(x));
+                    let backward = self.backward.get(y).or_else(|| added.backward.get(y));
+                    match (forward, backward) {
+                        (Some(a), Some(b)) if a == y && b == x => (),
+                        (None, None) => {
+                            added.forward.insert(*x, *y);
+                            added.backward.insert(*y, *x);
+                        }
+                        _ => return None,
+                    }
+                }
+                (TypeExpr::Function(a, b), TypeExpr::Function(c, d))
+                | (TypeExpr::Tuple(a, b), TypeExpr::Tuple(c, d)) => {
+                    stack.push((b, d));
+                    stack.push((a, c));
+                }
+                (TypeExpr::Int, TypeExpr::Int) | (TypeExpr::Bool, TypeExpr::Bool) => (),
+                _ => return None,
+            }
+        }
+        let mut added: Vec<(usize, usize)> = added.forward.into_iter().collect();
+        added.sort();
+        Some(added)
+    }
+
+    /// Like `extension`, but a constraint may also be written the other way around, so there can be two ways to match it
+    fn constraint_extensions(
+        &self,
+        (a, b): &(TypeExpr, TypeExpr),
+        (c, d): &(TypeExpr, TypeExpr),
+    ) -> Vec<Vec<(usize, usize)>> {
+        let mut result = vec![];
+        for pairs in [vec![(a, c), (b, d)], vec![(a, d), (b, c)]] {
+            if let Some(added) = self.extension(pairs) {
+                if !result.contains(&added) {
+                    result.push(added);
+                }
+            }
+        }
+        result
+    }
+
+    fn extend(&mut self, added: &[(usize, usize)]) {
+        for (x, y) in added {
+            self.forward.insert(*x, *y);
+            self.backward.insert(*y, *x);
+        }
+    }
+}
+
+// the shape of a type without the names of its variables, types which differ in their shape can never match
+fn shape(typ: &TypeExpr) -> String {
+    match typ {
+        TypeExpr::Function(a, b) => format!("({} -> {})", shape(a), shape(b)),
+        TypeExpr::Tuple(a, b) => format!("({}, {})", shape(a), shape(b)),
+        TypeExpr::Var(_) => "t".to_string(),
+        TypeExpr::Bool => "Bool".to_string(),
+        TypeExpr::Int => "Int".to_string(),
+    }
+}
+
+// the shape of a constraint, independent of the order of its sides
+fn constraint_shape((a, b): &(TypeExpr, TypeExpr)) -> (String, String) {
+    let (a, b) = (shape(a), shape(b));
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+// drops trivial constraints like `t1 = t1` and constraints which appear twice, possibly the other way around
+fn normalize(constraints: &[(TypeExpr, TypeExpr)]) -> Vec<(TypeExpr, TypeExpr)> {
+    let mut result: Vec<(TypeExpr, TypeExpr)> = vec![];
+    for (a, b) in constraints {
+        let duplicate = result
+            .iter()
+            .any(|(c, d)| (a == c && b == d) || (a == d && b == c));
+        if a != b && !duplicate {
+            result.push((a.clone(), b.clone()));
+        }
+    }
+    result
+}
+
+/// Number of pairs of constraints the search for the best renaming may compare, after that the best matching found so far is used
+const SEARCH_BUDGET: usize = 1_000_000;
+
+// A student constraint together with the variables matching it adds to the renaming, `None` leaves the expected constraint unmatched
+type Choice = Option<(usize, Vec<(usize, usize)>)>;
+
+// One expected constraint during the search: the student constraints it can be matched to and the next one to try.
+struct Frame {
+    choices: Vec<Choice>,
+    next: usize,
+}
+
+// Searches the renaming under which the most expected constraints match a constraint of the student.
+// It is a depth first search over the candidates of each expected constraint, the first path it takes is the greedy matching.
+// Branches which cannot match more constraints than the best matching so far are skipped, so a complete match ends the search at once.
+fn match_constraints(
+    expected: &[(TypeExpr, TypeExpr)],
+    student: &[(TypeExpr, TypeExpr)],
+) -> (Renaming, Vec<Option<usize>>) {
+    // only constraints of the same shape are candidates for each other
+    let mut by_shape = HashMap::<(String, String), Vec<usize>>::new();
+    for (j, constraint) in student.iter().enumerate() {
+        by_shape
+            .entry(constraint_shape(constraint))
+            .or_default()
+            .push(j);
+    }
+    let candidates: Vec<Vec<usize>> = expected
+        .iter()
+        .map(|constraint| {
+            by_shape
+                .get(&constraint_shape(constraint))
+                .cloned()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    // constraints with many variables fix a large part of the renaming at once, so they are decided first
+    let mut order: Vec<usize> = (0..expected.len()).collect();
+    order.sort_by_key(|i| {
+        let (a, b) = &expected[*i];
+        std::cmp::Reverse(a.all_vars().len() + b.all_vars().len())
+    });
+
+    let n = order.len();
+    let mut renaming = Renaming::default();
+    let mut matches: Vec<Option<usize>> = vec![None; expected.len()];
+    let mut used = vec![false; student.len()];
+    let mut matched = 0;
+    let mut best = (0, Renaming::default(), matches.clone());
+    let mut frames: Vec<Frame> = vec![];
+    let mut budget = SEARCH_BUDGET;
+
+    loop {
+        let level = frames.len();
+        if level < n && matched + (n - level) > best.0 {
+            // decide the next expected constraint, the candidates needing the fewest new variables come first
+            let i = order[level];
+            budget = budget.saturating_sub(candidates[i].len());
+            let mut choices: Vec<Choice> = candidates[i]
+                .iter()
+                .filter(|j| !used[**j])
+                .flat_map(|&j| {
+                    renaming
+                        .constraint_extensions(&student[j], &expected[i])
+                        .into_iter()
+                        .map(move |added| Some((j, added)))
+                })
+                .collect();
+            choices.sort_by_key(|choice| choice.as_ref().map_or(0, |(_, added)| added.len()));
+            choices.push(None);
+            frames.push(Frame { choices, next: 0 });
+        } else {
+            if matched > best.0 {
+                best = (matched, renaming.clone(), matches.clone());
+            }
+            // go back to the last expected constraint which has another choice left
+            loop {
+                let level = match frames.len().checked_sub(1) {
+                    Some(level) => level,
+                    None => return (best.1, best.2),
+                };
+                let i = order[level];
+                if let Some(j) = matches[i].take() {
+                    used[j] = false;
+                    matched -= 1;
+                    if let Some(Some((_, added))) =
+                        frames[level].choices.get(frames[level].next - 1)
+                    {
+                        for (x, y) in added {
+                            renaming.forward.remove(x);
+                            renaming.backward.remove(y);
+                        }
+                    }
+                }
+                let frame = &frames[level];
+                let possible = match frame.choices.get(frame.next) {
+                    Some(Some(_)) => matched + (n - level) > best.0,
+                    Some(None) => matched + (n - level - 1) > best.0,
+                    None => false,
+                };
+                if possible && budget > 0 {
+                    break;
+                }
+                frames.pop();
+            }
+        }
+
+        // take the next choice of the current expected constraint
+        let level = frames.len() - 1;
+        let frame = &mut frames[level];
+        if let Some(Some((j, added))) = frame.choices.get(frame.next) {
+            renaming.extend(added);
+            used[*j] = true;
+            matches[order[level]] = Some(*j);
+            matched += 1;
+        }
+        frame.next += 1;
+    }
+}
+
+/// Compares the constraints and the final type of a student with the expected ones.
+/// The renaming is chosen so that as many constraints as possible match, trivial and repeated constraints are ignored on both sides.
+pub fn grade(
+    expected: &[(TypeExpr, TypeExpr)],
+    expected_type: Option<&TypeExpr>,
+    student: &[(TypeExpr, TypeExpr)],
+    student_type: Option<&TypeExpr>,
+) -> Grade {
+    let expected = normalize(expected);
+    let student = normalize(student);
+
+    let (renaming, matches) = match_constraints(&expected, &student);
+    let mut expected_left: Vec<bool> = matches.iter().map(Option::is_none).collect();
+    let mut student_left = vec![true; student.len()];
+    for j in matches.iter().flatten() {
+        student_left[*j] = false;
+    }
+    let mut correct: Vec<(usize, usize)> = matches
+        .iter()
+        .enumerate()
+        .filter_map(|(i, j)| j.map(|j| (j, i)))
+        .collect();
+    correct.sort();
+    let correct = correct
+        .into_iter()
+        .map(|(j, i)| (student[j].clone(), expected[i].clone()))
+        .collect();
+
+    // A remaining constraint of the student is wrong if it talks about the variables of a remaining expected constraint
+    let mut wrong = vec![];
+    let mut extra = vec![];
+    for (j, constraint) in student.iter().enumerate() {
+        if !student_left[j] {
+            continue;
+        }
+        let mut vars = constraint.0.all_vars();
+        vars.extend(constraint.1.all_vars());
+        let renamed: Vec<usize> = vars
+            .iter()
+            .filter_map(|x| renaming.forward.get(x).copied())
+            .collect();
+        let best = (0..expected.len())
+            .filter(|i| expected_left[*i])
+            .map(|i| {
+                let (a, b) = &expected[i];
+                let mut vars = a.all_vars();
+                vars.extend(b.all_vars());
+                (renamed.iter().filter(|x| vars.contains(x)).count(), i)
+            })
+            .filter(|(shared, _)| *shared > 0)
+            // the most shared variables win, on a tie the first expected constraint
+            .max_by_key(|(shared, i)| (*shared, std::cmp::Reverse(*i)));
+        match best {
+            Some((_, i)) => {
+                expected_left[i] = false;
+                wrong.push((constraint.clone(), expected[i].clone()));
+            }
+            None => extra.push(constraint.clone()),
+        }
+    }
+    let missing = expected
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| expected_left[*i])
+        .map(|(_, constraint)| constraint.clone())
+        .collect();
+
+    // the final type is compared on its own, its variables are independent of the constraints
+    let type_correct = match (student_type, expected_type) {
+        (Some(student), Some(expected)) => Renaming::default()
+            .extension(vec![(student, expected)])
+            .is_some(),
+        (None, None) => true,
+        _ => false,
+    };
+
+    Grade {
+        correct,
+        wrong,
+        missing,
+        extra,
+        renaming: renaming.forward.into_iter().collect(),
+        expected_type: expected_type.cloned(),
+        type_correct,
+    }
+}
+
+/// The ways a node of a derivation tree can misapply its rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Tsify)]
+pub enum MistakeKind {
+    /// The root does not derive the expression of the exercise in the empty context
+    WrongConclusion,
+    /// The rule does not belong to the expression of the node
+    WrongRule,
+    /// A context is not the one the rule requires
+    WrongContext,
+    /// A premise is missing or superfluous, about the wrong subexpression or has a type the rule does not allow
+    WrongPremise,
+    /// The type of the conclusion contradicts the rule
+    WrongType,
+}
+
+/// The first node of a derivation tree which misapplies its rule
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct Mistake {
+    /// The number of the node in pre-order, the root is `0`
+    pub node: usize,
+    /// The line of the node in the student's input
+    pub line: usize,
+    pub kind: MistakeKind,
+    /// What is wrong, formatted for MathJax
+    pub message: String,
+}
+
+/// The result of checking a student's derivation tree
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct TreeGrade {
+    /// The first node which misapplies its rule, `None` if the tree is correct
+    pub mistake: Option<Mistake>,
+    /// The tree of the student for MathJax, the node with the mistake is shown in red
+    pub mathjax: String,
+}
+
+// whether the student's name is one of the names of the rule for the expression, binary operations may also be named by their operator
+fn is_rule_of(name: &str, ast: &AstNode) -> bool {
+    let op = match ast {
+        AstNode::BinOp { op, .. } => Some(op.to_string()),
+        _ => None,
+    };
+    name.eq_ignore_ascii_case(&ast.name()) || op.as_deref() == Some(name)
+}
+
+// Checks a single node against the typing rule of its expression, the same rule `TypeInference` applies.
+// Types the rule leaves open are accepted, they are tied together by constraints instead.
+// A concrete type of the conclusion has to be the one the rule gives, unless that one is still a type variable.
+fn check_node(tree: &Tree, rule: &str) -> Option<(MistakeKind, String)> {
+    let ast = tree.expr.0.as_ref();
+    let typ = &tree.expr.1;
+    if !is_rule_of(rule, ast) {
+        return Some((
+            MistakeKind::WrongRule,
+            format!(
+                "the rule {} does not apply to \\({}\\), it needs the rule {}",
+                rule,
+                ast.to_mathjax(),
+                ast.name()
+            ),
+        ));
+    }
+
+    let children = ast.children();
+    let premises = &tree.constraints;
+    if premises.len() != children.len() {
+        return Some((
+            MistakeKind::WrongPremise,
+            format!(
+                "the rule {} has {} premises, but there are {}",
+                ast.name(),
+                children.len(),
+                premises.len()
+            ),
+        ));
+    }
+    for (premise, child) in premises.iter().zip(&children) {
+        if premise.expr.0.as_ref() != child.as_ref() {
+            return Some((
+                MistakeKind::WrongPremise,
+                format!(
+                    "the premise \\({}\\) should be about \\({}\\)",
+                    premise.expr.0.to_mathjax(),
+                    child.to_mathjax()
+                ),
+            ));
+        }
+    }
+
+    // only an abstraction changes the context, it binds its variable to the type of the parameter
+    match ast {
+        AstNode::Var(var) if !tree.gamma.contains_key(var) => {
+            return Some((
+                MistakeKind::WrongContext,
+                format!("\\({}\\) is not bound in the context", var),
+            ));
+        }
+        AstNode::Abs { var, .. } => {
+            let body = &premises[0].gamma;
+            let extended = body.contains_key(var)
+                && body.len() == tree.gamma.len() + usize::from(!tree.gamma.contains_key(var))
+                && tree
+                    .gamma
+                    .iter()
+                    .all(|(k, v)| k == var || body.get(k) == Some(v));
+            if !extended {
+                return Some((
+                    MistakeKind::WrongContext,
+                    format!(
+                        "the context of the premise has to be the context of the conclusion with a type for \\({}\\)",
+                        var
+                    ),
+                ));
+            }
+        }
+        _ => {
+            if premises.iter().any(|premise| premise.gamma != tree.gamma) {
+                return Some((
+                    MistakeKind::WrongContext,
+                    "the premises have to use the context of the conclusion".to_string(),
+                ));
+            }
+        }
+    }
+
+    // the type of the conclusion which the rule determines, a type variable on either side stays open
+    let rule_type = match ast {
+        AstNode::Int(_) | AstNode::BinOp { .. } => Some(TypeExpr::Int),
+        AstNode::True | AstNode::False | AstNode::IsZero(_) => Some(TypeExpr::Bool),
+        AstNode::Var(var) => tree.gamma.get(var).cloned(),
+        AstNode::Abs { var, .. } => premises[0].gamma.get(var).map(|param| {
+            TypeExpr::Function(
+                Box::new(param.clone()),
+                Box::new(premises[0].expr.1.clone()),
+            )
+        }),
+        AstNode::Tuple { .. } => Some(TypeExpr::Tuple(
+            Box::new(premises[0].expr.1.clone()),
+            Box::new(premises[1].expr.1.clone()),
+        )),
+        _ => None,
+    };
+    match rule_type {
+        Some(rule_type)
+            if !matches!(typ, TypeExpr::Var(_))
+                && !matches!(rule_type, TypeExpr::Var(_))
+                && *typ != rule_type =>
+        {
+            return Some((
+                MistakeKind::WrongType,
+                format!(
+                    "\\({}\\) has to have the type \\({}\\) by the rule {}",
+                    ast.to_mathjax(),
+                    rule_type.to_mathjax(),
+                    ast.name()
+                ),
+            ));
+        }
+        _ => (),
+    }
+
+    // the types of the premises which the rule determines
+    let int = TypeExpr::Int;
+    let bool = TypeExpr::Bool;
+    let expected: Vec<Option<TypeExpr>> = match ast {
+        AstNode::App { .. } => vec![
+            Some(TypeExpr::Function(
+                Box::new(premises[1].expr.1.clone()),
+                Box::new(typ.clone()),
+            )),
+            None,
+        ],
+        AstNode::IsZero(_) => vec![Some(int)],
+        AstNode::BinOp { .. } => vec![Some(int.clone()), Some(int)],
+        AstNode::IfThenElse { .. } => vec![Some(bool), Some(typ.clone()), Some(typ.clone())],
+        AstNode::Fst(_) => match &premises[0].expr.1 {
+            TypeExpr::Tuple(first, _) if first.as_ref() == typ => vec![None],
+            _ => {
+                return Some((
+                    MistakeKind::WrongPremise,
+                    format!(
+                        "the premise has to be a tuple with \\({}\\) as its first component",
+                        typ.to_mathjax()
+                    ),
+                ))
+            }
+        },
+        AstNode::Snd(_) => match &premises[0].expr.1 {
+            TypeExpr::Tuple(_, second) if second.as_ref() == typ => vec![None],
+            _ => {
+                return Some((
+                    MistakeKind::WrongPremise,
+                    format!(
+                        "the premise has to be a tuple with \\({}\\) as its second component",
+                        typ.to_mathjax()
+                    ),
+                ))
+            }
+        },
+        _ => vec![],
+    };
+    for (premise, expected) in premises.iter().zip(expected) {
+        match expected {
+            Some(expected) if premise.expr.1 != expected => {
+                return Some((
+                    MistakeKind::WrongPremise,
+                    format!(
+                        "the premise \\({}\\) has to have the type \\({}\\)",
+                        premise.expr.0.to_mathjax(),
+                        expected.to_mathjax()
+                    ),
+                ));
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Checks a student's derivation tree for the expression node by node, in the order the nodes were written.
+/// Every node has to apply the typing rule of its expression, types which the rule leaves open may be chosen freely.
+pub fn check_derivation(expected: &AstNode, derivation: &Derivation) -> TreeGrade {
+    let root = &derivation.tree;
+    let mut mistake = None;
+    if root.expr.0.as_ref() != expected {
+        mistake = Some((
+            0,
+            MistakeKind::WrongConclusion,
+            format!(
+                "the tree has to derive a type for \\({}\\)",
+                expected.to_mathjax()
+            ),
+        ));
+    } else if !root.gamma.is_empty() {
+        mistake = Some((
+            0,
+            MistakeKind::WrongConclusion,
+            "the context of the root has to be empty".to_string(),
+        ));
+    }
+
+    // the nodes are visited in pre-order, like they were parsed
+    let mut stack = vec![root];
+    let mut node = 0;
+    while let (None, Some(tree)) = (&mistake, stack.pop()) {
+        let rule = derivation.rules.get(node).map_or("", String::as_str);
+        if let Some((kind, message)) = check_node(tree, rule) {
+            mistake = Some((node, kind, message));
+        }
+        stack.extend(tree.constraints.iter().rev());
+        node += 1;
+    }
+
+    let mistake = mistake.map(|(node, kind, message)| Mistake {
+        node,
+        line: derivation.lines.get(node).copied().unwrap_or_default(),
+        kind,
+        message,
+    });
+    let mathjax = annotated_tree(
+        root,
+        &Annotations {
+            labels: &derivation.rules,
+            highlight: mistake.as_ref().map(|mistake| mistake.node),
+        },
+    );
+    TreeGrade { mistake, mathjax }
+}
@@ -0,0 +1,54 @@
+use serde::Serialize;
+use tsify::Tsify;
+
+use super::{parser::AstNode, rules::TypeExpr, solver::Solution, tree::Tree};
+
+/// A typed hole `_name` of the expression, with the type it needs and the variables it can use, like GHC reports them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Tsify)]
+pub struct TypedHole {
+    /// The name after the underscore, empty for `_`
+    pub name: String,
+    /// The number of the node in the pre-order of the derivation tree
+    pub node: usize,
+    /// The type the hole needs after solving, variables which are still free can be filled with any type
+    pub typ: TypeExpr,
+    /// The context of the hole after solving, sorted by variable name
+    pub gamma: Vec<(String, TypeExpr)>,
+}
+
+/// All typed holes of an expression from left to right
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct Holes {
+    pub holes: Vec<TypedHole>,
+}
+
+/// Resolves the type and the context of every hole in the tree with the substitution of the solution.
+/// Fails if the constraints could not be solved, because then no hole has a type.
+pub fn find_holes(tree: &Tree, solution: &Solution) -> Result<Holes, String> {
+    if let Some(Err(e)) = &solution.result {
+        return Err(format!(
+            "the expression has no type, so its holes have none: {}",
+            e
+        ));
+    }
+    let substitution = solution.substitution()?;
+    let resolve = |typ: &TypeExpr| Solution::resolve(typ, &substitution);
+
+    let mut holes = vec![];
+    for (node, tree) in tree.preorder().into_iter().enumerate() {
+        if let AstNode::Hole(name) = tree.expr.0.as_ref() {
+            holes.push(TypedHole {
+                name: name.clone(),
+                node,
+                typ: resolve(&tree.expr.1)?,
+                gamma: tree
+                    .sorted_gamma()
+                    .into_iter()
+                    .map(|(var, typ)| Ok((var.clone(), resolve(typ)?)))
+                    .collect::<Result<_, String>>()?,
+            });
+        }
+    }
+    Ok(Holes { holes })
+}
@@ -41,6 +41,7 @@ macro_rules! rule {
         RuleExpr {
             var: $var,
             rhs: $rhs,
+            origin: None,
         }
     };
 }
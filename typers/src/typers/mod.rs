@@ -1,6 +1,15 @@
+pub mod config;
+pub mod engines;
+pub mod grading;
+pub mod holes;
 pub mod macros;
 pub mod parser;
 pub mod rules;
+pub mod session;
+pub mod slicing;
 pub mod solver;
 pub mod tree;
+pub mod type_parser;
+pub mod unify;
 pub mod utils;
+pub mod validate;
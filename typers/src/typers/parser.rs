@@ -1,46 +1,168 @@
+use std::rc::Rc;
+
 use pest::{iterators::Pair, Parser};
-use wasm_bindgen::prelude::wasm_bindgen;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+use super::config::Config;
 
 /// Represents binary operators.
-#[wasm_bindgen]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Tsify)]
 pub enum BinOp {
     Plus,
     Mult,
 }
 
 /// Represents an abstract syntax tree node.
-#[derive(Debug, Clone)]
+/// The children are reference counted, so the derivation tree can share subexpressions instead of cloning them.
+#[derive(Debug, Clone, Serialize, Deserialize, Tsify)]
+#[tsify(into_wasm_abi)]
 pub enum AstNode {
     Var(String),
     Abs {
         var: String,
-        body: Box<AstNode>,
+        body: Rc<AstNode>,
     },
     App {
-        fun: Box<AstNode>,
-        arg: Box<AstNode>,
+        fun: Rc<AstNode>,
+        arg: Rc<AstNode>,
     },
-    IsZero(Box<AstNode>),
+    IsZero(Rc<AstNode>),
     Int(i32),
     True,
     False,
     BinOp {
         op: BinOp,
-        lhs: Box<AstNode>,
-        rhs: Box<AstNode>,
+        lhs: Rc<AstNode>,
+        rhs: Rc<AstNode>,
     },
     IfThenElse {
-        cond: Box<AstNode>,
-        then: Box<AstNode>,
-        else_: Box<AstNode>,
+        cond: Rc<AstNode>,
+        then: Rc<AstNode>,
+        else_: Rc<AstNode>,
     },
     Tuple {
-        fst: Box<AstNode>,
-        snd: Box<AstNode>,
+        fst: Rc<AstNode>,
+        snd: Rc<AstNode>,
     },
-    Fst(Box<AstNode>),
-    Snd(Box<AstNode>),
+    Fst(Rc<AstNode>),
+    Snd(Rc<AstNode>),
+    /// A typed hole, the name after the underscore is empty for `_`
+    Hole(String),
+}
+
+impl AstNode {
+    /// Returns the direct subexpressions of the node, from left to right.
+    pub fn children(&self) -> Vec<&Rc<AstNode>> {
+        match self {
+            AstNode::Var(_)
+            | AstNode::Int(_)
+            | AstNode::True
+            | AstNode::False
+            | AstNode::Hole(_) => vec![],
+            AstNode::Abs { body, .. } => vec![body],
+            AstNode::App { fun, arg } => vec![fun, arg],
+            AstNode::IsZero(expr) | AstNode::Fst(expr) | AstNode::Snd(expr) => vec![expr],
+            AstNode::BinOp { lhs, rhs, .. } => vec![lhs, rhs],
+            AstNode::IfThenElse { cond, then, else_ } => vec![cond, then, else_],
+            AstNode::Tuple { fst, snd } => vec![fst, snd],
+        }
+    }
+
+    /// Returns mutable references to the direct subexpressions of the node.
+    fn children_mut(&mut self) -> Vec<&mut Rc<AstNode>> {
+        match self {
+            AstNode::Var(_)
+            | AstNode::Int(_)
+            | AstNode::True
+            | AstNode::False
+            | AstNode::Hole(_) => vec![],
+            AstNode::Abs { body, .. } => vec![body],
+            AstNode::App { fun, arg } => vec![fun, arg],
+            AstNode::IsZero(expr) | AstNode::Fst(expr) | AstNode::Snd(expr) => vec![expr],
+            AstNode::BinOp { lhs, rhs, .. } => vec![lhs, rhs],
+            AstNode::IfThenElse { cond, then, else_ } => vec![cond, then, else_],
+            AstNode::Tuple { fst, snd } => vec![fst, snd],
+        }
+    }
+}
+
+impl PartialEq for AstNode {
+    /// Compares the expressions node by node without recursion, the derived comparison would overflow the stack for deep expressions.
+    fn eq(&self, other: &Self) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some((a, b)) = stack.pop() {
+            let same = match (a, b) {
+                (AstNode::Var(x), AstNode::Var(y)) => x == y,
+                (AstNode::Hole(x), AstNode::Hole(y)) => x == y,
+                (AstNode::Abs { var: x, .. }, AstNode::Abs { var: y, .. }) => x == y,
+                (AstNode::Int(x), AstNode::Int(y)) => x == y,
+                (AstNode::BinOp { op: x, .. }, AstNode::BinOp { op: y, .. }) => x == y,
+                (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+            };
+            if !same {
+                return false;
+            }
+            stack.extend(
+                a.children()
+                    .into_iter()
+                    .zip(b.children())
+                    .map(|(a, b)| (a.as_ref(), b.as_ref())),
+            );
+        }
+        true
+    }
+}
+
+impl Eq for AstNode {}
+
+thread_local! {
+    /// Leaf which takes the place of the children while a node is dropped.
+    static DROP_PLACEHOLDER: Rc<AstNode> = Rc::new(AstNode::True);
+}
+
+impl Drop for AstNode {
+    /// Drops the subexpressions iteratively, the derived drop would recurse once per level and overflow the stack for deep expressions.
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        let Ok(placeholder) = DROP_PLACEHOLDER.try_with(Rc::clone) else {
+            return;
+        };
+        for child in self.children_mut() {
+            stack.push(std::mem::replace(child, placeholder.clone()));
+        }
+        while let Some(node) = stack.pop() {
+            // Only nodes which are not shared any more are taken apart, their children are now owned by the stack.
+            if let Ok(mut node) = Rc::try_unwrap(node) {
+                for child in node.children_mut() {
+                    stack.push(std::mem::replace(child, placeholder.clone()));
+                }
+            }
+        }
+    }
+}
+
+// pest stops once less than 64 KiB of the stack are left, the parse starts with twice that much
+const STACK_RED_ZONE: usize = 128 * 1024;
+
+// The stack pest needs per level of parentheses, a debug build takes about 3 KiB and a release build about 300 bytes
+const STACK_PER_LEVEL: usize = 4 * 1024;
+
+// the deepest nesting of parentheses in the input
+fn nesting_depth(input: &str) -> usize {
+    let mut depth = 0usize;
+    let mut deepest = 0;
+    for c in input.chars() {
+        match c {
+            '(' => {
+                depth += 1;
+                deepest = deepest.max(depth);
+            }
+            ')' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+    deepest
 }
 
 /// Parser for the MiniHaskell language.
@@ -51,8 +173,22 @@ pub struct MiniHaskellParser;
 impl MiniHaskellParser {
     /// Parses a string into a `Pair` representing the root rule of the grammar.
     pub fn parse_str(input: &str) -> Result<Pair<'_, Rule>, String> {
-        // Parse the input string
-        let mut parsed = Self::parse(Rule::expr, input).map_err(|e| format!("{}", e))?;
+        Self::parse_str_with_config(input, &Config::default())
+    }
+
+    /// Parses a string into a `Pair`, rejecting inputs which exceed the limits of the configuration.
+    pub fn parse_str_with_config<'a>(
+        input: &'a str,
+        config: &Config,
+    ) -> Result<Pair<'a, Rule>, String> {
+        // Reject huge inputs before the recursive descent parser gets to see them.
+        config.check_input(input)?;
+
+        // pest descends on the call stack once per level of parentheses and fails with "stack limit reached" once it runs low.
+        // Deep inputs are therefore parsed on a stack which is grown to fit their depth.
+        let stack = STACK_RED_ZONE + nesting_depth(input) * STACK_PER_LEVEL;
+        let mut parsed = stacker::maybe_grow(stack, stack, || Self::parse(Rule::expr, input))
+            .map_err(|e| format!("{}", e))?;
 
         let first_pair = parsed.next().ok_or("no first pair".to_string())?;
 
@@ -60,69 +196,157 @@ impl MiniHaskellParser {
     }
 
     /// Builds an abstract syntax tree from a `Pair` representing a rule.
+    /// The tree is built with an explicit stack, so deeply nested expressions cannot overflow the call stack.
     pub fn build_ast(pair: Pair<Rule>) -> Result<AstNode, String> {
-        match pair.as_rule() {
-            Rule::var => Ok(Self::build_ast_var(pair)?),
-            Rule::abs => Ok(Self::build_ast_abs(pair)?),
-            Rule::app => Ok(Self::build_ast_app(pair)?),
-            Rule::isZero => Ok(Self::build_ast_iszero(pair)?),
-            Rule::int => Ok(Self::build_ast_int(pair)?),
-            Rule::boolean => Ok(Self::build_ast_boolean(pair)?),
-            Rule::binOp => Ok(Self::build_ast_binop(pair)?),
-            Rule::ifThenElse => Ok(Self::build_ast_if_then_else(pair)?),
-            Rule::tuple => Ok(Self::build_ast_tuple(pair)?),
-            Rule::fst => Ok(Self::build_ast_fst(pair)?),
-            Rule::snd => Ok(Self::build_ast_snd(pair)?),
-            e => Err(format!("unexpected rule: {:?}", e)),
-        }
-    }
+        let mut tasks = vec![Build::Enter(pair)];
+        // the finished subexpressions, the children of a node are on top once it is exited
+        let mut nodes: Vec<AstNode> = vec![];
 
-    /// Builds an abstract syntax tree node for a variable.
-    pub fn build_ast_var(pair: Pair<Rule>) -> Result<AstNode, String> {
-        Ok(AstNode::Var(pair.as_str().to_string()))
+        while let Some(task) = tasks.pop() {
+            let (shape, children) = match task {
+                Build::Enter(pair) => match pair.as_rule() {
+                    Rule::var => {
+                        nodes.push(Self::build_ast_var(pair)?);
+                        continue;
+                    }
+                    Rule::int => {
+                        nodes.push(Self::build_ast_int(pair)?);
+                        continue;
+                    }
+                    Rule::boolean => {
+                        nodes.push(Self::build_ast_boolean(pair)?);
+                        continue;
+                    }
+                    Rule::hole => {
+                        nodes.push(Self::build_ast_hole(pair)?);
+                        continue;
+                    }
+                    Rule::abs => {
+                        let mut pairs = pair.into_inner();
+                        let var = pairs.next().ok_or("no var".to_string())?;
+                        let body = pairs.next().ok_or("no body".to_string())?;
+                        (Shape::Abs(var.as_str().to_string()), vec![body])
+                    }
+                    // The first expression is shared by tuples, binary operations and applications, the second pair decides which one it is.
+                    Rule::binary => {
+                        let mut pairs = pair.into_inner();
+                        let first = pairs.next().ok_or("no first expression".to_string())?;
+                        let rest = pairs.next().ok_or("no second expression".to_string())?;
+                        let rule = rest.as_rule();
+                        let mut pairs = rest.into_inner();
+                        let shape = match rule {
+                            Rule::app => Shape::App,
+                            Rule::tuple => Shape::Tuple,
+                            Rule::binOp => {
+                                let op = pairs.next().ok_or("no binop".to_string())?;
+                                Shape::BinOp(Self::build_ast_op(op)?)
+                            }
+                            e => {
+                                return Err(format!(
+                                    "expected app, binop or tuple but got: {:?}",
+                                    e
+                                ))
+                            }
+                        };
+                        let second = pairs.next().ok_or("no second expression".to_string())?;
+                        (shape, vec![first, second])
+                    }
+                    Rule::isZero | Rule::fst | Rule::snd => {
+                        let shape = match pair.as_rule() {
+                            Rule::isZero => Shape::IsZero,
+                            Rule::fst => Shape::Fst,
+                            _ => Shape::Snd,
+                        };
+                        let expr = pair.into_inner().next().ok_or("no expr".to_string())?;
+                        (shape, vec![expr])
+                    }
+                    Rule::ifThenElse => {
+                        let mut pairs = pair.into_inner();
+                        let cond = pairs.next().ok_or("no cond".to_string())?;
+                        let then = pairs.next().ok_or("no then".to_string())?;
+                        let else_ = pairs.next().ok_or("no else".to_string())?;
+                        (Shape::IfThenElse, vec![cond, then, else_])
+                    }
+                    e => return Err(format!("unexpected rule: {:?}", e)),
+                },
+                Build::Exit(shape) => {
+                    let node = Self::combine(shape, &mut nodes)?;
+                    nodes.push(node);
+                    continue;
+                }
+            };
+            tasks.push(Build::Exit(shape));
+            // Push the children in reverse, so the leftmost one is built first.
+            tasks.extend(children.into_iter().rev().map(Build::Enter));
+        }
+        nodes.pop().ok_or("the expression is empty".to_string())
     }
 
-    /// Builds an abstract syntax tree node for an abstraction.
-    pub fn build_ast_abs(pair: Pair<Rule>) -> Result<AstNode, String> {
-        let mut pairs = pair.into_inner();
-        let var = pairs.next().ok_or("no var".to_string())?;
-        let body = pairs.next().ok_or("no body".to_string())?;
-
-        let var = var.as_str().to_string();
-        let body = Self::build_ast(body)?;
-
-        Ok(AstNode::Abs {
-            var,
-            body: Box::new(body),
+    // builds a node from the shape and its children, which are on top of `nodes`
+    fn combine(shape: Shape, nodes: &mut Vec<AstNode>) -> Result<AstNode, String> {
+        let arity = match shape {
+            Shape::Abs(_) | Shape::IsZero | Shape::Fst | Shape::Snd => 1,
+            Shape::App | Shape::BinOp(_) | Shape::Tuple => 2,
+            Shape::IfThenElse => 3,
+        };
+        if nodes.len() < arity {
+            return Err("a subexpression is missing".to_string());
+        }
+        let mut children = nodes
+            .split_off(nodes.len() - arity)
+            .into_iter()
+            .map(Rc::new);
+        let mut next = || {
+            children
+                .next()
+                .ok_or("a subexpression is missing".to_string())
+        };
+        Ok(match shape {
+            Shape::Abs(var) => AstNode::Abs { var, body: next()? },
+            Shape::App => AstNode::App {
+                fun: next()?,
+                arg: next()?,
+            },
+            Shape::BinOp(op) => AstNode::BinOp {
+                op,
+                lhs: next()?,
+                rhs: next()?,
+            },
+            Shape::Tuple => AstNode::Tuple {
+                fst: next()?,
+                snd: next()?,
+            },
+            Shape::IsZero => AstNode::IsZero(next()?),
+            Shape::Fst => AstNode::Fst(next()?),
+            Shape::Snd => AstNode::Snd(next()?),
+            Shape::IfThenElse => AstNode::IfThenElse {
+                cond: next()?,
+                then: next()?,
+                else_: next()?,
+            },
         })
     }
 
-    /// Builds an abstract syntax tree node for an application.
-    pub fn build_ast_app(pair: Pair<Rule>) -> Result<AstNode, String> {
-        let mut pairs = pair.into_inner();
-        let fun = pairs.next().ok_or("no fun".to_string())?;
-        let arg = pairs.next().ok_or("no arg".to_string())?;
-
-        let fun = Self::build_ast(fun)?;
-        let arg = Self::build_ast(arg)?;
-
-        Ok(AstNode::App {
-            fun: Box::new(fun),
-            arg: Box::new(arg),
-        })
+    /// Builds an abstract syntax tree node for a variable.
+    pub fn build_ast_var(pair: Pair<Rule>) -> Result<AstNode, String> {
+        Ok(AstNode::Var(pair.as_str().to_string()))
     }
 
-    /// Builds an abstract syntax tree node for an iszero expression.
-    pub fn build_ast_iszero(pair: Pair<Rule>) -> Result<AstNode, String> {
-        let mut pairs = pair.into_inner();
-        let expr = pairs.next().ok_or("no expr".to_string())?;
-        let expr = Self::build_ast(expr)?;
-        Ok(AstNode::IsZero(Box::new(expr)))
+    /// Builds an abstract syntax tree node for a typed hole.
+    pub fn build_ast_hole(pair: Pair<Rule>) -> Result<AstNode, String> {
+        Ok(AstNode::Hole(pair.as_str()[1..].to_string()))
     }
 
     /// Builds an abstract syntax tree node for an integer.
     pub fn build_ast_int(pair: Pair<Rule>) -> Result<AstNode, String> {
-        let int = pair.as_str().parse::<i32>().map_err(|e| format!("{}", e))?;
+        // The grammar only accepts digits, so the only way parsing can fail is an overflow.
+        let int = pair.as_str().parse::<i32>().map_err(|_| {
+            format!(
+                "integer literal {} is out of range (the maximum is {})",
+                pair.as_str(),
+                i32::MAX
+            )
+        })?;
         Ok(AstNode::Int(int))
     }
 
@@ -146,73 +370,22 @@ impl MiniHaskellParser {
             e => Err(format!("expected binop but got: {:?}", e)),
         }
     }
+}
 
-    /// Builds an abstract syntax tree node for a binary operation.
-    pub fn build_ast_binop(pair: Pair<Rule>) -> Result<AstNode, String> {
-        let mut pairs = pair.into_inner();
-
-        let lhs = pairs.next().ok_or("no lhs".to_string())?;
-        let binop = pairs.next().ok_or("no binop".to_string())?;
-        let rhs = pairs.next().ok_or("no rhs".to_string())?;
-
-        dbg!(&lhs, &binop, &rhs);
-
-        let lhs = Self::build_ast(lhs)?;
-        let op = Self::build_ast_op(binop)?;
-        let rhs = Self::build_ast(rhs)?;
-        Ok(AstNode::BinOp {
-            op,
-            lhs: Box::new(lhs),
-            rhs: Box::new(rhs),
-        })
-    }
-
-    /// Builds an abstract syntax tree node for an if-then-else expression.
-    pub fn build_ast_if_then_else(pair: Pair<Rule>) -> Result<AstNode, String> {
-        let mut pairs = pair.into_inner();
-        let cond = pairs.next().ok_or("no cond".to_string())?;
-        let then = pairs.next().ok_or("no then".to_string())?;
-        let else_ = pairs.next().ok_or("no else".to_string())?;
-
-        let cond = Self::build_ast(cond)?;
-        let then = Self::build_ast(then)?;
-        let else_ = Self::build_ast(else_)?;
-
-        Ok(AstNode::IfThenElse {
-            cond: Box::new(cond),
-            then: Box::new(then),
-            else_: Box::new(else_),
-        })
-    }
-
-    /// Builds an abstract syntax tree node for a tuple.
-    pub fn build_ast_tuple(pair: Pair<Rule>) -> Result<AstNode, String> {
-        let mut pairs = pair.into_inner();
-        let fst = pairs.next().ok_or("no fst".to_string())?;
-        let snd = pairs.next().ok_or("no snd".to_string())?;
-
-        let fst = Self::build_ast(fst)?;
-        let snd = Self::build_ast(snd)?;
-
-        Ok(AstNode::Tuple {
-            fst: Box::new(fst),
-            snd: Box::new(snd),
-        })
-    }
-
-    /// Builds an abstract syntax tree node for the `fst` operation.
-    pub fn build_ast_fst(pair: Pair<Rule>) -> Result<AstNode, String> {
-        let mut pairs = pair.into_inner();
-        let expr = pairs.next().ok_or("no expr".to_string())?;
-        let expr = Self::build_ast(expr)?;
-        Ok(AstNode::Fst(Box::new(expr)))
-    }
+// A pair of the parse tree which still has to be built, or a node whose children are built and which only has to be put together.
+enum Build<'a> {
+    Enter(Pair<'a, Rule>),
+    Exit(Shape),
+}
 
-    /// Builds an abstract syntax tree node for the `snd` operation.
-    pub fn build_ast_snd(pair: Pair<Rule>) -> Result<AstNode, String> {
-        let mut pairs = pair.into_inner();
-        let expr = pairs.next().ok_or("no expr".to_string())?;
-        let expr = Self::build_ast(expr)?;
-        Ok(AstNode::Snd(Box::new(expr)))
-    }
+// The kind of a compound node, together with the parts of it which are not subexpressions
+enum Shape {
+    Abs(String),
+    App,
+    BinOp(BinOp),
+    Tuple,
+    IsZero,
+    Fst,
+    Snd,
+    IfThenElse,
 }
@@ -1,5 +1,7 @@
 use crate::{rule, var};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use tsify::Tsify;
 
 /// Utility trait to implement these functions for `Vec<RuleExpr>`
 pub trait RuleInfo {
@@ -29,7 +31,7 @@ impl RuleInfo for Vec<RuleExpr> {
 }
 
 /// A type expression, it forms a recursive tree structure, therefore `Box<Type>` is needed
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug, Serialize, Deserialize, Tsify)]
 pub enum TypeExpr {
     /// A function, the type expression has the form `tX -> tY`
     Function(Box<TypeExpr>, Box<TypeExpr>),
@@ -50,11 +52,8 @@ impl TypeExpr {
         match *self {
             TypeExpr::Function(ref mut left, ref mut right) => {
                 if let Some(rule) = left.substitute_constraint(rules) {
-                    dbg!("left");
-                    dbg!(&left);
                     Some(rule)
                 } else {
-                    dbg!("right");
                     right.substitute_constraint(rules)
                 }
             }
@@ -66,7 +65,6 @@ impl TypeExpr {
                 }
             }
             TypeExpr::Var(x) => {
-                dbg!("var");
                 if let Some(rule) = rules.iter().find(|r| r.var == x) {
                     let new_expr = *rule.rhs.clone();
                     *self = new_expr;
@@ -129,26 +127,39 @@ impl TypeExpr {
         }
         res
     }
-    /// Compares types and returns all new constraints generated by the comparison
-    pub fn compare_types(&self, other: &TypeExpr) -> Result<Vec<RuleExpr>, ()> {
+    /// Compares types and returns all new constraints generated by the comparison.
+    /// Fails with the position inside both types where they have different forms.
+    pub fn compare_types(&self, other: &TypeExpr) -> Result<Vec<RuleExpr>, Mismatch> {
         match &self {
             TypeExpr::Function(sleft, sright) => match other {
                 TypeExpr::Function(oleft, oright) => {
-                    let mut rules = sleft.compare_types(oleft)?;
-                    rules.append(&mut sright.compare_types(oright)?);
+                    let mut rules = sleft
+                        .compare_types(oleft)
+                        .map_err(|m| m.inside(TypePosition::Argument))?;
+                    rules.append(
+                        &mut sright
+                            .compare_types(oright)
+                            .map_err(|m| m.inside(TypePosition::Result))?,
+                    );
                     Ok(rules)
                 }
                 TypeExpr::Var(x) => Ok(vec![rule!(*x, Box::new(self.clone()))]),
-                _ => Err(()),
+                _ => Err(Mismatch::new(self, other)),
             },
             TypeExpr::Tuple(sleft, sright) => match other {
                 TypeExpr::Tuple(oleft, oright) => {
-                    let mut rules = sleft.compare_types(oleft)?;
-                    rules.append(&mut sright.compare_types(oright)?);
+                    let mut rules = sleft
+                        .compare_types(oleft)
+                        .map_err(|m| m.inside(TypePosition::First))?;
+                    rules.append(
+                        &mut sright
+                            .compare_types(oright)
+                            .map_err(|m| m.inside(TypePosition::Second))?,
+                    );
                     Ok(rules)
                 }
                 TypeExpr::Var(x) => Ok(vec![rule!(*x, Box::new(self.clone()))]),
-                _ => Err(()),
+                _ => Err(Mismatch::new(self, other)),
             },
             TypeExpr::Var(x) => match other {
                 TypeExpr::Var(c) => {
@@ -166,24 +177,121 @@ impl TypeExpr {
             TypeExpr::Bool => match other {
                 TypeExpr::Var(x) => Ok(vec![rule!(*x, Box::new(self.clone()))]),
                 TypeExpr::Bool => Ok(vec![]),
-                _ => Err(()),
+                _ => Err(Mismatch::new(self, other)),
             },
             TypeExpr::Int => match other {
                 TypeExpr::Var(x) => Ok(vec![rule!(*x, Box::new(self.clone()))]),
                 TypeExpr::Int => Ok(vec![]),
-                _ => Err(()),
+                _ => Err(Mismatch::new(self, other)),
             },
         }
     }
 }
 
+/// A step from a function or tuple type into one of its parts
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize, Tsify)]
+pub enum TypePosition {
+    /// The argument of a function type
+    Argument,
+    /// The result of a function type
+    Result,
+    /// The first component of a tuple type
+    First,
+    /// The second component of a tuple type
+    Second,
+}
+
+impl TypePosition {
+    /// The name of the part, like "result"
+    pub fn name(&self) -> &'static str {
+        match self {
+            TypePosition::Argument => "argument",
+            TypePosition::Result => "result",
+            TypePosition::First => "first component",
+            TypePosition::Second => "second component",
+        }
+    }
+}
+
+/// Where two compared types have different forms, and the parts of both types found there
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize, Tsify)]
+pub struct Mismatch {
+    /// The steps from the outermost types to the clash, empty if the outermost types already differ
+    pub path: Vec<TypePosition>,
+    /// The part of the first type at the end of the path
+    pub expected: TypeExpr,
+    /// The part of the second type at the end of the path
+    pub found: TypeExpr,
+}
+
+impl Mismatch {
+    /// A clash between the outermost forms of both types
+    pub fn new(expected: &TypeExpr, found: &TypeExpr) -> Self {
+        Mismatch {
+            path: vec![],
+            expected: expected.clone(),
+            found: found.clone(),
+        }
+    }
+
+    /// The same clash, seen from a type which contains the compared types at the given position
+    pub fn inside(mut self, position: TypePosition) -> Self {
+        self.path.insert(0, position);
+        self
+    }
+
+    /// Names the position of the clash from the inside out, like "the result of the argument", `None` for the outermost types
+    pub fn position(&self) -> Option<String> {
+        if self.path.is_empty() {
+            return None;
+        }
+        let names: Vec<&str> = self.path.iter().rev().map(TypePosition::name).collect();
+        Some(format!("the {}", names.join(" of the ")))
+    }
+}
+
+/// The node of the derivation tree whose typing rule generated a constraint
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize, Tsify)]
+pub struct Origin {
+    /// The number of the node in pre-order, the root is `0`
+    pub node: usize,
+    /// The typing rule of the node, like `App`
+    pub rule: String,
+}
+
 /// A single rule expression, with a left hand side variable and a type expression on the right hand side
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize, Tsify)]
+#[tsify(into_wasm_abi)]
 pub struct RuleExpr {
     /// The variable ID of the left hand side
     pub var: usize,
     /// The type expression on the right hand side
     pub rhs: Box<TypeExpr>,
+    /// The node of the derivation tree the rule comes from, rules derived by the solver keep the origin of the rule they replace.
+    /// It is ignored when rules are compared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub origin: Option<Origin>,
+}
+
+impl PartialEq for RuleExpr {
+    fn eq(&self, other: &Self) -> bool {
+        self.var == other.var && self.rhs == other.rhs
+    }
+}
+
+impl Eq for RuleExpr {}
+
+impl PartialOrd for RuleExpr {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RuleExpr {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.var, &self.rhs).cmp(&(other.var, &other.rhs))
+    }
 }
 
 impl RuleExpr {
@@ -206,12 +314,10 @@ impl RuleExpr {
     }
     /// Substitutes the first variable with a known constraint, mutates in place and only substitutes the first occurence
     pub fn substitute_constraint(&mut self, rules: &Vec<RuleExpr>) -> Option<RuleExpr> {
-        let res = self.rhs.substitute_constraint(rules);
-        dbg!(&self);
-        res
+        self.rhs.substitute_constraint(rules)
     }
-    /// Compares rules and returns all new constraints generated by the comparison
-    pub fn compare_rules(&self, other: &RuleExpr) -> Result<Vec<RuleExpr>, ()> {
+    /// Compares rules and returns all new constraints generated by the comparison, or where their right hand sides clash
+    pub fn compare_rules(&self, other: &RuleExpr) -> Result<Vec<RuleExpr>, Mismatch> {
         self.rhs.compare_types(&other.rhs)
     }
     /// Checks if both rules have the same left hand side variable
@@ -0,0 +1,230 @@
+use serde::Serialize;
+use tsify::Tsify;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::typers::{
+    config::Config,
+    rules::{RuleExpr, TypeExpr},
+    solver::{Action, Solution, Solver, Step},
+    utils::{
+        mathjax::MathJax,
+        wasm::{from_js, ActionJs, ConstraintsJs},
+    },
+};
+
+/// A working set of rules, as it is passed to JavaScript
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct Rules(pub Vec<RuleExpr>);
+
+/// Equations which still have to be decomposed, as they are passed to JavaScript
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct Equations(pub Vec<(TypeExpr, TypeExpr)>);
+
+/// The answer to an action of the student, either the step it resulted in or why it is not allowed.
+/// Both are missing if the action was allowed, but showed that the constraints cannot be solved, the reason is then the result of the solution.
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct Feedback {
+    pub step: Option<Step>,
+    pub error: Option<String>,
+}
+
+/// The action the solver would take next, with an explanation for the student
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct Hint {
+    pub action: Action,
+    pub text: String,
+}
+
+/// Solves constraints one step at a time, so the web interface can animate, pause and rewind the solver.
+/// Every step keeps a snapshot of the solver, going back restores it instead of solving again from the start.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct SolverSession {
+    solver: Solver,
+    // the solver right before each step taken so far
+    history: Vec<Solver>,
+    solution: Solution,
+    // the limits of the session, they also apply to the actions coming from JavaScript
+    config: Config,
+}
+
+#[wasm_bindgen]
+impl SolverSession {
+    /// Starts solving the constraints returned by `infer`, the result is the type of `goal_var`.
+    /// The steps are always recorded, `record_steps` of the configuration is ignored.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        constraints: ConstraintsJs,
+        goal_var: usize,
+        config: &Config,
+    ) -> Result<SolverSession, String> {
+        let constraints: Vec<(TypeExpr, TypeExpr)> = from_js(&constraints, config)?;
+        Ok(SolverSession::start(
+            Solver::with_equations(constraints, goal_var, config),
+            config,
+        ))
+    }
+
+    /// Takes the next step, returns `undefined` once the solver is done
+    pub fn next_step(&mut self) -> Option<Step> {
+        let before = self.solver.clone();
+        match self.solver.next_step() {
+            Some(step) => {
+                self.history.push(before);
+                self.solution.push(step.clone());
+                Some(step)
+            }
+            None => {
+                self.solution.result = self.solver.result().cloned();
+                self.solution.mismatch = self.solver.mismatch().cloned();
+                None
+            }
+        }
+    }
+
+    /// Undoes the last step, returns `false` if no step was taken yet
+    pub fn previous_step(&mut self) -> bool {
+        match self.history.pop() {
+            Some(before) => {
+                self.solver = before;
+                self.solution.truncate(self.solver.num_steps());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies an action chosen by the student instead of the next step, or explains why it is not allowed
+    pub fn apply(&mut self, action: ActionJs) -> Feedback {
+        let result =
+            from_js(&action, &self.config).and_then(|action: Action| self.apply_action(&action));
+        match result {
+            Ok(step) => Feedback { step, error: None },
+            Err(e) => Feedback {
+                step: None,
+                error: Some(e),
+            },
+        }
+    }
+
+    /// The action the solver would take next, `undefined` once it is done
+    pub fn hint(&mut self) -> Option<Hint> {
+        let action = self.solver.hint()?;
+        let rules = self.solver.rules();
+        let text = match action {
+            Action::Decompose { equation } => {
+                let (a, b) = &self.solver.equations()[equation];
+                format!(
+                    "\\({} = {}\\) has no variable on either side, decompose it",
+                    a.to_mathjax(),
+                    b.to_mathjax()
+                )
+            }
+            Action::Accumulate { first, second } => format!(
+                "\\({}\\) and \\({}\\) have the same left hand side, compare them",
+                rules[first].to_mathjax(),
+                rules[second].to_mathjax()
+            ),
+            Action::Remove { rule } => format!(
+                "\\({}\\) only renames a variable, remove it",
+                rules[rule].to_mathjax()
+            ),
+            Action::Substitute { var } => format!(
+                "The goal rule still contains \\(t_{{{}}}\\), substitute it",
+                var
+            ),
+        };
+        Some(Hint { action, text })
+    }
+
+    /// Takes all remaining steps and returns the whole trace together with the result
+    pub fn run_to_end(&mut self) -> Solution {
+        while self.next_step().is_some() {}
+        self.solution.clone()
+    }
+
+    /// The working set of rules after the steps taken so far
+    pub fn current_rules(&self) -> Rules {
+        Rules(self.solver.rules().to_vec())
+    }
+
+    /// The equations which still have to be decomposed before the rules are simplified
+    pub fn current_equations(&self) -> Equations {
+        Equations(self.solver.equations().to_vec())
+    }
+
+    /// The goal rule, it is only known once the rules are simplified
+    pub fn current_goal(&self) -> Option<RuleExpr> {
+        self.solver.goal_rule().cloned()
+    }
+
+    /// The steps taken so far, and the result once the solver is done
+    pub fn solution(&self) -> Solution {
+        self.solution.clone()
+    }
+
+    pub fn num_steps(&self) -> usize {
+        self.solver.num_steps()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.solver.is_done()
+    }
+
+    /// An independent copy of the session, to explore a different branch without losing this one
+    pub fn fork(&self) -> SolverSession {
+        self.clone()
+    }
+}
+
+impl SolverSession {
+    /// Applies an action like `apply` without going through JavaScript.
+    /// Returns `None` if the action stopped the solver, the result then explains why.
+    /// Both change the solver, so `previous_step` undoes them alike.
+    pub fn apply_action(&mut self, action: &Action) -> Result<Option<Step>, String> {
+        let before = self.solver.clone();
+        let step = self.solver.apply(action)?;
+        self.history.push(before);
+        if let Some(step) = &step {
+            self.solution.push(step.clone());
+        }
+        self.solution.result = self.solver.result().cloned();
+        self.solution.mismatch = self.solver.mismatch().cloned();
+        Ok(step)
+    }
+
+    /// Starts solving the rules, like `new` without going through JavaScript
+    pub fn from_rules(rules: Vec<RuleExpr>, goal_var: usize, config: &Config) -> Self {
+        SolverSession::start(Solver::new(rules, goal_var, config), config)
+    }
+
+    /// Starts solving equations between arbitrary types, like `new` without going through JavaScript
+    pub fn from_equations(
+        equations: Vec<(TypeExpr, TypeExpr)>,
+        goal_var: usize,
+        config: &Config,
+    ) -> Self {
+        SolverSession::start(Solver::with_equations(equations, goal_var, config), config)
+    }
+
+    // starts a session with a solver which did not take any steps yet
+    fn start(mut solver: Solver, config: &Config) -> Self {
+        let solution = Solution::with_equations(solver.rules(), solver.equations());
+        // a student has to know right away whether there is anything to do
+        solver.settle();
+        SolverSession {
+            solution: Solution {
+                result: solver.result().cloned(),
+                mismatch: solver.mismatch().cloned(),
+                ..solution
+            },
+            solver,
+            history: vec![],
+            config: *config,
+        }
+    }
+}
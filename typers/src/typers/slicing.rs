@@ -0,0 +1,96 @@
+use serde::Serialize;
+use tsify::Tsify;
+
+use super::{
+    parser::AstNode,
+    rules::{Origin, TypeExpr},
+    tree::Tree,
+    unify::Unifier,
+};
+
+/// The constraints of an expression without a type which already contradict each other, and the subexpressions which generated them.
+/// Leaving out any one of the constraints makes the rest solvable, so every subexpression takes part in the type error.
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct ErrorSlice {
+    /// Indices of the constraints in the order `infer` returned them
+    pub indices: Vec<usize>,
+    pub constraints: Vec<(TypeExpr, TypeExpr)>,
+    /// The node of the derivation tree which generated each constraint, the same node can generate several.
+    /// `None` if the origin of the constraint is not known, so the entries line up with `constraints`.
+    pub origins: Vec<Option<Origin>>,
+    /// The subexpression of each of these nodes, `None` where the node is not known
+    pub expressions: Vec<Option<AstNode>>,
+}
+
+/// Slices the type error out of the constraints of the tree, `origins` holds the node of every constraint like `TypeInference::infer_with_origins` returns it.
+/// Returns `None` if the constraints can be solved.
+pub fn slice_error(
+    tree: &Tree,
+    constraints: &[(TypeExpr, TypeExpr)],
+    origins: &[Origin],
+) -> Option<ErrorSlice> {
+    let indices = unsatisfiable_core(constraints)?;
+    let origins: Vec<Option<Origin>> = indices.iter().map(|&i| origins.get(i).cloned()).collect();
+    let nodes = tree.preorder();
+    Some(ErrorSlice {
+        constraints: indices.iter().map(|&i| constraints[i].clone()).collect(),
+        expressions: origins
+            .iter()
+            .map(|origin| {
+                let node = nodes.get(origin.as_ref()?.node)?;
+                Some(node.expr.0.as_ref().clone())
+            })
+            .collect(),
+        origins,
+        indices,
+    })
+}
+
+/// The indices of a minimal set of constraints which cannot be solved together, `None` if all constraints can be solved.
+/// The set is minimal because no constraint can be left out, there may be other sets with fewer constraints.
+pub fn unsatisfiable_core(constraints: &[(TypeExpr, TypeExpr)]) -> Option<Vec<usize>> {
+    let all: Vec<usize> = (0..constraints.len()).collect();
+    if solvable(constraints, &all) {
+        return None;
+    }
+
+    // Adding constraints never makes them solvable again, so the shortest unsolvable prefix is found by bisection.
+    // Its last constraint is part of every core of the prefix.
+    let (mut low, mut high) = (0, constraints.len());
+    while low + 1 < high {
+        let middle = (low + high) / 2;
+        if solvable(constraints, &all[..middle]) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    // Deletion: every constraint which is not needed for the contradiction is left out
+    let mut core = all[..high].to_vec();
+    let mut i = 0;
+    while i + 1 < core.len() {
+        let without: Vec<usize> = core
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, &c)| c)
+            .collect();
+        if solvable(constraints, &without) {
+            i += 1;
+        } else {
+            core = without;
+        }
+    }
+    Some(core)
+}
+
+// whether the constraints with the given indices have a solution without infinite types
+fn solvable(constraints: &[(TypeExpr, TypeExpr)], indices: &[usize]) -> bool {
+    let mut unifier = Unifier::default();
+    indices
+        .iter()
+        .all(|&i| unifier.unify(&constraints[i].0, &constraints[i].1).is_ok())
+        && unifier.check_cycles().is_ok()
+}
@@ -1,161 +1,1173 @@
-use crate::typers::rules::{RuleExpr, RuleInfo};
-use std::collections::{HashMap, HashSet, VecDeque};
+use crate::typers::{
+    config::{Config, SolverStrategy},
+    rules::{Mismatch, Origin, RuleExpr, RuleInfo, TypeExpr},
+    unify::solve_constraints_fast,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use tsify::Tsify;
 
 use super::utils::mathjax::MathJax;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Tsify)]
+/// The change a single step makes to the working set of rules.
+/// Applying it to the rules before the step yields the rules after the step.
+pub struct RuleDelta {
+    /// Rules removed with `swap_remove`, in the order of removal, together with their index at that time
+    pub removed: Vec<(usize, RuleExpr)>,
+    /// Variable replaced in all remaining rules, as `(from, to)`
+    pub renamed: Option<(usize, usize)>,
+    /// Rules appended to the end of the working set
+    pub added: Vec<RuleExpr>,
+}
+
+impl RuleDelta {
+    /// Replays the step on the given rules
+    pub fn apply(&self, rules: &mut Vec<RuleExpr>) {
+        for (index, _) in &self.removed {
+            rules.swap_remove(*index);
+        }
+        if let Some((from, to)) = self.renamed {
+            for rule in rules.iter_mut() {
+                rule.replace_var(from, to)
+            }
+        }
+        rules.extend(self.added.iter().cloned());
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Tsify)]
 // Represents the information of a remove step
 pub struct ResultRemoveStep {
     pub id: i32,
-    pub rules_before: Vec<RuleExpr>,
-    pub rules_after: Vec<RuleExpr>,
+    pub delta: RuleDelta,
     pub text: Option<String>,
-    pub rules_removed: Vec<RuleExpr>,
 }
 
-#[derive(Debug, Clone)]
+impl ResultRemoveStep {
+    /// The rules the step removed, in the order of removal
+    pub fn rules_removed(&self) -> Vec<RuleExpr> {
+        self.delta
+            .removed
+            .iter()
+            .map(|(_, rule)| rule.clone())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Tsify)]
 // Represents the information of an accumulate step
 pub struct ResultAccumulateStep {
     pub id: i32,
-    pub rules_before: Vec<RuleExpr>,
-    pub rules_after: Vec<RuleExpr>,
+    pub delta: RuleDelta,
     pub text: Option<String>,
     pub rules_added: Vec<RuleExpr>,
     pub rules_compared: (RuleExpr, RuleExpr),
 }
 
-#[derive(Debug, Clone)]
-// Represents the information of a substitute step
+#[derive(Debug, Clone, Serialize, Deserialize, Tsify)]
+// Represents the information of a decompose step, which replaces an equation between two functions or two tuples by equations between their parts.
+// Parts with a variable on one side become rules, the others stay equations and are decomposed next.
+pub struct ResultDecomposeStep {
+    pub id: i32,
+    pub delta: RuleDelta,
+    pub text: Option<String>,
+    pub equation: (TypeExpr, TypeExpr),
+    pub equations_added: Vec<(TypeExpr, TypeExpr)>,
+    pub rules_added: Vec<RuleExpr>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Tsify)]
+// Represents the information of a clash step, an equation between two types of different forms, which shows that the constraints cannot be solved
+pub struct ResultClashStep {
+    pub id: i32,
+    pub text: Option<String>,
+    pub equation: (TypeExpr, TypeExpr),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Tsify)]
+// Represents the information of a substitute step, it only changes the goal rule and leaves the working set untouched
 pub struct ResultSubstituteStep {
     pub id: i32,
     pub goal_id: usize,
-    pub rules_available: Vec<RuleExpr>,
     pub rule_goal_before: RuleExpr,
     pub rule_goal_after: RuleExpr,
     pub rule_used: RuleExpr,
     pub text: Option<String>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Tsify)]
+#[tsify(into_wasm_abi)]
 // Represents the solution of the constraint solving process
 pub struct Solution {
     pub rules: Vec<RuleExpr>,
+    /// The initial equations without a variable on either side, they are decomposed before the rules are simplified
+    pub equations: Vec<(TypeExpr, TypeExpr)>,
     pub variables: Vec<usize>,
     pub result_remove_steps: Vec<ResultRemoveStep>,
     pub result_accumulate_steps: Vec<ResultAccumulateStep>,
     pub result_substitute_steps: Vec<ResultSubstituteStep>,
+    pub result_decompose_steps: Vec<ResultDecomposeStep>,
+    pub result_clash_steps: Vec<ResultClashStep>,
     pub result: Option<Result<RuleExpr, String>>,
+    /// Where two types clash, if the solver failed because of it
+    pub mismatch: Option<Mismatch>,
+    /// The type of every bound variable, if the constraints were solved without recording steps.
+    /// There are no steps to replay then, so the substitution is built from these rules, their variables still have to be resolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tsify(optional)]
+    pub bindings: Option<Vec<RuleExpr>>,
 }
 
-pub fn solve_constraints(mut rules: Vec<RuleExpr>, goal_var: usize) -> Solution {
-    // Initialize solution
-    let mut solution = Solution {
-        rules: rules.clone(),
-        variables: variables(rules.clone()),
-        ..Default::default()
-    };
+impl Solution {
+    /// A trace without any steps yet, which starts with the given rules
+    pub fn new(rules: &[RuleExpr]) -> Self {
+        Solution::with_equations(rules, &[])
+    }
+
+    /// A trace without any steps yet, which starts with the given rules and equations
+    pub fn with_equations(rules: &[RuleExpr], equations: &[(TypeExpr, TypeExpr)]) -> Self {
+        let mut vars = variables(rules.to_vec());
+        vars.extend(
+            equations
+                .iter()
+                .flat_map(|(a, b)| a.all_vars().into_iter().chain(b.all_vars())),
+        );
+        vars.sort();
+        vars.dedup();
+        Solution {
+            rules: rules.to_vec(),
+            equations: equations.to_vec(),
+            variables: vars,
+            ..Default::default()
+        }
+    }
 
-    // Set up Vectors for the individual steps
-    let _accumulate_steps = Vec::<ResultAccumulateStep>::new();
-    let _remove_steps = Vec::<ResultRemoveStep>::new();
-    let _substitute_steps = Vec::<ResultSubstituteStep>::new();
+    /// Number of steps the solver took
+    pub fn num_steps(&self) -> usize {
+        self.result_remove_steps.len()
+            + self.result_accumulate_steps.len()
+            + self.result_substitute_steps.len()
+            + self.result_decompose_steps.len()
+            + self.result_clash_steps.len()
+    }
 
-    // This counter is used to keep track of the order of the steps.
-    // This is necessary, because they are in different vectors and we need to know the order of the steps.
-    let mut counter = 0;
-
-    loop {
-        // Do a accumulate step
-        let result = accumulate_constraints(&mut rules, counter);
-        match result {
-            Ok(Some(step)) => {
-                solution.result_accumulate_steps.push(step);
-                counter += 1;
+    /// Reconstructs the working set of rules right before the step with the given id, by replaying all earlier steps.
+    /// Returns `None` if there is no such step.
+    pub fn rules_before(&self, step_id: i32) -> Option<Vec<RuleExpr>> {
+        if step_id < 0 || step_id as usize >= self.num_steps() {
+            return None;
+        }
+        Some(self.replay(step_id))
+    }
+
+    /// Reconstructs the working set of rules right after the step with the given id.
+    /// Returns `None` if there is no such step.
+    pub fn rules_after(&self, step_id: i32) -> Option<Vec<RuleExpr>> {
+        if step_id < 0 || step_id as usize >= self.num_steps() {
+            return None;
+        }
+        Some(self.replay(step_id + 1))
+    }
+
+    /// The working set after all accumulate and remove steps, these are the rules which are checked for cycles.
+    /// If the solver failed while simplifying, these are the rules it got stuck on.
+    pub fn simplified_rules(&self) -> Vec<RuleExpr> {
+        self.replay(i32::MAX)
+    }
+
+    /// All steps in the order they were taken
+    pub fn steps(&self) -> Vec<Step> {
+        let mut steps: Vec<Step> = self
+            .result_remove_steps
+            .iter()
+            .cloned()
+            .map(Step::Remove)
+            .chain(
+                self.result_accumulate_steps
+                    .iter()
+                    .cloned()
+                    .map(Step::Accumulate),
+            )
+            .chain(
+                self.result_substitute_steps
+                    .iter()
+                    .cloned()
+                    .map(Step::Substitute),
+            )
+            .chain(
+                self.result_decompose_steps
+                    .iter()
+                    .cloned()
+                    .map(Step::Decompose),
+            )
+            .chain(self.result_clash_steps.iter().cloned().map(Step::Clash))
+            .collect();
+        steps.sort_by_key(Step::id);
+        steps
+    }
+
+    /// Appends a step to the trace
+    pub fn push(&mut self, step: Step) {
+        match step {
+            Step::Remove(step) => self.result_remove_steps.push(step),
+            Step::Accumulate(step) => self.result_accumulate_steps.push(step),
+            Step::Substitute(step) => self.result_substitute_steps.push(step),
+            Step::Decompose(step) => self.result_decompose_steps.push(step),
+            Step::Clash(step) => self.result_clash_steps.push(step),
+        }
+    }
+
+    /// Drops all steps from the given id on, together with the result
+    pub fn truncate(&mut self, num_steps: usize) {
+        let end = num_steps as i32;
+        self.result_remove_steps.retain(|step| step.id < end);
+        self.result_accumulate_steps.retain(|step| step.id < end);
+        self.result_substitute_steps.retain(|step| step.id < end);
+        self.result_decompose_steps.retain(|step| step.id < end);
+        self.result_clash_steps.retain(|step| step.id < end);
+        self.result = None;
+        self.mismatch = None;
+    }
+
+    /// Checks the result against the constraints collected by `TypeInference::infer`, including the trivial ones.
+    /// Every variable a remove step replaced and every left hand side of the simplified rules is substituted,
+    /// until both sides of every constraint and the goal variable and its type are the same.
+    /// Returns the first constraint which does not hold, or why there is nothing to check.
+    pub fn verify(&self, constraints: &[(TypeExpr, TypeExpr)]) -> Result<(), String> {
+        let goal_rule = match &self.result {
+            Some(Ok(rule)) => rule,
+            Some(Err(e)) => return Err(format!("the solver found no type to verify: {}", e)),
+            None => return Err("the solver is not done yet".to_string()),
+        };
+        let substitution = self.substitution()?;
+
+        let goal = resolve(&TypeExpr::Var(goal_rule.var), &substitution, &mut vec![])?;
+        if goal != *goal_rule.rhs {
+            return Err(format!(
+                "the result is \\({}\\), but the substitution gives \\(t_{{{}}} = {}\\)",
+                goal_rule.to_mathjax(),
+                goal_rule.var,
+                goal.to_mathjax()
+            ));
+        }
+        for (i, (a, b)) in constraints.iter().enumerate() {
+            let left = resolve(a, &substitution, &mut vec![])?;
+            let right = resolve(b, &substitution, &mut vec![])?;
+            if left != right {
+                return Err(format!(
+                    "constraint {} \\({} = {}\\) does not hold, it becomes \\({} = {}\\)",
+                    i + 1,
+                    a.to_mathjax(),
+                    b.to_mathjax(),
+                    left.to_mathjax(),
+                    right.to_mathjax()
+                ));
             }
-            Ok(None) => {
-                // if nothing can be accumulated, we cannot simplify the rules any further.
-                break;
+        }
+        Ok(())
+    }
+
+    /// The type of every variable the solution determines: variables a remove step replaced map to the replacing variable,
+    /// the left hand sides of the simplified rules to their right hand side. Use `Solution::resolve` to substitute them completely.
+    /// A solution without recorded steps uses its `bindings` instead.
+    pub fn substitution(&self) -> Result<HashMap<usize, TypeExpr>, String> {
+        if let Some(bindings) = &self.bindings {
+            return Ok(bindings
+                .iter()
+                .map(|rule| (rule.var, *rule.rhs.clone()))
+                .collect());
+        }
+        // a variable replaced by a remove step no longer occurs in later rules, so it maps to the replacing variable
+        let mut substitution: HashMap<usize, TypeExpr> = self
+            .result_remove_steps
+            .iter()
+            .filter_map(|step| step.delta.renamed)
+            .map(|(from, to)| (from, TypeExpr::Var(to)))
+            .collect();
+        for rule in self.simplified_rules() {
+            if let Some(rhs) = substitution.insert(rule.var, *rule.rhs.clone()) {
+                return Err(format!(
+                    "\\(t_{{{}}}\\) is both \\({}\\) and \\({}\\) after simplifying",
+                    rule.var,
+                    rhs.to_mathjax(),
+                    rule.rhs.to_mathjax()
+                ));
             }
-            Err(e) => {
-                solution.result = Some(Err(e));
-                return solution;
+        }
+        Ok(substitution)
+    }
+
+    /// Substitutes the variables of the type with `substitution` until none of them has a type in it, fails for infinite types
+    pub fn resolve(
+        typ: &TypeExpr,
+        substitution: &HashMap<usize, TypeExpr>,
+    ) -> Result<TypeExpr, String> {
+        resolve(typ, substitution, &mut vec![])
+    }
+
+    /// The working set of rules before every step and after the last one, reconstructed in a single replay.
+    /// The set before the step with id `i` is at index `i`, the set after it at `i + 1`.
+    pub fn rule_sets(&self) -> Vec<Vec<RuleExpr>> {
+        let deltas = self.deltas(i32::MAX);
+        let mut deltas = deltas.iter().peekable();
+        let mut rules = self.rules.clone();
+        let mut sets = Vec::with_capacity(self.num_steps() + 1);
+        for id in 0..self.num_steps() as i32 {
+            sets.push(rules.clone());
+            while let Some((_, delta)) = deltas.next_if(|(step, _)| *step <= id) {
+                delta.apply(&mut rules);
             }
         }
+        sets.push(rules);
+        sets
+    }
+
+    /// Applies the deltas of all steps with an id smaller than `end` to the initial rules
+    fn replay(&self, end: i32) -> Vec<RuleExpr> {
+        let mut rules = self.rules.clone();
+        for (_, delta) in self.deltas(end) {
+            delta.apply(&mut rules);
+        }
+        rules
+    }
 
-        let remove = remove_simple_rule(&mut rules, counter);
+    // The deltas of all steps with an id smaller than `end`, sorted by id
+    fn deltas(&self, end: i32) -> Vec<(i32, &RuleDelta)> {
+        let mut deltas: Vec<(i32, &RuleDelta)> = self
+            .result_remove_steps
+            .iter()
+            .map(|step| (step.id, &step.delta))
+            .chain(
+                self.result_accumulate_steps
+                    .iter()
+                    .map(|step| (step.id, &step.delta)),
+            )
+            .chain(
+                self.result_decompose_steps
+                    .iter()
+                    .map(|step| (step.id, &step.delta)),
+            )
+            .filter(|(id, _)| *id < end)
+            .collect();
+        deltas.sort_by_key(|(id, _)| *id);
+        deltas
+    }
+}
 
-        match remove {
-            Ok(Some(step)) => {
-                solution.result_remove_steps.push(step);
-                counter += 1;
+pub fn solve_constraints(rules: Vec<RuleExpr>, goal_var: usize) -> Solution {
+    solve_constraints_with_config(rules, goal_var, &Config::default())
+}
+
+/// Like `solve_constraints`, with the limits and options of the `Config`
+pub fn solve_constraints_with_config(
+    rules: Vec<RuleExpr>,
+    goal_var: usize,
+    config: &Config,
+) -> Solution {
+    solve(Solver::new(rules, goal_var, config), config)
+}
+
+/// Solves equations between arbitrary types, like the constraints collected while building the derivation tree.
+/// Equations with a variable on one side are rules right away, the others are decomposed step by step first.
+pub fn solve_equations(equations: Vec<(TypeExpr, TypeExpr)>, goal_var: usize) -> Solution {
+    solve_equations_with_config(equations, goal_var, &Config::default())
+}
+
+/// Like `solve_equations`, with the limits and options of the `Config`
+pub fn solve_equations_with_config(
+    equations: Vec<(TypeExpr, TypeExpr)>,
+    goal_var: usize,
+    config: &Config,
+) -> Solution {
+    solve_equations_with_origins(equations, &[], goal_var, config)
+}
+
+/// Solves the equations like `solve_equations_with_config`, every rule keeps the origin of the equation it comes from.
+/// `origins` holds the origin of each equation like `TypeInference::infer_with_origins` returns it.
+pub fn solve_equations_with_origins(
+    equations: Vec<(TypeExpr, TypeExpr)>,
+    origins: &[Origin],
+    goal_var: usize,
+    config: &Config,
+) -> Solution {
+    solve(
+        Solver::with_origins(equations, origins, goal_var, config),
+        config,
+    )
+}
+
+// runs the solver to the end and collects its steps
+fn solve(mut solver: Solver, config: &Config) -> Solution {
+    // Without recording the steps, there is no need to rewrite the rules one step at a time
+    if !config.record_steps {
+        return solve_constraints_fast(
+            solver.rules.clone(),
+            &solver.equations,
+            solver.goal_var,
+            config,
+        );
+    }
+
+    let mut solution = Solution::with_equations(&solver.rules, &solver.equations);
+    while let Some(step) = solver.next_step() {
+        solution.push(step);
+    }
+    solution.result = solver.result().cloned();
+    solution.mismatch = solver.mismatch().cloned();
+    solution
+}
+
+/// The trace of the solver with one strategy
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct StrategyRun {
+    /// The name of the strategy
+    pub strategy: String,
+    /// A description of every step, formatted for MathJax
+    pub steps: Vec<String>,
+    pub result: Option<Result<RuleExpr, String>>,
+    /// The first step which differs from the trace of the first strategy, `None` if both traces are the same
+    pub first_difference: Option<usize>,
+}
+
+/// The traces of the solver with every strategy on the same rules
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct StrategyComparison {
+    pub runs: Vec<StrategyRun>,
+    /// Whether all strategies found the same type, or all of them failed
+    pub same_result: bool,
+}
+
+/// Solves the rules once with every strategy, so the traces can be compared step by step.
+/// The strategy of the configuration is ignored, the default strategy comes first and the other traces are compared with it.
+pub fn compare_strategies(
+    equations: Vec<(TypeExpr, TypeExpr)>,
+    goal_var: usize,
+    config: &Config,
+) -> StrategyComparison {
+    let mut runs: Vec<StrategyRun> = vec![];
+    for strategy in SolverStrategy::all() {
+        let config = Config {
+            strategy,
+            record_steps: true,
+            ..*config
+        };
+        let solution = solve_equations_with_config(equations.clone(), goal_var, &config);
+        let steps: Vec<String> = solution.steps().iter().map(Step::describe).collect();
+        let first_difference = runs.first().and_then(|first| {
+            let same = first
+                .steps
+                .iter()
+                .zip(&steps)
+                .take_while(|(a, b)| a == b)
+                .count();
+            (same < first.steps.len().max(steps.len())).then_some(same)
+        });
+        runs.push(StrategyRun {
+            strategy: strategy.name().to_string(),
+            steps,
+            result: solution.result,
+            first_difference,
+        });
+    }
+    let same_result = runs
+        .windows(2)
+        .all(|pair| match (&pair[0].result, &pair[1].result) {
+            (Some(Ok(a)), Some(Ok(b))) => a == b,
+            (Some(Err(_)), Some(Err(_))) => true,
+            _ => false,
+        });
+    StrategyComparison { runs, same_result }
+}
+
+/// A single step of the solver
+#[derive(Debug, Clone, Serialize, Deserialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub enum Step {
+    Remove(ResultRemoveStep),
+    Accumulate(ResultAccumulateStep),
+    Substitute(ResultSubstituteStep),
+    Decompose(ResultDecomposeStep),
+    Clash(ResultClashStep),
+}
+
+impl Step {
+    /// The position of the step in the trace
+    pub fn id(&self) -> i32 {
+        match self {
+            Step::Remove(step) => step.id,
+            Step::Accumulate(step) => step.id,
+            Step::Substitute(step) => step.id,
+            Step::Decompose(step) => step.id,
+            Step::Clash(step) => step.id,
+        }
+    }
+
+    /// What the step does, formatted for MathJax, so traces can be compared
+    pub fn describe(&self) -> String {
+        match self {
+            Step::Remove(step) => step.text.clone().unwrap_or_default(),
+            Step::Accumulate(step) => format!(
+                "Comparing \\({}\\) and \\({}\\)",
+                step.rules_compared.0.to_mathjax(),
+                step.rules_compared.1.to_mathjax()
+            ),
+            Step::Substitute(step) => format!(
+                "Substituting \\({}\\) into the goal rule",
+                step.rule_used.to_mathjax()
+            ),
+            Step::Decompose(step) => step.text.clone().unwrap_or_default(),
+            Step::Clash(step) => step.text.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// An action a student can choose instead of letting the solver take the next step.
+/// Rules are given by their index in the current working set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+pub enum Action {
+    /// Compare two rules with the same left hand side, the second one is replaced by the rules resulting from the comparison
+    Accumulate { first: usize, second: usize },
+    /// Remove a rule `tX = tY` and replace the larger of both variables with the smaller one in all rules
+    Remove { rule: usize },
+    /// Substitute the first occurrence of the variable in the goal rule with the rule for the variable
+    Substitute { var: usize },
+    /// Decompose an equation without a variable on either side, given by its index among the equations
+    Decompose { equation: usize },
+}
+
+impl SolverStrategy {
+    /// All strategies, the default one first
+    pub fn all() -> [SolverStrategy; 4] {
+        [
+            SolverStrategy::Alternate,
+            SolverStrategy::RemoveFirst,
+            SolverStrategy::AccumulateFirst,
+            SolverStrategy::LowestVariable,
+        ]
+    }
+
+    /// The name shown when comparing the traces
+    pub fn name(&self) -> &'static str {
+        match self {
+            SolverStrategy::Alternate => "alternate",
+            SolverStrategy::RemoveFirst => "remove first",
+            SolverStrategy::AccumulateFirst => "accumulate first",
+            SolverStrategy::LowestVariable => "lowest variable first",
+        }
+    }
+
+    /// The accumulate or remove action to take next, `None` once the rules are simplified.
+    /// `accumulated` tells whether the previous step compared two rules.
+    pub fn choose(&self, rules: &[RuleExpr], accumulated: bool) -> Option<Action> {
+        let accumulate =
+            || find_same_lhs(rules).map(|(first, second)| Action::Accumulate { first, second });
+        let remove = || find_simple_rule(rules).map(|rule| Action::Remove { rule });
+        match self {
+            SolverStrategy::Alternate if accumulated => remove().or_else(accumulate),
+            SolverStrategy::Alternate | SolverStrategy::AccumulateFirst => {
+                accumulate().or_else(remove)
             }
-            Ok(None) => {
-                // if nothing can be removed, we cannot simplify the rules any further.
-                continue;
+            SolverStrategy::RemoveFirst => remove().or_else(accumulate),
+            SolverStrategy::LowestVariable => {
+                let simple = rules
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, rule)| rule.is_simple().is_some())
+                    .min_by_key(|(_, rule)| rule.var)
+                    .map(|(i, rule)| (rule.var, 0, Action::Remove { rule: i }));
+                let pair = find_lowest_same_lhs(rules).map(|(first, second)| {
+                    (rules[first].var, 1, Action::Accumulate { first, second })
+                });
+                simple
+                    .into_iter()
+                    .chain(pair)
+                    .min_by_key(|(var, order, _)| (*var, *order))
+                    .map(|(_, _, action)| action)
             }
-            Err(e) => {
-                solution.result = Some(Err(e));
-                return solution;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Where the solver continues with its next step
+enum Phase {
+    // The equations without a variable on either side are decomposed, until only rules are left
+    Decompose,
+    // The rules are simplified with accumulate and remove steps in the order of the strategy, as long as one of both changes something.
+    // `accumulated` tells whether the previous step compared two rules.
+    Simplify { accumulated: bool },
+    // The rules are simplified, the goal rule is substituted until nothing changes
+    Substitute,
+    // The result is known
+    Done,
+}
+
+/// The solver as a state machine, which can be advanced one step at a time.
+/// It first decomposes the equations which are not rules yet, then simplifies the rules with accumulate and remove steps, checks them for cycles and then substitutes them into the goal rule.
+#[derive(Debug, Clone)]
+pub struct Solver {
+    rules: Vec<RuleExpr>,
+    // equations which still have to be decomposed, they always come before simplifying the rules
+    equations: Vec<(TypeExpr, TypeExpr)>,
+    // the origin of each equation, the rules it is decomposed into inherit it, `None` if it is not known
+    equation_origins: Vec<Option<Origin>>,
+    goal_var: usize,
+    // the variable which stands for the goal variable after the remove steps so far, they may have replaced it by a smaller one
+    goal_alias: usize,
+    goal_rule: Option<RuleExpr>,
+    phase: Phase,
+    // This counter is used to keep track of the order of the steps.
+    // This is necessary, because they are in different vectors and we need to know the order of the steps.
+    counter: i32,
+    config: Config,
+    result: Option<Result<RuleExpr, String>>,
+    mismatch: Option<Mismatch>,
+}
+
+impl Solver {
+    pub fn new(rules: Vec<RuleExpr>, goal_var: usize, config: &Config) -> Self {
+        Solver {
+            rules,
+            equations: vec![],
+            equation_origins: vec![],
+            goal_var,
+            goal_alias: goal_var,
+            goal_rule: None,
+            phase: Phase::Simplify { accumulated: false },
+            counter: 0,
+            config: *config,
+            result: None,
+            mismatch: None,
+        }
+    }
+
+    /// A solver for equations between arbitrary types, the equations which are not rules are decomposed first
+    pub fn with_equations(
+        equations: Vec<(TypeExpr, TypeExpr)>,
+        goal_var: usize,
+        config: &Config,
+    ) -> Self {
+        Solver::with_origins(equations, &[], goal_var, config)
+    }
+
+    /// A solver for equations like `with_equations`, the rules keep the origin of the equation they come from
+    pub fn with_origins(
+        equations: Vec<(TypeExpr, TypeExpr)>,
+        origins: &[Origin],
+        goal_var: usize,
+        config: &Config,
+    ) -> Self {
+        let origins: Vec<Option<Origin>> = origins.iter().cloned().map(Some).collect();
+        let (rules, equations, equation_origins) = split_equations(&equations, &origins);
+        let phase = if equations.is_empty() {
+            Phase::Simplify { accumulated: false }
+        } else {
+            Phase::Decompose
+        };
+        Solver {
+            equations,
+            equation_origins,
+            phase,
+            ..Solver::new(rules, goal_var, config)
+        }
+    }
+
+    /// The current working set of rules
+    pub fn rules(&self) -> &[RuleExpr] {
+        &self.rules
+    }
+
+    /// The equations which still have to be decomposed
+    pub fn equations(&self) -> &[(TypeExpr, TypeExpr)] {
+        &self.equations
+    }
+
+    /// The goal rule, it is only known once the rules are simplified
+    pub fn goal_rule(&self) -> Option<&RuleExpr> {
+        self.goal_rule.as_ref()
+    }
+
+    /// Number of steps taken so far
+    pub fn num_steps(&self) -> usize {
+        self.counter as usize
+    }
+
+    /// The type of the goal variable or the reason why the rules cannot be solved, once the solver is done
+    pub fn result(&self) -> Option<&Result<RuleExpr, String>> {
+        self.result.as_ref()
+    }
+
+    /// Where two types clash, once the solver failed because of it
+    pub fn mismatch(&self) -> Option<&Mismatch> {
+        self.mismatch.as_ref()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.phase == Phase::Done
+    }
+
+    /// Takes the next step, returns `None` once the solver is done and the result is known
+    pub fn next_step(&mut self) -> Option<Step> {
+        // Phases which do not change anything are passed through, until a step is taken or the solver is done
+        loop {
+            match self.phase {
+                Phase::Decompose => {
+                    if let Err(e) = check_limits(&self.rules, &self.config) {
+                        return self.fail(e);
+                    }
+                    if self.equations.is_empty() {
+                        self.phase = Phase::Simplify { accumulated: false };
+                        continue;
+                    }
+                    let step = decompose_equation(
+                        &mut self.equations,
+                        &mut self.equation_origins,
+                        &mut self.rules,
+                        0,
+                        self.counter,
+                    );
+                    return self.take_decomposition(step);
+                }
+                Phase::Simplify { accumulated } => {
+                    if let Err(e) = check_limits(&self.rules, &self.config) {
+                        return self.fail(e);
+                    }
+                    let step = match self.config.strategy.choose(&self.rules, accumulated) {
+                        Some(Action::Accumulate { first, second }) => {
+                            match accumulate_rules(&mut self.rules, first, second, self.counter) {
+                                Ok(step) => Step::Accumulate(step),
+                                Err((e, mismatch)) => {
+                                    self.mismatch = Some(mismatch);
+                                    return self.fail(e);
+                                }
+                            }
+                        }
+                        Some(Action::Remove { rule }) => {
+                            Step::Remove(remove_rule(&mut self.rules, rule, self.counter))
+                        }
+                        // if nothing can be accumulated or removed, we cannot simplify the rules any further.
+                        // Removing all simple rules names every class of equal variables after its smallest variable.
+                        Some(Action::Substitute { .. } | Action::Decompose { .. }) | None => {
+                            self.start_substituting();
+                            continue;
+                        }
+                    };
+                    self.phase = Phase::Simplify {
+                        accumulated: matches!(step, Step::Accumulate(_)),
+                    };
+                    return self.record(step);
+                }
+                Phase::Substitute => {
+                    if let Err(e) = check_limits(&self.rules, &self.config) {
+                        return self.fail(e);
+                    }
+                    let goal_rule = self.goal_rule.as_mut()?;
+                    match substitute_constraints(
+                        &self.rules,
+                        goal_rule,
+                        self.goal_var,
+                        self.counter,
+                    ) {
+                        Ok(Some(step)) => return self.record(Step::Substitute(step)),
+                        Ok(None) => {
+                            // if nothing can be substituted, we are done
+                            self.result = Some(Ok(goal_rule.clone()));
+                            self.phase = Phase::Done;
+                        }
+                        Err(e) => return self.fail(e),
+                    }
+                }
+                Phase::Done => return None,
             }
         }
     }
 
-    // Check if there are infinite types
-    let res_cycle = check_cycles(&rules);
+    /// Applies an action chosen by the student instead of the next step of the solver.
+    /// Returns an explanation if the action is not allowed right now, and `None` if the action stopped the solver.
+    pub fn apply(&mut self, action: &Action) -> Result<Option<Step>, String> {
+        self.settle();
+        if self.phase == Phase::Done {
+            return Err("the solver is already done".to_string());
+        }
+        if let Err(e) = check_limits(&self.rules, &self.config) {
+            self.fail(e);
+            return Ok(None);
+        }
+        let simplifying = matches!(self.phase, Phase::Simplify { .. });
+        let step = match *action {
+            Action::Decompose { equation } if self.phase == Phase::Decompose => {
+                if equation >= self.equations.len() {
+                    return Err(format!("there is no equation with index {}", equation));
+                }
+                let step = decompose_equation(
+                    &mut self.equations,
+                    &mut self.equation_origins,
+                    &mut self.rules,
+                    equation,
+                    self.counter,
+                );
+                let step = self.take_decomposition(step);
+                self.settle();
+                return Ok(step);
+            }
+            Action::Decompose { .. } => {
+                return Err("all equations are already decomposed".to_string());
+            }
+            _ if self.phase == Phase::Decompose => {
+                return Err(
+                    "some equations still have no variable on either side, decompose them first"
+                        .to_string(),
+                );
+            }
+            Action::Accumulate { first, second } if simplifying => {
+                let (a, b) = (self.rule(first)?, self.rule(second)?);
+                if first == second {
+                    return Err("a rule cannot be compared with itself".to_string());
+                }
+                if !a.has_same_lhs(b) {
+                    return Err(format!(
+                        "\\({}\\) and \\({}\\) have different left hand sides",
+                        a.to_mathjax(),
+                        b.to_mathjax()
+                    ));
+                }
+                match accumulate_rules(&mut self.rules, first, second, self.counter) {
+                    Ok(step) => Step::Accumulate(step),
+                    Err((e, mismatch)) => {
+                        self.mismatch = Some(mismatch);
+                        self.fail(e);
+                        return Ok(None);
+                    }
+                }
+            }
+            Action::Remove { rule } if simplifying => {
+                let r = self.rule(rule)?;
+                if r.is_simple().is_none() {
+                    return Err(format!(
+                        "\\({}\\) does not have the form \\(t_X = t_Y\\)",
+                        r.to_mathjax()
+                    ));
+                }
+                Step::Remove(remove_rule(&mut self.rules, rule, self.counter))
+            }
+            Action::Substitute { .. } if simplifying => {
+                return Err("the rules can still be simplified, substitute only once no two rules have the same left hand side and no rule has the form \\(t_X = t_Y\\)".to_string());
+            }
+            Action::Accumulate { .. } | Action::Remove { .. } => {
+                return Err("the rules are already simplified, only substitutions into the goal rule are left".to_string());
+            }
+            Action::Substitute { var } => {
+                let goal_rule = self.goal_rule.as_mut().ok_or("there is no goal rule")?;
+                if !goal_rule.rhs.all_vars().contains(&var) {
+                    return Err(format!(
+                        "\\(t_{{{}}}\\) does not occur in the goal rule \\({}\\)",
+                        var,
+                        goal_rule.to_mathjax()
+                    ));
+                }
+                match substitute_var(&self.rules, goal_rule, self.goal_var, var, self.counter)? {
+                    Some(step) => Step::Substitute(step),
+                    None => {
+                        return Err(format!(
+                            "there is no rule for \\(t_{{{}}}\\), it stays a variable in the type",
+                            var
+                        ))
+                    }
+                }
+            }
+        };
+        // the solver continues as if it had taken the step itself
+        self.phase = match step {
+            Step::Accumulate(_) => Phase::Simplify { accumulated: true },
+            Step::Remove(_) => Phase::Simplify { accumulated: false },
+            Step::Substitute(_) => Phase::Substitute,
+            Step::Decompose(_) | Step::Clash(_) => self.phase,
+        };
+        let step = self.record(step);
+        self.settle();
+        Ok(step)
+    }
+
+    /// The action the solver would take next, `None` if it is done
+    pub fn hint(&mut self) -> Option<Action> {
+        self.settle();
+        match self.phase {
+            Phase::Decompose => Some(Action::Decompose { equation: 0 }),
+            Phase::Simplify { accumulated } => {
+                self.config.strategy.choose(&self.rules, accumulated)
+            }
+            Phase::Substitute => {
+                let mut goal_rule = self.goal_rule.clone()?;
+                goal_rule
+                    .substitute_constraint(&self.rules)
+                    .map(|rule| Action::Substitute { var: rule.var })
+            }
+            Phase::Done => None,
+        }
+    }
 
-    if let Err(e) = res_cycle {
-        solution.result = Some(Err(e));
-        return solution;
+    /// Moves on to the next phase as soon as nothing is left to do in the current one, without taking a step.
+    /// Students have to know whether to simplify or substitute before choosing an action.
+    pub fn settle(&mut self) {
+        if self.phase == Phase::Decompose && self.equations.is_empty() {
+            self.phase = Phase::Simplify { accumulated: false };
+        }
+        let simplifying = matches!(self.phase, Phase::Simplify { .. });
+        if simplifying
+            && find_same_lhs(&self.rules).is_none()
+            && find_simple_rule(&self.rules).is_none()
+        {
+            self.start_substituting();
+        }
+        if let (Phase::Substitute, Some(goal_rule)) = (self.phase, &self.goal_rule) {
+            if goal_rule
+                .clone()
+                .substitute_constraint(&self.rules)
+                .is_none()
+            {
+                // the solver checks the number of rules before every attempt to substitute, so it fails the same way
+                self.result =
+                    Some(check_limits(&self.rules, &self.config).map(|_| goal_rule.clone()));
+                self.phase = Phase::Done;
+            }
+        }
     }
 
-    // Now we have simplified the rules as much as possible, we can substitute the constraints into the goal rule
-    // Find the goal rule
-    let goal_rule = find_goal_rule(&mut rules, goal_var);
+    // the rule with the given index in the working set
+    fn rule(&self, i: usize) -> Result<&RuleExpr, String> {
+        self.rules
+            .get(i)
+            .ok_or_else(|| format!("there is no rule with index {}", i))
+    }
 
-    let mut goal_rule = match goal_rule {
-        Ok(rule) => rule,
-        Err(e) => {
-            solution.result = Some(Err(e));
-            return solution;
+    // the rules are simplified, checks them for cycles and finds the goal rule
+    fn start_substituting(&mut self) {
+        // Check if there are infinite types
+        if let Err(e) = check_cycles(&self.rules) {
+            self.fail(e);
+            return;
         }
-    };
+        // Now we have simplified the rules as much as possible, we can substitute the constraints into the goal rule
+        self.goal_rule = Some(find_goal_rule(&self.rules, self.goal_var, self.goal_alias));
+        self.phase = Phase::Substitute;
+    }
+
+    // records a decompose step, or stops the solver after a clash
+    fn take_decomposition(
+        &mut self,
+        step: Result<ResultDecomposeStep, ResultClashStep>,
+    ) -> Option<Step> {
+        match step {
+            Ok(step) => self.record(Step::Decompose(step)),
+            Err(step) => {
+                self.result = Some(Err(step.text.clone().unwrap_or_default()));
+                self.mismatch = Some(Mismatch::new(&step.equation.0, &step.equation.1));
+                self.phase = Phase::Done;
+                self.record(Step::Clash(step))
+            }
+        }
+    }
+
+    // counts a step which was taken, a step beyond the limit of the configuration stops the solver instead
+    fn record(&mut self, step: Step) -> Option<Step> {
+        if self.counter as usize >= self.config.max_solver_steps {
+            return self.fail(format!(
+                "the solver did not finish within {} steps, aborting ...",
+                self.config.max_solver_steps
+            ));
+        }
+        if let Step::Remove(remove) = &step {
+            if let Some((from, to)) = remove.delta.renamed {
+                if from == self.goal_alias {
+                    self.goal_alias = to;
+                }
+            }
+        }
+        self.counter += 1;
+        Some(step)
+    }
+
+    // stops the solver with an error
+    fn fail(&mut self, e: String) -> Option<Step> {
+        self.result = Some(Err(e));
+        self.phase = Phase::Done;
+        None
+    }
+}
+
+/// Checks that the number of rules does not exceed the limit of the configuration, `Solver::record` checks the number of steps
+fn check_limits(rules: &[RuleExpr], config: &Config) -> Result<(), String> {
+    if rules.len() > config.max_constraints {
+        return Err(format!(
+            "the solver has to handle more than {} constraints, aborting ...",
+            config.max_constraints
+        ));
+    }
+    Ok(())
+}
 
-    // Substitute constraints until we cannot do it anymore
-    loop {
-        let result = substitute_constraints(&mut rules, &mut goal_rule, goal_var, counter);
-        match result {
-            Ok(Some(step)) => {
-                solution.result_substitute_steps.push(step);
-                counter += 1;
+/// Splits equations into rules `tX = type` and equations without a variable on either side, together with the origins of the latter.
+/// Equations whose sides are equal are dropped, and so are rules which are already there.
+/// `origins` holds the origin of each equation, the rules keep it.
+fn split_equations(equations: &[(TypeExpr, TypeExpr)], origins: &[Option<Origin>]) -> Split {
+    let mut rules = Vec::<RuleExpr>::new();
+    let mut rest = vec![];
+    let mut rest_origins = vec![];
+    for (i, (a, b)) in equations.iter().enumerate() {
+        if a == b {
+            continue;
+        }
+        let origin = origins.get(i).cloned().flatten();
+        let rule = match (a, b) {
+            (TypeExpr::Var(x), other) | (other, TypeExpr::Var(x)) => RuleExpr {
+                var: *x,
+                rhs: Box::new(other.clone()),
+                origin,
+            },
+            _ => {
+                rest.push((a.clone(), b.clone()));
+                rest_origins.push(origin);
+                continue;
             }
-            Ok(None) => {
-                // if nothing can be substituted, we are done
-                break;
+        };
+        if !rules.contains(&rule) {
+            rules.push(rule);
+        }
+    }
+    (rules, rest, rest_origins)
+}
+
+// the rules, the equations without a variable on either side and their origins
+type Split = (
+    Vec<RuleExpr>,
+    Vec<(TypeExpr, TypeExpr)>,
+    Vec<Option<Origin>>,
+);
+
+/// Substitutes variables in the type until none of them has a type in the substitution.
+/// `visiting` holds the variables which are substituted right now, meeting one of them again means the type is infinite.
+fn resolve(
+    typ: &TypeExpr,
+    substitution: &HashMap<usize, TypeExpr>,
+    visiting: &mut Vec<usize>,
+) -> Result<TypeExpr, String> {
+    Ok(match typ {
+        TypeExpr::Var(x) => match substitution.get(x) {
+            Some(_) if visiting.contains(x) => {
+                return Err(format!(
+                    "\\(t_{{{}}}\\) occurs in its own type after simplifying",
+                    x
+                ))
             }
-            Err(e) => {
-                solution.result = Some(Err(e));
-                return solution;
+            Some(t) => {
+                visiting.push(*x);
+                let t = resolve(t, substitution, visiting)?;
+                visiting.pop();
+                t
             }
+            None => typ.clone(),
+        },
+        TypeExpr::Function(a, b) => TypeExpr::Function(
+            Box::new(resolve(a, substitution, visiting)?),
+            Box::new(resolve(b, substitution, visiting)?),
+        ),
+        TypeExpr::Tuple(a, b) => TypeExpr::Tuple(
+            Box::new(resolve(a, substitution, visiting)?),
+            Box::new(resolve(b, substitution, visiting)?),
+        ),
+        TypeExpr::Bool | TypeExpr::Int => typ.clone(),
+    })
+}
+
+/// Decomposes the equation with index `i` into equations between the parts of both sides.
+/// Parts with a variable on one side are appended to the rules, the other parts replace the equation, so they are decomposed next.
+/// Returns a clash step if both sides have different forms.
+fn decompose_equation(
+    equations: &mut Vec<(TypeExpr, TypeExpr)>,
+    origins: &mut Vec<Option<Origin>>,
+    rules: &mut Vec<RuleExpr>,
+    i: usize,
+    counter: i32,
+) -> Result<ResultDecomposeStep, ResultClashStep> {
+    let equation = equations.remove(i);
+    let origin = origins.remove(i);
+    let parts = match &equation {
+        (TypeExpr::Function(a1, a2), TypeExpr::Function(b1, b2))
+        | (TypeExpr::Tuple(a1, a2), TypeExpr::Tuple(b1, b2)) => {
+            vec![(*a1.clone(), *b1.clone()), (*a2.clone(), *b2.clone())]
+        }
+        (a, b) => {
+            return Err(ResultClashStep {
+                id: counter,
+                text: Some(format!(
+                    "impossible to unify \\({}\\) and \\({}\\), their outermost types differ",
+                    a.to_mathjax(),
+                    b.to_mathjax()
+                )),
+                equation,
+            })
         }
+    };
+    // both parts come from the same node as the equation
+    let (rules_added, equations_added, origins_added) =
+        split_equations(&parts, &[origin.clone(), origin]);
+    let text = format!(
+        "Decomposing \\({} = {}\\) into {}",
+        equation.0.to_mathjax(),
+        equation.1.to_mathjax(),
+        parts
+            .iter()
+            .filter(|(a, b)| a != b)
+            .map(|(a, b)| format!("\\({} = {}\\)", a.to_mathjax(), b.to_mathjax()))
+            .collect::<Vec<String>>()
+            .join(" and ")
+    );
+    for (offset, (added, origin)) in equations_added.iter().zip(origins_added).enumerate() {
+        equations.insert(i + offset, added.clone());
+        origins.insert(i + offset, origin);
     }
+    let delta = RuleDelta {
+        removed: vec![],
+        renamed: None,
+        added: rules_added.clone(),
+    };
+    delta.apply(rules);
+    Ok(ResultDecomposeStep {
+        id: counter,
+        delta,
+        text: Some(text),
+        equation,
+        equations_added,
+        rules_added,
+    })
+}
 
-    solution.result = Some(Ok(goal_rule));
-    solution
+/// Builds the dependency graph of the type variables, there is an edge from the left hand side of every rule to each variable on its right hand side.
+/// Rules with the same left hand side contribute to the same node.
+pub fn dependency_graph(rules: &[RuleExpr]) -> BTreeMap<usize, BTreeSet<usize>> {
+    let mut edge_list: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+    for rule in rules {
+        edge_list
+            .entry(rule.var)
+            .or_default()
+            .extend(rule.all_vars_rhs());
+    }
+    edge_list
 }
 
 /// Checks if the rules contain any cycle, assumes that all left hand sides are unique, uses topological sorting
-fn check_cycles(rules: &Vec<RuleExpr>) -> Result<(), String> {
+fn check_cycles(rules: &[RuleExpr]) -> Result<(), String> {
     // Build graph
-    let mut edge_list: HashMap<usize, HashSet<usize>> = HashMap::new();
+    let edge_list = dependency_graph(rules);
     let mut in_degree: HashMap<usize, usize> = HashMap::new();
-    // Build edge_list and in_degree
-    for rule in rules {
-        let rhs = rule.all_vars_rhs();
-        in_degree.entry(rule.var).or_insert(0);
-        for v in rhs.iter() {
+    // Build in_degree
+    for (u, edges) in edge_list.iter() {
+        in_degree.entry(*u).or_insert(0);
+        for v in edges {
             in_degree.entry(*v).and_modify(|x| *x += 1).or_insert(1);
         }
-        edge_list.insert(rule.var, rule.all_vars_rhs());
     }
 
     // Enqueue all zero in_degree nodes
@@ -174,7 +1186,9 @@ fn check_cycles(rules: &Vec<RuleExpr>) -> Result<(), String> {
         // adjust in_degree of next nodes
         if let Some(edges) = edge_list.get(&u) {
             for v in edges {
-                let degree = in_degree.get_mut(v).unwrap();
+                let degree = in_degree.get_mut(v).ok_or(format!(
+                    "\\(t_{{{v}}}\\) is missing in the constraint graph"
+                ))?;
                 *degree -= 1;
                 // if in_degree is 0 we need to enqueue the node
                 if *degree == 0 {
@@ -201,119 +1215,147 @@ fn variables(rules: Vec<RuleExpr>) -> Vec<usize> {
     all_vars_vec
 }
 
-/// Remove the first rule of the form `tX = tY` by replacing `X` with `Y` in all rules (where `X` < `Y`)
-/// Assumes that the goal variable has the lowest ID, since otherwise it might replace it
-fn remove_simple_rule(
-    rules: &mut Vec<RuleExpr>,
-    counter: i32,
-) -> Result<Option<ResultRemoveStep>, String> {
-    let rules_before = rules.clone();
+/// The index of the first rule of the form `tX = tY`
+fn find_simple_rule(rules: &[RuleExpr]) -> Option<usize> {
+    rules.iter().position(|rule| rule.is_simple().is_some())
+}
+
+/// Removes the simple rule with index `i` and replaces the larger of its variables with the smaller one in all rules
+fn remove_rule(rules: &mut Vec<RuleExpr>, i: usize, counter: i32) -> ResultRemoveStep {
+    let rule_used = rules[i].clone();
+    let (mut from, mut to) = rule_used
+        .is_simple()
+        .unwrap_or((rule_used.var, rule_used.var));
+    if from < to {
+        std::mem::swap(&mut to, &mut from);
+    }
+    let msg = if from == to {
+        // a rule like t_1 = t_1 holds trivially and can just be dropped
+        format!("Removing the trivial rule \\(t_{{{from}}}\\) = \\(t_{{{to}}}\\)")
+    } else {
+        format!("Replacing \\(t_{{{from}}}\\) with \\(t_{{{to}}}\\) in all rules")
+    };
+    let delta = RuleDelta {
+        removed: vec![(i, rule_used)],
+        renamed: (from != to).then_some((from, to)),
+        added: vec![],
+    };
+    delta.apply(rules);
+    ResultRemoveStep {
+        id: counter,
+        delta,
+        text: Some(msg),
+    }
+}
+
+/// The indices `(i, j)` with `i < j` of the first two rules with the same left hand side
+fn find_same_lhs(rules: &[RuleExpr]) -> Option<(usize, usize)> {
+    // Iterate over rules to find two matching ones
     for i in 0..rules.len() {
-        if let Some((mut from, mut to)) = rules[i].is_simple() {
-            if from == to {
-                let error = format!("recursive definition \\(t_{{{from}}}\\) = \\(t_{{{to}}}\\)!");
-                return Err(error);
+        for j in i + 1..rules.len() {
+            if rules[i].has_same_lhs(&rules[j]) {
+                return Some((i, j));
             }
+        }
+    }
+    None
+}
 
-            if from < to {
-                std::mem::swap(&mut to, &mut from);
+/// The indices `(i, j)` with `i < j` of the first two rules with the same left hand side among those with the lowest left hand side
+fn find_lowest_same_lhs(rules: &[RuleExpr]) -> Option<(usize, usize)> {
+    let mut first: HashMap<usize, usize> = HashMap::new();
+    let mut lowest: Option<(usize, usize)> = None;
+    for (j, rule) in rules.iter().enumerate() {
+        match first.get(&rule.var) {
+            Some(&i) if lowest.is_none_or(|(l, _)| rules[l].var > rule.var) => {
+                lowest = Some((i, j))
             }
-            let msg = format!("Replacing \\(t_{{{from}}}\\) with \\(t_{{{to}}}\\) in all rules");
-            let rule_used = rules[i].clone();
-            rules.swap_remove(i);
-
-            for rule in rules.iter_mut() {
-                rule.replace_var(from, to)
+            Some(_) => (),
+            None => {
+                first.insert(rule.var, j);
             }
-            let rules_after = rules.clone();
-            return Ok(Some(ResultRemoveStep {
-                id: counter,
-                rules_before,
-                rules_after,
-                text: Some(msg),
-                rules_removed: vec![rule_used],
-            }));
         }
     }
-    Ok(None)
+    lowest
 }
 
-/// Accumulate constraints by comparing rules with the same left hand side and replacing variables which are equal
-fn accumulate_constraints(
+/// Compares the rules with index `i` and `j`, which have the same left hand side.
+/// The rule `j` is replaced by the rules resulting from the comparison.
+fn accumulate_rules(
     rules: &mut Vec<RuleExpr>,
+    i: usize,
+    j: usize,
     counter: i32,
-) -> Result<Option<ResultAccumulateStep>, String> {
-    // Compare rules greedily to get new constraints
-
-    // Iterate over rules to find two matching ones
-    let _found_new = false;
-    let rules_before = rules.clone();
-    for i in 0..rules.len() {
-        for j in i + 1..rules.len() {
-            if !rules[i].has_same_lhs(&rules[j]) {
-                continue;
+) -> Result<ResultAccumulateStep, (String, Mismatch)> {
+    // Get all new constraints by comparing the rules
+    match rules[i].compare_rules(&rules[j]) {
+        Ok(mut new_rules) => {
+            let msg = format!("Comparing these rules\n{}\n{}", rules[i], rules[j]);
+            // save the compared rules for the step
+            let rule_i = rules[i].clone();
+            let rule_j = rules[j].clone();
+            // the new rules take the place of the second rule, so they come from the same node
+            for rule in new_rules.iter_mut() {
+                rule.origin = rule_j.origin.clone();
             }
-            // Get all new constraints by comparing the rules
-            if let Ok(new_rules) = rules[i].compare_rules(&rules[j]) {
-                let msg = format!("Comparing these rules\n{}\n{}", rules[i], rules[j]);
-                // save the compared rules for the step
-                let rule_i = rules[i].clone();
-                let rule_j = rules[j].clone();
-
-                rules.swap_remove(j);
-
-                // save the rules for the step
-                let mut rules_after = rules.clone();
-                rules_after.append(new_rules.clone().as_mut());
-
-                // Add the new rules to our working set
-                rules.append(new_rules.clone().as_mut());
-
-                return Ok(Some(ResultAccumulateStep {
-                    id: counter,
-                    rules_before,
-                    rules_after,
-                    text: Some(msg),
-                    rules_added: new_rules,
-                    rules_compared: (rule_i, rule_j),
-                }));
-            } else {
-                // Two rules with the same lhs was found, but they cannot be combined. Therefore the constraints are inconsistent.
-                let msg = format!(
-                    "impossible to combine these rules: \\({}\\) and \\({}\\)",
-                    rules[i].to_mathjax(),
-                    rules[j].to_mathjax()
-                );
-                return Err(msg);
+
+            // Remove the second rule and add the new rules to our working set
+            let delta = RuleDelta {
+                removed: vec![(j, rule_j.clone())],
+                renamed: None,
+                added: new_rules.clone(),
+            };
+            delta.apply(rules);
+
+            Ok(ResultAccumulateStep {
+                id: counter,
+                delta,
+                text: Some(msg),
+                rules_added: new_rules,
+                rules_compared: (rule_i, rule_j),
+            })
+        }
+        Err(mismatch) => {
+            // Two rules with the same lhs was found, but they cannot be combined. Therefore the constraints are inconsistent.
+            let mut msg = format!(
+                "impossible to combine these rules: \\({}\\) and \\({}\\)",
+                rules[i].to_mathjax(),
+                rules[j].to_mathjax()
+            );
+            // if the right hand sides already differ outermost, the rules show the clash themselves
+            if !mismatch.path.is_empty() {
+                msg.push_str(&format!(", {}", mismatch.to_mathjax()));
             }
+            Err((msg, mismatch))
         }
     }
-    Ok(None)
 }
 
-/// Find rule for goal variable
-fn find_goal_rule(rules: &mut [RuleExpr], goal_var: usize) -> Result<RuleExpr, String> {
-    match rules.iter().find(|r| r.has_lhs(goal_var)) {
-        Some(goal_rule) => Ok(goal_rule.clone()),
-        None => {
-            let msg = format!(
-                "could not find a constraint with \\(t_{{{goal_var}}}\\) on the left hand side"
-            );
-            Err(msg)
-        }
+/// Find rule for goal variable, `alias` is the variable the remove steps replaced it with, or the goal variable itself.
+/// Without a rule for the alias nothing constrains it, like for a typed hole on its own, so the goal is the free variable `t_alias`.
+fn find_goal_rule(rules: &[RuleExpr], goal_var: usize, alias: usize) -> RuleExpr {
+    match rules.iter().find(|r| r.has_lhs(alias)) {
+        Some(rule) => RuleExpr {
+            var: goal_var,
+            ..rule.clone()
+        },
+        None => RuleExpr {
+            var: goal_var,
+            rhs: Box::new(TypeExpr::Var(alias)),
+            origin: None,
+        },
     }
 }
 
 /// Substitute constraints, assumes that there is no cycle in the rules
 fn substitute_constraints(
-    rules: &mut Vec<RuleExpr>,
+    rules: &Vec<RuleExpr>,
     goal_rule: &mut RuleExpr,
     goal_var: usize,
     counter: i32,
 ) -> Result<Option<ResultSubstituteStep>, String> {
     // Save the goal rule before substitution
     let rule_goal_before = goal_rule.clone();
-    let rules_before = rules.clone();
 
     // Substitute constraints one by one
     if let Some(rule) = goal_rule.substitute_constraint(rules) {
@@ -323,7 +1365,6 @@ fn substitute_constraints(
         return Ok(Some(ResultSubstituteStep {
             id: counter,
             goal_id: goal_var,
-            rules_available: rules_before,
             rule_goal_before,
             rule_goal_after,
             rule_used: rule.clone(),
@@ -332,3 +1373,15 @@ fn substitute_constraints(
     }
     Ok(None)
 }
+
+/// Substitutes the first occurrence of `var` in the goal rule with the rule for `var`
+fn substitute_var(
+    rules: &[RuleExpr],
+    goal_rule: &mut RuleExpr,
+    goal_var: usize,
+    var: usize,
+    counter: i32,
+) -> Result<Option<ResultSubstituteStep>, String> {
+    let candidates: Vec<RuleExpr> = rules.iter().filter(|r| r.has_lhs(var)).cloned().collect();
+    substitute_constraints(&candidates, goal_rule, goal_var, counter)
+}
@@ -1,10 +1,19 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
 
-use wasm_bindgen::prelude::wasm_bindgen;
+use serde::{Deserialize, Serialize, Serializer};
+use tsify::Tsify;
 
-use crate::typers::{parser::AstNode, rules::TypeExpr, utils::mathjax::MathJax};
+use wasm_bindgen::prelude::wasm_bindgen;
 
-use super::parser::BinOp;
+use crate::typers::{
+    config::{Config, Generation},
+    parser::AstNode,
+    rules::{Origin, TypeExpr},
+    utils::mathjax::MathJax,
+};
 
 #[wasm_bindgen(getter_with_clone)]
 #[derive(Debug, Clone)]
@@ -14,376 +23,492 @@ pub struct TreeTS {
     pub constraints: Vec<TreeTS>,
 }
 
-#[derive(Debug, Clone)]
+/// Derivation tree, every node is a judgment `gamma ⊢ expr :: type` and `constraints` holds its premises.
+/// Both the expression and the context are shared with the rest of the tree, so building it takes linear memory.
+#[derive(Debug, Clone, Serialize, Deserialize, Tsify)]
 pub struct Tree {
-    pub gamma: HashMap<String, TypeExpr>,
-    pub expr: (AstNode, TypeExpr),
+    #[serde(serialize_with = "serialize_gamma")]
+    pub gamma: Rc<HashMap<String, TypeExpr>>,
+    pub expr: (Rc<AstNode>, TypeExpr),
     pub constraints: Vec<Tree>,
 }
 
+impl Tree {
+    /// The context of the judgment sorted by variable name, so every output lists it in the same order
+    pub fn sorted_gamma(&self) -> Vec<(&String, &TypeExpr)> {
+        let mut gamma: Vec<(&String, &TypeExpr)> = self.gamma.iter().collect();
+        gamma.sort_by(|a, b| a.0.cmp(b.0));
+        gamma
+    }
+
+    /// All nodes of the tree in pre-order, the position of a node in the list is its number
+    pub fn preorder(&self) -> Vec<&Tree> {
+        let mut nodes = vec![];
+        let mut stack = vec![self];
+        while let Some(tree) = stack.pop() {
+            nodes.push(tree);
+            stack.extend(tree.constraints.iter().rev());
+        }
+        nodes
+    }
+}
+
+// writes the context sorted by variable name, so the same tree always gives the same JSON
+fn serialize_gamma<S: Serializer>(
+    gamma: &Rc<HashMap<String, TypeExpr>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    gamma
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .serialize(serializer)
+}
+
+impl Drop for Tree {
+    /// Drops the premises iteratively, the derived drop would recurse once per level and overflow the stack for deep trees.
+    fn drop(&mut self) {
+        let mut stack = std::mem::take(&mut self.constraints);
+        while let Some(mut tree) = stack.pop() {
+            stack.append(&mut tree.constraints);
+        }
+    }
+}
+
 impl From<Tree> for TreeTS {
     fn from(val: Tree) -> Self {
-        let gamma = val
-            .gamma
-            .iter()
-            .map(|(k, v)| format!("{}: {}", k, v.to_mathjax()))
-            .collect::<Vec<String>>()
-            .join(", ");
-        let expr = format!("{} :: {}", val.expr.0, val.expr.1);
-        let constraints = val
-            .constraints
-            .into_iter()
-            .map(|a| a.into())
-            .collect::<Vec<TreeTS>>();
-        TreeTS {
-            gamma,
-            expr,
-            constraints,
+        // Converts the nodes in post order, so the premises of a node are always converted before the node itself.
+        let mut stack = vec![(&val, false)];
+        let mut converted = Vec::<TreeTS>::new();
+        while let Some((tree, visited)) = stack.pop() {
+            if !visited {
+                stack.push((tree, true));
+                stack.extend(tree.constraints.iter().rev().map(|a| (a, false)));
+                continue;
+            }
+            let gamma = tree
+                .sorted_gamma()
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, v.to_mathjax()))
+                .collect::<Vec<String>>()
+                .join(", ");
+            let expr = format!("{} :: {}", tree.expr.0, tree.expr.1);
+            let constraints = converted.split_off(converted.len() - tree.constraints.len());
+            converted.push(TreeTS {
+                gamma,
+                expr,
+                constraints,
+            });
+        }
+        converted.pop().unwrap_or(TreeTS {
+            gamma: String::new(),
+            expr: String::new(),
+            constraints: vec![],
+        })
+    }
+}
+
+/// A premise of a typing rule, which still needs to be derived.
+struct Premise {
+    ast: Rc<AstNode>,
+    gamma: Rc<HashMap<String, TypeExpr>>,
+    t: TypeExpr,
+}
+
+impl Premise {
+    fn new(ast: &Rc<AstNode>, gamma: &Rc<HashMap<String, TypeExpr>>, t: TypeExpr) -> Self {
+        Premise {
+            ast: ast.clone(),
+            gamma: gamma.clone(),
+            t,
+        }
+    }
+}
+
+/// The result of applying a typing rule to a node: the type of the node and the premises which need to be derived.
+struct RuleApplication {
+    typ: TypeExpr,
+    premises: Vec<Premise>,
+    // A constraint which is only added after all premises were derived.
+    deferred: Option<(TypeExpr, TypeExpr)>,
+}
+
+impl RuleApplication {
+    fn new(typ: TypeExpr, premises: Vec<Premise>) -> Self {
+        RuleApplication {
+            typ,
+            premises,
+            deferred: None,
         }
     }
 }
 
+/// A unit of work while building the tree, the explicit stack of frames replaces recursion.
+enum Frame {
+    // Apply the typing rule to the premise and schedule its premises.
+    Enter(Premise, usize),
+    // All premises of the node were derived, assemble the node.
+    Exit {
+        ast: Rc<AstNode>,
+        gamma: Rc<HashMap<String, TypeExpr>>,
+        typ: TypeExpr,
+        num_premises: usize,
+        deferred: Option<(TypeExpr, TypeExpr)>,
+        // The number of the node in pre-order, the origin of the deferred constraint.
+        node: usize,
+    },
+}
+
+/// The derivation tree, the constraints and the node which generated each constraint
+pub type Inferred = (Tree, Vec<(TypeExpr, TypeExpr)>, Vec<Origin>);
+
 #[derive(Debug, Clone)]
 pub struct TypeInference {
     typ_num: usize,
+    config: Config,
     pub constraints: Vec<(TypeExpr, TypeExpr)>,
+    /// The node whose typing rule generated each constraint, in the same order as `constraints`
+    pub origins: Vec<Origin>,
 }
 
 impl TypeInference {
     // Transform an AST into a tree and a list of constraints.
     pub fn infer(ast: AstNode) -> Result<(Tree, Vec<(TypeExpr, TypeExpr)>), String> {
-        let gamma = HashMap::new();
-        let constraints = Vec::<(TypeExpr, TypeExpr)>::new();
+        Self::infer_with_config(ast, &Config::default())
+    }
+
+    // Transform an AST into a tree and a list of constraints, aborting if a limit of the configuration is exceeded.
+    pub fn infer_with_config(
+        ast: AstNode,
+        config: &Config,
+    ) -> Result<(Tree, Vec<(TypeExpr, TypeExpr)>), String> {
+        let (tree, inference) = Self::run(ast, config)?;
+        Ok((tree, inference.constraints))
+    }
+
+    // Transform an AST into a tree and a list of constraints, together with the node which generated each constraint.
+    pub fn infer_with_origins(ast: AstNode, config: &Config) -> Result<Inferred, String> {
+        let (tree, inference) = Self::run(ast, config)?;
+        Ok((tree, inference.constraints, inference.origins))
+    }
+
+    // Builds the tree, the constraints and their origins are left in the returned state.
+    fn run(ast: AstNode, config: &Config) -> Result<(Tree, TypeInference), String> {
+        let gamma = Rc::new(HashMap::new());
         let mut type_inference = TypeInference {
             typ_num: 0,
-            constraints,
+            config: *config,
+            constraints: vec![],
+            origins: vec![],
         };
-        let start_t = type_inference.new_typ();
-        let res = type_inference.build_tree(ast, gamma, start_t)?;
-        Ok((res, type_inference.constraints))
+        let start_t = type_inference.new_typ()?;
+        let res = type_inference.build_tree(Rc::new(ast), gamma, start_t)?;
+        Ok((res, type_inference))
     }
     // gives a new type variable with a fresh number.
-    pub fn new_typ(&mut self) -> TypeExpr {
+    pub fn new_typ(&mut self) -> Result<TypeExpr, String> {
+        if self.typ_num >= self.config.max_type_vars {
+            return Err(format!(
+                "the derivation tree needs more than {} type variables",
+                self.config.max_type_vars
+            ));
+        }
         let t = TypeExpr::Var(self.typ_num);
         self.typ_num += 1;
-        t
+        Ok(t)
+    }
+
+    // Whether a type which already has the form a rule needs is used instead of fresh variables.
+    fn reuses_structure(&self) -> bool {
+        self.config.generation != Generation::AlwaysFresh
     }
 
     // Add the constraint a = b to the constraint list.
-    pub fn add_constraint(&mut self, a: &TypeExpr, b: &TypeExpr) {
+    pub fn add_constraint(&mut self, a: &TypeExpr, b: &TypeExpr) -> Result<(), String> {
+        if self.constraints.len() >= self.config.max_constraints {
+            return Err(format!(
+                "the derivation tree generates more than {} constraints",
+                self.config.max_constraints
+            ));
+        }
         self.constraints.push((a.clone(), b.clone()));
+        Ok(())
     }
 
     // Build a tree with types from an AST. It collects the type constraints.
+    // The tree is built depth first from left to right with an explicit stack, so deep expressions cannot overflow the call stack.
     pub fn build_tree(
         &mut self,
-        ast: AstNode,
-        gamma: HashMap<String, TypeExpr>,
+        ast: Rc<AstNode>,
+        gamma: Rc<HashMap<String, TypeExpr>>,
         t: TypeExpr,
     ) -> Result<Tree, String> {
-        let my_ast = ast.clone();
-        match my_ast {
-            AstNode::Var(var) => Ok(self.build_var(var, gamma, t)?),
-            AstNode::Abs { var, body } => Ok(self.build_abs(var, *body, gamma, t)?),
-            AstNode::App { fun, arg } => Ok(self.build_app(*fun, *arg, gamma, t)?),
-            AstNode::IsZero(expr) => Ok(self.build_zero(*expr, gamma, t)?),
-            AstNode::Int(num) => Ok(self.build_int(num, gamma, t)?),
-            AstNode::True => Ok(self.build_bool(true, gamma, t)?),
-            AstNode::False => Ok(self.build_bool(false, gamma, t)?),
-            AstNode::BinOp { lhs, op, rhs } => Ok(self.build_binop(*lhs, op, *rhs, gamma, t)?),
+        let mut frames = vec![Frame::Enter(Premise { ast, gamma, t }, 0)];
+        let mut trees = Vec::<Tree>::new();
+        // Nodes are entered in pre-order, so counting them numbers the nodes like `Tree::preorder` does.
+        let mut node = 0;
+
+        while let Some(frame) = frames.pop() {
+            match frame {
+                Frame::Enter(premise, depth) => {
+                    // The root has depth zero, so a leaf below `max_ast_depth` bracketed expressions is still accepted.
+                    if depth > self.config.max_ast_depth {
+                        return Err(format!(
+                            "the expression is nested more than {} levels deep",
+                            self.config.max_ast_depth
+                        ));
+                    }
+                    let application = self.apply_rule(&premise)?;
+                    // all constraints added by the rule belong to the node it was applied to
+                    self.origins.resize(
+                        self.constraints.len(),
+                        Origin {
+                            node,
+                            rule: premise.ast.name(),
+                        },
+                    );
+                    frames.push(Frame::Exit {
+                        ast: premise.ast,
+                        gamma: premise.gamma,
+                        typ: application.typ,
+                        num_premises: application.premises.len(),
+                        deferred: application.deferred,
+                        node,
+                    });
+                    node += 1;
+                    // Push the premises in reverse, so the leftmost premise is derived first.
+                    frames.extend(
+                        application
+                            .premises
+                            .into_iter()
+                            .rev()
+                            .map(|p| Frame::Enter(p, depth + 1)),
+                    );
+                }
+                Frame::Exit {
+                    ast,
+                    gamma,
+                    typ,
+                    num_premises,
+                    deferred,
+                    node,
+                } => {
+                    if let Some((a, b)) = deferred {
+                        self.add_constraint(&a, &b)?;
+                        self.origins.push(Origin {
+                            node,
+                            rule: ast.name(),
+                        });
+                    }
+                    let constraints = trees.split_off(trees.len() - num_premises);
+                    trees.push(Tree {
+                        gamma,
+                        expr: (ast, typ),
+                        constraints,
+                    });
+                }
+            }
+        }
+        trees
+            .pop()
+            .ok_or("the derivation tree is empty".to_string())
+    }
+
+    // Apply the typing rule matching the node, dispatching on the kind of the node.
+    fn apply_rule(&mut self, premise: &Premise) -> Result<RuleApplication, String> {
+        let Premise { ast, gamma, t } = premise;
+        let t = t.clone();
+        match ast.as_ref() {
+            AstNode::Var(var) => self.build_var(var, gamma, t),
+            AstNode::Abs { var, body } => self.build_abs(var, body, gamma, t),
+            AstNode::App { fun, arg } => self.build_app(fun, arg, gamma, t),
+            AstNode::IsZero(expr) => self.build_zero(expr, gamma, t),
+            AstNode::Int(_) => self.build_int(t),
+            AstNode::True | AstNode::False => self.build_bool(t),
+            AstNode::BinOp { lhs, rhs, .. } => self.build_binop(lhs, rhs, gamma, t),
             AstNode::IfThenElse { cond, then, else_ } => {
-                Ok(self.build_if_then_else(*cond, *then, *else_, gamma, t)?)
+                self.build_if_then_else(cond, then, else_, gamma, t)
             }
-            AstNode::Tuple { fst, snd } => Ok(self.build_tuple(*fst, *snd, gamma, t)?),
-            AstNode::Fst(expr) => Ok(self.build_fst(*expr, gamma, t)?),
-            AstNode::Snd(expr) => Ok(self.build_snd(*expr, gamma, t)?),
+            AstNode::Tuple { fst, snd } => self.build_tuple(fst, snd, gamma, t),
+            AstNode::Fst(expr) => self.build_fst(expr, gamma, t),
+            AstNode::Snd(expr) => self.build_snd(expr, gamma, t),
+            AstNode::Hole(_) => self.build_hole(t),
         }
     }
 
-    // Build a tree, starting from a variable rule.
+    // Apply the variable rule.
     fn build_var(
         &mut self,
-        var: String,
-        gamma: HashMap<String, TypeExpr>,
+        var: &str,
+        gamma: &Rc<HashMap<String, TypeExpr>>,
         t: TypeExpr,
-    ) -> Result<Tree, String> {
+    ) -> Result<RuleApplication, String> {
         // Check if the variable is in the gamma. If it is not, the tree is invalid and a error is returned.
-        dbg!("build var");
         let type_var = gamma
-            .get(&var.to_string())
+            .get(var)
             .ok_or(format!("{} not found!", var).to_string())?;
 
-        self.add_constraint(type_var, &t);
+        self.add_constraint(type_var, &t)?;
 
-        Ok(Tree {
-            gamma,
-            expr: (AstNode::Var(var), t),
-            constraints: Vec::<Tree>::new(),
-        })
+        Ok(RuleApplication::new(t, vec![]))
     }
 
-    // Build a tree, starting from an abstraction rule.
+    // Apply the abstraction rule.
     fn build_abs(
         &mut self,
-        var: String,
-        body: AstNode,
-        gamma: HashMap<String, TypeExpr>,
+        var: &str,
+        body: &Rc<AstNode>,
+        gamma: &Rc<HashMap<String, TypeExpr>>,
         t: TypeExpr,
-    ) -> Result<Tree, String> {
+    ) -> Result<RuleApplication, String> {
         // Check if the type of the expression has already the function form.
-        dbg!("build abs");
-        if let TypeExpr::Function(sigma, tau) = t.clone() {
-            let mut new_gamma = gamma.clone();
-            new_gamma.insert(var.clone(), *sigma);
-            let body_tree = self.build_tree(body.clone(), new_gamma, *tau)?;
-            Ok(Tree {
-                gamma,
-                expr: (
-                    AstNode::Abs {
-                        var,
-                        body: Box::new(body),
-                    },
-                    t,
-                ),
-                constraints: vec![body_tree],
-            })
-        } else {
-            // The type of the expression is not a function, so we need to create a new function type and add a constraint.
-            let sigma = self.new_typ();
-            let tau = self.new_typ();
-            let new_t = TypeExpr::Function(Box::new(sigma.clone()), Box::new(tau.clone()));
-            self.add_constraint(&t, &new_t);
-            let mut new_gamma = gamma.clone();
-            new_gamma.insert(var.clone(), sigma);
-            let body_tree = self.build_tree(body.clone(), new_gamma, tau)?;
-            Ok(Tree {
-                gamma,
-                expr: (
-                    AstNode::Abs {
-                        var,
-                        body: Box::new(body),
-                    },
-                    t,
-                ),
-                constraints: vec![body_tree],
-            })
-        }
+        let (sigma, tau) = match t.clone() {
+            TypeExpr::Function(sigma, tau) if self.reuses_structure() => (*sigma, *tau),
+            _ => {
+                // The type of the expression is not a function, so we need to create a new function type and add a constraint.
+                let sigma = self.new_typ()?;
+                let tau = self.new_typ()?;
+                let new_t = TypeExpr::Function(Box::new(sigma.clone()), Box::new(tau.clone()));
+                self.add_constraint(&t, &new_t)?;
+                (sigma, tau)
+            }
+        };
+        let mut new_gamma = gamma.as_ref().clone();
+        new_gamma.insert(var.to_string(), sigma);
+        let body = Premise::new(body, &Rc::new(new_gamma), tau);
+        Ok(RuleApplication::new(t, vec![body]))
     }
-    // Build a tree, starting from an application rule.
+
+    // Apply the application rule.
     fn build_app(
         &mut self,
-        fun: AstNode,
-        arg: AstNode,
-        gamma: HashMap<String, TypeExpr>,
+        fun: &Rc<AstNode>,
+        arg: &Rc<AstNode>,
+        gamma: &Rc<HashMap<String, TypeExpr>>,
         t: TypeExpr,
-    ) -> Result<Tree, String> {
-        dbg!("build app");
-        let sigma = self.new_typ();
+    ) -> Result<RuleApplication, String> {
+        let sigma = self.new_typ()?;
         let new_t = TypeExpr::Function(Box::new(sigma.clone()), Box::new(t.clone()));
-        let fun_tree = self.build_tree(fun.clone(), gamma.clone(), new_t)?;
-        let arg_tree = self.build_tree(arg.clone(), gamma.clone(), sigma.clone())?;
-        Ok(Tree {
-            gamma,
-            expr: (
-                AstNode::App {
-                    fun: Box::new(fun),
-                    arg: Box::new(arg),
-                },
-                t,
-            ),
-            constraints: vec![fun_tree, arg_tree],
-        })
+        let fun = Premise::new(fun, gamma, new_t);
+        let arg = Premise::new(arg, gamma, sigma);
+        Ok(RuleApplication::new(t, vec![fun, arg]))
     }
 
-    // Build a tree, starting from an iszero rule.
+    // Apply the iszero rule.
     fn build_zero(
         &mut self,
-        expr: AstNode,
-        gamma: HashMap<String, TypeExpr>,
+        expr: &Rc<AstNode>,
+        gamma: &Rc<HashMap<String, TypeExpr>>,
         t: TypeExpr,
-    ) -> Result<Tree, String> {
-        dbg!("build zero");
-        let expr_tree = self.build_tree(expr.clone(), gamma.clone(), TypeExpr::Int)?;
-        self.add_constraint(&t, &TypeExpr::Bool);
-        Ok(Tree {
-            gamma,
-            expr: (AstNode::IsZero(Box::new(expr)), TypeExpr::Bool),
-            constraints: vec![expr_tree],
+    ) -> Result<RuleApplication, String> {
+        let expr = Premise::new(expr, gamma, TypeExpr::Int);
+        Ok(RuleApplication {
+            typ: t.clone(),
+            premises: vec![expr],
+            deferred: Some((t, TypeExpr::Bool)),
         })
     }
-    // Build a tree, starting from an integer rule.
-    fn build_int(
-        &mut self,
-        num: i32,
-        gamma: HashMap<String, TypeExpr>,
-        t: TypeExpr,
-    ) -> Result<Tree, String> {
-        dbg!("build int");
-        self.add_constraint(&t, &TypeExpr::Int);
-        Ok(Tree {
-            gamma,
-            expr: (AstNode::Int(num), t),
-            constraints: vec![],
-        })
+
+    // Apply the integer rule.
+    fn build_int(&mut self, t: TypeExpr) -> Result<RuleApplication, String> {
+        self.add_constraint(&t, &TypeExpr::Int)?;
+        Ok(RuleApplication::new(t, vec![]))
     }
-    // Build a tree, starting from a boolean rule.
-    fn build_bool(
-        &mut self,
-        value: bool,
-        gamma: HashMap<String, TypeExpr>,
-        t: TypeExpr,
-    ) -> Result<Tree, String> {
-        dbg!("build bool");
-        self.add_constraint(&t, &TypeExpr::Bool);
-        Ok(Tree {
-            gamma,
-            expr: (if value { AstNode::True } else { AstNode::False }, t),
-            constraints: vec![],
-        })
+
+    // A typed hole gets a fresh type variable, so it fits any type and the solver tells which one it needs.
+    fn build_hole(&mut self, t: TypeExpr) -> Result<RuleApplication, String> {
+        let hole = self.new_typ()?;
+        self.add_constraint(&t, &hole)?;
+        Ok(RuleApplication::new(t, vec![]))
+    }
+
+    // Apply the boolean rule.
+    fn build_bool(&mut self, t: TypeExpr) -> Result<RuleApplication, String> {
+        self.add_constraint(&t, &TypeExpr::Bool)?;
+        Ok(RuleApplication::new(t, vec![]))
     }
 
-    // Build a tree, starting from a binary operation rule.
+    // Apply the binary operation rule.
     fn build_binop(
         &mut self,
-        lhs: AstNode,
-        op: BinOp,
-        rhs: AstNode,
-        gamma: HashMap<String, TypeExpr>,
+        lhs: &Rc<AstNode>,
+        rhs: &Rc<AstNode>,
+        gamma: &Rc<HashMap<String, TypeExpr>>,
         t: TypeExpr,
-    ) -> Result<Tree, String> {
-        dbg!("build binop");
-        self.add_constraint(&t, &TypeExpr::Int);
-        let lhs_tree = self.build_tree(lhs.clone(), gamma.clone(), TypeExpr::Int)?;
-        let rhs_tree = self.build_tree(rhs.clone(), gamma.clone(), TypeExpr::Int)?;
-
-        Ok(Tree {
-            gamma,
-            expr: (
-                AstNode::BinOp {
-                    op,
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                },
-                t,
-            ),
-            constraints: vec![lhs_tree, rhs_tree],
-        })
+    ) -> Result<RuleApplication, String> {
+        self.add_constraint(&t, &TypeExpr::Int)?;
+        let lhs = Premise::new(lhs, gamma, TypeExpr::Int);
+        let rhs = Premise::new(rhs, gamma, TypeExpr::Int);
+        Ok(RuleApplication::new(t, vec![lhs, rhs]))
     }
 
-    // Build a tree, starting from an if-then-else rule.
+    // Apply the if-then-else rule.
     fn build_if_then_else(
         &mut self,
-        cond: AstNode,
-        then: AstNode,
-        else_: AstNode,
-        gamma: HashMap<String, TypeExpr>,
+        cond: &Rc<AstNode>,
+        then: &Rc<AstNode>,
+        else_: &Rc<AstNode>,
+        gamma: &Rc<HashMap<String, TypeExpr>>,
         t: TypeExpr,
-    ) -> Result<Tree, String> {
-        dbg!("build if then else");
-        let cond_tree = self.build_tree(cond.clone(), gamma.clone(), TypeExpr::Bool)?;
-        let then_tree = self.build_tree(then.clone(), gamma.clone(), t.clone())?;
-        let else_tree = self.build_tree(else_.clone(), gamma.clone(), t.clone())?;
-
-        Ok(Tree {
-            gamma,
-            expr: (
-                AstNode::IfThenElse {
-                    cond: Box::new(cond),
-                    then: Box::new(then),
-                    else_: Box::new(else_),
-                },
-                t,
-            ),
-            constraints: vec![cond_tree, then_tree, else_tree],
-        })
+    ) -> Result<RuleApplication, String> {
+        let cond = Premise::new(cond, gamma, TypeExpr::Bool);
+        let then = Premise::new(then, gamma, t.clone());
+        let else_ = Premise::new(else_, gamma, t.clone());
+        Ok(RuleApplication::new(t, vec![cond, then, else_]))
     }
 
-    // Build a tree, starting from a tuple rule.
+    // Apply the tuple rule.
     fn build_tuple(
         &mut self,
-        fst: AstNode,
-        snd: AstNode,
-        gamma: HashMap<String, TypeExpr>,
+        fst: &Rc<AstNode>,
+        snd: &Rc<AstNode>,
+        gamma: &Rc<HashMap<String, TypeExpr>>,
         t: TypeExpr,
-    ) -> Result<Tree, String> {
-        dbg!("build tuple");
+    ) -> Result<RuleApplication, String> {
         // Check if the type of the expression has already the tuple form.
-        if let TypeExpr::Tuple(a, b) = t.clone() {
-            let fst_tree = self.build_tree(fst.clone(), gamma.clone(), *a)?;
-            let snd_tree = self.build_tree(snd.clone(), gamma.clone(), *b)?;
-
-            Ok(Tree {
-                gamma,
-                expr: (
-                    AstNode::Tuple {
-                        fst: Box::new(fst),
-                        snd: Box::new(snd),
-                    },
-                    t,
-                ),
-                constraints: vec![fst_tree, snd_tree],
-            })
+        if let (TypeExpr::Tuple(a, b), true) = (t.clone(), self.reuses_structure()) {
+            let fst = Premise::new(fst, gamma, *a);
+            let snd = Premise::new(snd, gamma, *b);
+            Ok(RuleApplication::new(t, vec![fst, snd]))
         } else {
             // The type of the expression is not a tuple, so we need to create a new tuple type and add a constraint.
-            let a = self.new_typ();
-            let b = self.new_typ();
+            let a = self.new_typ()?;
+            let b = self.new_typ()?;
             let new_t = TypeExpr::Tuple(Box::new(a.clone()), Box::new(b.clone()));
-            self.add_constraint(&t, &new_t.clone());
-            let fst_tree = self.build_tree(fst.clone(), gamma.clone(), a)?;
-            let snd_tree = self.build_tree(snd.clone(), gamma.clone(), b)?;
-
-            Ok(Tree {
-                gamma,
-                expr: (
-                    AstNode::Tuple {
-                        fst: Box::new(fst),
-                        snd: Box::new(snd),
-                    },
-                    new_t,
-                ),
-                constraints: vec![fst_tree, snd_tree],
-            })
+            self.add_constraint(&t, &new_t)?;
+            let fst = Premise::new(fst, gamma, a);
+            let snd = Premise::new(snd, gamma, b);
+            // Only the course convention keeps the expected type as the conclusion, the other strategies show the tuple type.
+            let typ = match self.config.generation {
+                Generation::CourseConvention => t,
+                _ => new_t,
+            };
+            Ok(RuleApplication::new(typ, vec![fst, snd]))
         }
     }
 
-    // Build a tree, starting from a first projection rule.
+    // Apply the first projection rule.
     fn build_fst(
         &mut self,
-        expr: AstNode,
-        gamma: HashMap<String, TypeExpr>,
+        expr: &Rc<AstNode>,
+        gamma: &Rc<HashMap<String, TypeExpr>>,
         t: TypeExpr,
-    ) -> Result<Tree, String> {
-        dbg!("build fst");
-        let b = self.new_typ();
+    ) -> Result<RuleApplication, String> {
+        let b = self.new_typ()?;
         let new_t = TypeExpr::Tuple(Box::new(t.clone()), Box::new(b));
-
-        let expr_tree = self.build_tree(expr.clone(), gamma.clone(), new_t)?;
-
-        Ok(Tree {
-            gamma,
-            expr: (AstNode::Fst(Box::new(expr)), t),
-            constraints: vec![expr_tree],
-        })
+        let expr = Premise::new(expr, gamma, new_t);
+        Ok(RuleApplication::new(t, vec![expr]))
     }
 
-    // Build a tree, starting from a second projection rule.
+    // Apply the second projection rule.
     fn build_snd(
         &mut self,
-        expr: AstNode,
-        gamma: HashMap<String, TypeExpr>,
+        expr: &Rc<AstNode>,
+        gamma: &Rc<HashMap<String, TypeExpr>>,
         t: TypeExpr,
-    ) -> Result<Tree, String> {
-        dbg!("build snd");
-        let a = self.new_typ();
+    ) -> Result<RuleApplication, String> {
+        let a = self.new_typ()?;
         let new_t = TypeExpr::Tuple(Box::new(a), Box::new(t.clone()));
-
-        let expr_tree = self.build_tree(expr.clone(), gamma.clone(), new_t)?;
-
-        let tree = Tree {
-            gamma,
-            expr: (AstNode::Snd(Box::new(expr)), t),
-            constraints: vec![expr_tree],
-        };
-        Ok(tree)
+        let expr = Premise::new(expr, gamma, new_t);
+        Ok(RuleApplication::new(t, vec![expr]))
     }
 }
@@ -0,0 +1,254 @@
+use std::{collections::HashMap, rc::Rc};
+
+use pest::{iterators::Pair, Parser};
+
+use super::{config::Config, parser::MiniHaskellParser, rules::TypeExpr, tree::Tree};
+
+/// A derivation tree written by a student, see `TypeParser::parse_derivation` for the format.
+/// The rule names and line numbers of the nodes are listed in pre-order.
+#[derive(Debug, Clone)]
+pub struct Derivation {
+    pub tree: Tree,
+    pub rules: Vec<String>,
+    pub lines: Vec<usize>,
+}
+
+/// Parser for types, lists of constraints and derivation trees, as they are written by students.
+#[derive(pest_derive::Parser)]
+#[grammar = "./typers/types.pest"]
+pub struct TypeParser;
+
+impl TypeParser {
+    /// Parses a single type like `t1 -> (Int, t2)`.
+    pub fn parse_type(input: &str) -> Result<TypeExpr, String> {
+        Self::parse_type_with_config(input, &Config::default())
+    }
+
+    /// Parses a single type, rejecting inputs which exceed the limits of the configuration.
+    pub fn parse_type_with_config(input: &str, config: &Config) -> Result<TypeExpr, String> {
+        config.check_input(input)?;
+        let mut parsed = Self::parse(Rule::type_main, input).map_err(|e| format!("{}", e))?;
+        let typ = parsed.next().ok_or("no type".to_string())?;
+        Ok(Self::build_type(typ, config)?.0)
+    }
+
+    /// Parses constraints like `t0 = t1 -> t2`, one per line or separated by semicolons.
+    pub fn parse_constraints(input: &str) -> Result<Vec<(TypeExpr, TypeExpr)>, String> {
+        Self::parse_constraints_with_config(input, &Config::default())
+    }
+
+    /// Parses constraints, rejecting inputs which exceed the limits of the configuration.
+    pub fn parse_constraints_with_config(
+        input: &str,
+        config: &Config,
+    ) -> Result<Vec<(TypeExpr, TypeExpr)>, String> {
+        config.check_input(input)?;
+        let mut parsed = Self::parse(Rule::constraints, input).map_err(|e| format!("{}", e))?;
+        let constraints = parsed.next().ok_or("no constraints".to_string())?;
+
+        let mut result = vec![];
+        for constraint in constraints.into_inner() {
+            if constraint.as_rule() != Rule::constraint {
+                continue;
+            }
+            let mut sides = constraint.into_inner();
+            let lhs = sides.next().ok_or("no left hand side".to_string())?;
+            let rhs = sides.next().ok_or("no right hand side".to_string())?;
+            result.push((
+                Self::build_type(lhs, config)?.0,
+                Self::build_type(rhs, config)?.0,
+            ));
+        }
+        if result.len() > config.max_constraints {
+            return Err(format!(
+                "there are more than {} constraints",
+                config.max_constraints
+            ));
+        }
+        Ok(result)
+    }
+
+    /// Parses a derivation tree with one judgment per line like `[App] x: t1 |- (f x) :: t0`.
+    /// The rule name comes first, then the context, the expression and its type. `⊢` may be used instead of `|-`.
+    /// The premises of a judgment follow on the next lines and are indented further than it, empty lines are ignored.
+    pub fn parse_derivation(input: &str) -> Result<Derivation, String> {
+        Self::parse_derivation_with_config(input, &Config::default())
+    }
+
+    /// Parses a derivation tree, rejecting inputs which exceed the limits of the configuration.
+    pub fn parse_derivation_with_config(
+        input: &str,
+        config: &Config,
+    ) -> Result<Derivation, String> {
+        config.check_input(input)?;
+
+        // The nodes are read in pre-order, the stack holds the open judgments whose premises may still follow.
+        // A node is finished and attached to its parent once a line is indented less or as far as it.
+        let mut rules = vec![];
+        let mut lines = vec![];
+        let mut stack: Vec<(usize, Tree)> = vec![];
+        let mut root: Option<Tree> = None;
+        for (number, line) in input.lines().enumerate() {
+            let number = number + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let indent: usize = line
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .map(|c| if c == '\t' { 4 } else { 1 })
+                .sum();
+            while stack.last().is_some_and(|(open, _)| *open >= indent) {
+                if let Some((_, tree)) = stack.pop() {
+                    match stack.last_mut() {
+                        Some((_, parent)) => parent.constraints.push(tree),
+                        None => root = Some(tree),
+                    }
+                }
+            }
+            if root.is_some() {
+                return Err(format!(
+                    "line {}: the tree has only one root, premises have to be indented further than their conclusion",
+                    number
+                ));
+            }
+            if stack.len() >= config.max_ast_depth {
+                return Err(format!(
+                    "line {}: the tree is nested more than {} levels deep",
+                    number, config.max_ast_depth
+                ));
+            }
+            let (rule, tree) = Self::parse_judgment(line.trim(), config)
+                .map_err(|e| format!("line {}: {}", number, e))?;
+            rules.push(rule);
+            lines.push(number);
+            stack.push((indent, tree));
+        }
+        while let Some((_, tree)) = stack.pop() {
+            match stack.last_mut() {
+                Some((_, parent)) => parent.constraints.push(tree),
+                None => root = Some(tree),
+            }
+        }
+        let tree = root.ok_or("the derivation tree is empty".to_string())?;
+        Ok(Derivation { tree, rules, lines })
+    }
+
+    /// Parses a single judgment `[Rule] context |- expression :: type` into a node without premises.
+    fn parse_judgment(line: &str, config: &Config) -> Result<(String, Tree), String> {
+        let rest = line.strip_prefix('[').ok_or(
+            "a judgment starts with the name of its rule in brackets, like [App]".to_string(),
+        )?;
+        let (rule, rest) = rest
+            .split_once(']')
+            .ok_or("the name of the rule is not closed with ]".to_string())?;
+        let rule = rule.trim();
+        if rule.is_empty()
+            || !rule
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '*')
+        {
+            return Err(format!("{} is not the name of a rule", rule));
+        }
+        let (context, rest) = rest
+            .split_once("|-")
+            .or_else(|| rest.split_once('⊢'))
+            .ok_or("the judgment has no |-".to_string())?;
+        let (expr, typ) = rest
+            .rsplit_once("::")
+            .ok_or("the judgment has no :: before the type".to_string())?;
+
+        let gamma = Self::parse_context(context.trim(), config)?;
+        let expr = expr.trim();
+        let parsed = MiniHaskellParser::parse_str_with_config(expr, config)?;
+        if parsed.as_str().len() != expr.len() {
+            return Err(format!("{} is not a single expression", expr));
+        }
+        let ast = MiniHaskellParser::build_ast(parsed)?;
+        let typ = Self::parse_type_with_config(typ.trim(), config)?;
+        Ok((
+            rule.to_string(),
+            Tree {
+                gamma: Rc::new(gamma),
+                expr: (Rc::new(ast), typ),
+                constraints: vec![],
+            },
+        ))
+    }
+
+    /// Parses a context like `x: t1, f: Int -> t2`, every variable may only be bound once.
+    fn parse_context(input: &str, config: &Config) -> Result<HashMap<String, TypeExpr>, String> {
+        let mut parsed = Self::parse(Rule::context, input).map_err(|e| format!("{}", e))?;
+        let context = parsed.next().ok_or("no context".to_string())?;
+        let mut gamma = HashMap::new();
+        for binding in context.into_inner() {
+            if binding.as_rule() != Rule::binding {
+                continue;
+            }
+            let mut inner = binding.into_inner();
+            let name = inner.next().ok_or("no variable".to_string())?.as_str();
+            let typ = inner.next().ok_or("no type".to_string())?;
+            if gamma
+                .insert(name.to_string(), Self::build_type(typ, config)?.0)
+                .is_some()
+            {
+                return Err(format!("{} is bound twice in the context", name));
+            }
+        }
+        Ok(gamma)
+    }
+
+    /// Builds a type from a `Pair` of the rule `typ`, together with its nesting depth.
+    /// Only parenthesis lead to recursion, their depth is bounded by the configuration.
+    fn build_type(pair: Pair<Rule>, config: &Config) -> Result<(TypeExpr, usize), String> {
+        let mut atoms = pair
+            .into_inner()
+            .map(|atom| Self::build_atom(atom, config))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        // fold the arrows from the right, `a -> b -> c` is `a -> (b -> c)`
+        let (mut typ, mut depth) = atoms.pop().ok_or("empty type".to_string())?;
+        while let Some((arg, arg_depth)) = atoms.pop() {
+            typ = TypeExpr::Function(Box::new(arg), Box::new(typ));
+            depth = depth.max(arg_depth) + 1;
+        }
+        if depth > config.max_ast_depth {
+            return Err(format!(
+                "the type is nested more than {} levels deep",
+                config.max_ast_depth
+            ));
+        }
+        Ok((typ, depth))
+    }
+
+    /// Builds a type variable, a base type or a type in parenthesis.
+    fn build_atom(pair: Pair<Rule>, config: &Config) -> Result<(TypeExpr, usize), String> {
+        match pair.as_rule() {
+            Rule::type_var => {
+                let digits: String = pair.as_str().chars().filter(char::is_ascii_digit).collect();
+                let var = digits
+                    .parse()
+                    .map_err(|_| format!("{} is not a valid type variable", pair.as_str()))?;
+                Ok((TypeExpr::Var(var), 0))
+            }
+            Rule::int_type => Ok((TypeExpr::Int, 0)),
+            Rule::bool_type => Ok((TypeExpr::Bool, 0)),
+            Rule::paren => {
+                let mut inner = pair.into_inner();
+                let first = inner.next().ok_or("empty parenthesis".to_string())?;
+                let (first, first_depth) = Self::build_type(first, config)?;
+                match inner.next() {
+                    Some(second) => {
+                        let (second, second_depth) = Self::build_type(second, config)?;
+                        Ok((
+                            TypeExpr::Tuple(Box::new(first), Box::new(second)),
+                            first_depth.max(second_depth) + 1,
+                        ))
+                    }
+                    None => Ok((first, first_depth)),
+                }
+            }
+            e => Err(format!("unexpected rule: {:?}", e)),
+        }
+    }
+}
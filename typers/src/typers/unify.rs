@@ -0,0 +1,324 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use crate::typers::{
+    config::Config,
+    rules::{RuleExpr, TypeExpr},
+    solver::Solution,
+    utils::mathjax::MathJax,
+};
+
+/// Solves the constraints with a union-find structure instead of rewriting the rules step by step.
+/// It does not record any steps, but gives the same result as `solve_constraints`:
+/// every class of equal variables is named after its smallest variable, exactly like the remove steps do.
+/// The equations which are not rules are unified right away instead of being decomposed.
+pub fn solve_constraints_fast(
+    rules: Vec<RuleExpr>,
+    equations: &[(TypeExpr, TypeExpr)],
+    goal_var: usize,
+    config: &Config,
+) -> Solution {
+    let mut solution = Solution::with_equations(&rules, equations);
+
+    if rules.len() > config.max_constraints {
+        solution.result = Some(Err(format!(
+            "the solver has to handle more than {} constraints, aborting ...",
+            config.max_constraints
+        )));
+        return solution;
+    }
+
+    let mut unifier = Unifier::default();
+    for rule in &rules {
+        if let Err(e) = unifier.unify(&TypeExpr::Var(rule.var), &rule.rhs) {
+            solution.result = Some(Err(e));
+            return solution;
+        }
+    }
+    for (a, b) in equations {
+        if let Err(e) = unifier.unify(a, b) {
+            solution.result = Some(Err(e));
+            return solution;
+        }
+    }
+
+    if let Err(e) = unifier.check_cycles() {
+        solution.result = Some(Err(e));
+        return solution;
+    }
+
+    // like in the step solver, an unbound goal variable can have any type
+    let rhs = match unifier.resolve(&TypeExpr::Var(goal_var)) {
+        Ok(rhs) => rhs,
+        Err(e) => {
+            solution.result = Some(Err(e));
+            return solution;
+        }
+    };
+
+    // without steps to replay, `Solution::substitution` needs the type of every variable which is not free.
+    // The types are not resolved, that would make them as large as the result each and the bindings quadratic.
+    let bindings = solution
+        .variables
+        .clone()
+        .into_iter()
+        .filter_map(|var| {
+            unifier.binding(var).map(|rhs| RuleExpr {
+                var,
+                rhs: Box::new(rhs),
+                origin: None,
+            })
+        })
+        .collect();
+    solution.bindings = Some(bindings);
+    solution.result = Some(Ok(RuleExpr {
+        var: goal_var,
+        rhs: Box::new(rhs),
+        origin: None,
+    }));
+    solution
+}
+
+/// A class of type variables which are known to be equal.
+#[derive(Debug, Clone)]
+struct Class {
+    /// Number of variables in the class, used to keep the trees flat
+    size: usize,
+    /// The smallest variable of the class, it names the class in the result
+    name: usize,
+    /// The type all variables of the class are equal to, if it is known
+    binding: Option<TypeExpr>,
+}
+
+/// Union-find structure over type variables with path compression.
+/// Unifying never recurses, so arbitrarily large and deep types can be handled.
+#[derive(Debug, Clone, Default)]
+pub struct Unifier {
+    parent: HashMap<usize, usize>,
+    classes: HashMap<usize, Class>,
+}
+
+impl Unifier {
+    /// Returns the representative of the class of the variable, compressing the path to it
+    fn find(&mut self, var: usize) -> usize {
+        let mut root = var;
+        while let Some(&parent) = self.parent.get(&root) {
+            if parent == root {
+                break;
+            }
+            root = parent;
+        }
+        if let Entry::Vacant(entry) = self.parent.entry(root) {
+            // First time the variable is seen, it forms a class on its own
+            entry.insert(root);
+            self.classes.insert(
+                root,
+                Class {
+                    size: 1,
+                    name: root,
+                    binding: None,
+                },
+            );
+        }
+
+        // Path compression, every variable on the way now points to the root directly
+        let mut current = var;
+        while current != root {
+            let next = self.parent[&current];
+            self.parent.insert(current, root);
+            current = next;
+        }
+        root
+    }
+
+    /// Merges the classes of both variables, returns the bindings of both classes if both had one
+    fn union(&mut self, a: usize, b: usize) -> Option<(TypeExpr, TypeExpr)> {
+        let a = self.find(a);
+        let b = self.find(b);
+        if a == b {
+            return None;
+        }
+        let class_a = self.classes.remove(&a)?;
+        let class_b = self.classes.remove(&b)?;
+        let (root, child) = if class_a.size >= class_b.size {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parent.insert(child, root);
+
+        let (binding, conflict) = match (class_a.binding, class_b.binding) {
+            (Some(x), Some(y)) => (Some(x.clone()), Some((x, y))),
+            (x, y) => (x.or(y), None),
+        };
+        self.classes.insert(
+            root,
+            Class {
+                size: class_a.size + class_b.size,
+                name: class_a.name.min(class_b.name),
+                binding,
+            },
+        );
+        conflict
+    }
+
+    /// The type of the variable without substituting the variables in it, `None` if the variable is free.
+    /// The variable which names a class maps to the binding of the class, the other variables of the class map to that one.
+    pub fn binding(&mut self, var: usize) -> Option<TypeExpr> {
+        let root = self.find(var);
+        let class = &self.classes[&root];
+        if class.name != var {
+            return Some(TypeExpr::Var(class.name));
+        }
+        class.binding.clone()
+    }
+
+    /// Checks if the class of the variable is equal to a type which is not a variable
+    pub fn is_bound(&mut self, var: usize) -> bool {
+        let root = self.find(var);
+        self.classes[&root].binding.is_some()
+    }
+
+    /// Adds the equation `a = b`, fails if the types have different constructors
+    pub fn unify(&mut self, a: &TypeExpr, b: &TypeExpr) -> Result<(), String> {
+        let mut work = vec![(a.clone(), b.clone())];
+        while let Some((a, b)) = work.pop() {
+            match (a, b) {
+                (TypeExpr::Var(x), TypeExpr::Var(y)) => {
+                    if let Some(conflict) = self.union(x, y) {
+                        work.push(conflict);
+                    }
+                }
+                (TypeExpr::Var(x), other) | (other, TypeExpr::Var(x)) => {
+                    let root = self.find(x);
+                    let class = self
+                        .classes
+                        .get_mut(&root)
+                        .ok_or(format!("\\(t_{{{x}}}\\) has no class"))?;
+                    match &class.binding {
+                        Some(binding) => work.push((binding.clone(), other)),
+                        None => class.binding = Some(other),
+                    }
+                }
+                (TypeExpr::Function(a1, a2), TypeExpr::Function(b1, b2))
+                | (TypeExpr::Tuple(a1, a2), TypeExpr::Tuple(b1, b2)) => {
+                    work.push((*a2, *b2));
+                    work.push((*a1, *b1));
+                }
+                (TypeExpr::Int, TypeExpr::Int) | (TypeExpr::Bool, TypeExpr::Bool) => (),
+                (a, b) => {
+                    return Err(format!(
+                        "impossible to unify \\({}\\) and \\({}\\)",
+                        a.to_mathjax(),
+                        b.to_mathjax()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Occurs check: fails if a variable is bound to a type which contains the variable itself
+    pub fn check_cycles(&mut self) -> Result<(), String> {
+        // Edges lead from the representative of a class to the representatives of all variables in its binding
+        let roots: Vec<usize> = self.classes.keys().copied().collect();
+        let mut edges = HashMap::<usize, Vec<usize>>::new();
+        for root in roots {
+            let vars = match &self.classes[&root].binding {
+                Some(binding) => binding.all_vars(),
+                None => continue,
+            };
+            let targets = vars.into_iter().map(|v| self.find(v)).collect();
+            edges.insert(root, targets);
+        }
+
+        // Iterative depth first search, a back edge to a class on the current path is a cycle
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            OnPath,
+            Done,
+        }
+        let mut state = HashMap::<usize, State>::new();
+        for &start in edges.keys() {
+            if state.contains_key(&start) {
+                continue;
+            }
+            let mut stack = vec![(start, 0)];
+            state.insert(start, State::OnPath);
+            while let Some((node, index)) = stack.pop() {
+                let next = edges.get(&node).and_then(|targets| targets.get(index));
+                match next {
+                    Some(&next) => {
+                        stack.push((node, index + 1));
+                        match state.get(&next) {
+                            Some(State::OnPath) => {
+                                return Err(
+                                    "detected cycle in constraints, cannot proceed ...".to_string()
+                                );
+                            }
+                            Some(State::Done) => (),
+                            None => {
+                                state.insert(next, State::OnPath);
+                                stack.push((next, 0));
+                            }
+                        }
+                    }
+                    None => {
+                        state.insert(node, State::Done);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces every variable by its binding until only unbound variables are left, those are named after their class.
+    /// Assumes that `check_cycles` succeeded, otherwise this does not terminate.
+    /// Fails only if the traversal gets out of step, which is a bug.
+    pub fn resolve(&mut self, typ: &TypeExpr) -> Result<TypeExpr, String> {
+        // Post order traversal with an explicit stack, finished subtypes are collected in `done`
+        enum Task {
+            Visit(TypeExpr),
+            Function,
+            Tuple,
+        }
+        let mut tasks = vec![Task::Visit(typ.clone())];
+        let mut done = Vec::<TypeExpr>::new();
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Visit(TypeExpr::Var(x)) => {
+                    let root = self.find(x);
+                    let class = &self.classes[&root];
+                    match &class.binding {
+                        Some(binding) => tasks.push(Task::Visit(binding.clone())),
+                        None => done.push(TypeExpr::Var(class.name)),
+                    }
+                }
+                Task::Visit(TypeExpr::Function(a, b)) => {
+                    tasks.push(Task::Function);
+                    tasks.push(Task::Visit(*b));
+                    tasks.push(Task::Visit(*a));
+                }
+                Task::Visit(TypeExpr::Tuple(a, b)) => {
+                    tasks.push(Task::Tuple);
+                    tasks.push(Task::Visit(*b));
+                    tasks.push(Task::Visit(*a));
+                }
+                Task::Visit(other) => done.push(other),
+                Task::Function | Task::Tuple => {
+                    // Both subtypes were visited right before, so they are the last two finished types
+                    let missing = || "resolving a type lost one of its parts".to_string();
+                    let b = done.pop().ok_or_else(missing)?;
+                    let a = done.pop().ok_or_else(missing)?;
+                    done.push(match task {
+                        Task::Function => TypeExpr::Function(Box::new(a), Box::new(b)),
+                        _ => TypeExpr::Tuple(Box::new(a), Box::new(b)),
+                    });
+                }
+            }
+        }
+        match (done.pop(), done.is_empty()) {
+            (Some(typ), true) => Ok(typ),
+            _ => Err("resolving a type did not end with exactly one type".to_string()),
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::typers::{parser::AstNode, rules::RuleExpr, solver::dependency_graph, tree::Tree};
+
+/// The structures which can be exported as a Graphviz graph
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DotGraph {
+    /// The abstract syntax tree of the expression
+    Ast,
+    /// The derivation tree with one node per judgment
+    Tree,
+    /// The dependency graph of the type variables after simplifying the constraints
+    Constraints,
+}
+
+pub trait Dot {
+    fn to_dot(&self) -> String;
+}
+
+// escapes text, so it can be used inside a quoted DOT string
+fn quote(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+// the label of a single AST node, without its subexpressions
+fn ast_label(ast: &AstNode) -> String {
+    match ast {
+        AstNode::Var(var) => var.clone(),
+        AstNode::Abs { var, .. } => format!("λ{}", var),
+        AstNode::Int(int) => int.to_string(),
+        AstNode::True => "true".to_string(),
+        AstNode::False => "false".to_string(),
+        AstNode::BinOp { op, .. } => op.to_string(),
+        AstNode::Hole(name) => format!("_{}", name),
+        _ => ast.name(),
+    }
+}
+
+// names of the edges to the subexpressions, in the order of `AstNode::children`
+fn ast_edges(ast: &AstNode) -> &'static [&'static str] {
+    match ast {
+        AstNode::Abs { .. } => &["body"],
+        AstNode::App { .. } => &["fun", "arg"],
+        AstNode::BinOp { .. } => &["lhs", "rhs"],
+        AstNode::IfThenElse { .. } => &["cond", "then", "else"],
+        AstNode::Tuple { .. } => &["fst", "snd"],
+        _ => &[""],
+    }
+}
+
+impl Dot for AstNode {
+    // one node per subexpression, the edges are labeled with the role of the subexpression
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph ast {\n  node [shape=box, fontname=\"monospace\"];\n");
+        let mut next_id = 1;
+        let mut stack = vec![(self, 0)];
+        while let Some((ast, id)) = stack.pop() {
+            out.push_str(&format!("  n{} [label={}];\n", id, quote(&ast_label(ast))));
+            for (child, edge) in ast
+                .children()
+                .into_iter()
+                .zip(ast_edges(ast).iter().cycle())
+            {
+                out.push_str(&format!(
+                    "  n{} -> n{} [label={}];\n",
+                    id,
+                    next_id,
+                    quote(edge)
+                ));
+                stack.push((child, next_id));
+                next_id += 1;
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+impl Dot for Tree {
+    // one node per judgment, with an edge from every conclusion to its premises
+    fn to_dot(&self) -> String {
+        let mut out = String::from(
+            "digraph derivation {\n  rankdir=BT;\n  node [shape=box, fontname=\"monospace\"];\n",
+        );
+        let mut next_id = 1;
+        let mut stack = vec![(self, 0)];
+        while let Some((tree, id)) = stack.pop() {
+            let gamma = tree
+                .sorted_gamma()
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, v))
+                .collect::<Vec<String>>()
+                .join(", ");
+            let label = format!(
+                "{} ⊢ {} :: {}\n({})",
+                gamma,
+                tree.expr.0,
+                tree.expr.1,
+                tree.expr.0.name()
+            );
+            let style = if id == 0 { ", style=bold" } else { "" };
+            out.push_str(&format!(
+                "  n{} [label={}{}];\n",
+                id,
+                quote(label.trim_start()),
+                style
+            ));
+            for premise in &tree.constraints {
+                // premises point to their conclusion, so the root ends up at the bottom like in a proof tree
+                out.push_str(&format!("  n{} -> n{};\n", next_id, id));
+                stack.push((premise, next_id));
+                next_id += 1;
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// The dependency graph of the type variables in the rules, every node lists the rules for its variable.
+/// Variables on a cycle and the edges between them are red, the goal variable has a double circle.
+pub fn constraint_graph(rules: &[RuleExpr], goal_var: usize) -> String {
+    let graph = dependency_graph(rules);
+    let cyclic: BTreeMap<usize, usize> = cycles(&graph)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, component)| component.into_iter().map(move |v| (v, i)))
+        .collect();
+
+    let mut bindings = BTreeMap::<usize, Vec<String>>::new();
+    for rule in rules {
+        bindings
+            .entry(rule.var)
+            .or_default()
+            .push(format!("= {}", rule.rhs));
+    }
+    let mut nodes: BTreeSet<usize> = graph.values().flatten().copied().collect();
+    nodes.extend(graph.keys());
+    nodes.insert(goal_var);
+
+    let mut out = String::from(
+        "digraph constraints {\n  rankdir=LR;\n  node [shape=circle, fontname=\"monospace\"];\n",
+    );
+    for v in &nodes {
+        let mut label = format!("t{}", v);
+        for binding in bindings.get(v).into_iter().flatten() {
+            label.push('\n');
+            label.push_str(binding);
+        }
+        let mut attributes = vec![format!("label={}", quote(&label))];
+        if bindings.contains_key(v) {
+            attributes.push("shape=box".to_string());
+        }
+        if *v == goal_var {
+            attributes.push("peripheries=2".to_string());
+            attributes.push("style=bold".to_string());
+        }
+        if cyclic.contains_key(v) {
+            attributes.push("color=red".to_string());
+            attributes.push("fontcolor=red".to_string());
+        }
+        out.push_str(&format!("  t{} [{}];\n", v, attributes.join(", ")));
+    }
+    for (u, edges) in &graph {
+        for v in edges {
+            let on_cycle = matches!((cyclic.get(u), cyclic.get(v)), (Some(a), Some(b)) if a == b);
+            let style = if on_cycle {
+                " [color=red, penwidth=2]"
+            } else {
+                ""
+            };
+            out.push_str(&format!("  t{} -> t{}{};\n", u, v, style));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Strongly connected components which contain a cycle, found with Tarjan's algorithm.
+/// A single variable only counts if it depends on itself.
+fn cycles(graph: &BTreeMap<usize, BTreeSet<usize>>) -> Vec<BTreeSet<usize>> {
+    let successors = |v: usize| -> Vec<usize> {
+        graph
+            .get(&v)
+            .map(|edges| edges.iter().copied().collect())
+            .unwrap_or_default()
+    };
+
+    let mut index = HashMap::<usize, usize>::new();
+    let mut low = HashMap::<usize, usize>::new();
+    let mut on_stack = BTreeSet::<usize>::new();
+    let mut stack = Vec::<usize>::new();
+    let mut components = vec![];
+
+    for &root in graph.keys() {
+        if index.contains_key(&root) {
+            continue;
+        }
+        // Explicit call stack of the depth first search, with the position in the list of successors
+        let mut calls = vec![(root, successors(root), 0)];
+        index.insert(root, index.len());
+        low.insert(root, index[&root]);
+        stack.push(root);
+        on_stack.insert(root);
+
+        while let Some((v, next, i)) = calls.last_mut() {
+            let v = *v;
+            if let Some(&w) = next.get(*i) {
+                *i += 1;
+                if !index.contains_key(&w) {
+                    index.insert(w, index.len());
+                    low.insert(w, index[&w]);
+                    stack.push(w);
+                    on_stack.insert(w);
+                    calls.push((w, successors(w), 0));
+                } else if on_stack.contains(&w) {
+                    low.insert(v, low[&v].min(index[&w]));
+                }
+                continue;
+            }
+
+            calls.pop();
+            if let Some((parent, _, _)) = calls.last() {
+                low.insert(*parent, low[parent].min(low[&v]));
+            }
+            if low[&v] == index[&v] {
+                let mut component = BTreeSet::new();
+                while let Some(w) = stack.pop() {
+                    on_stack.remove(&w);
+                    component.insert(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                let self_loop = graph.get(&v).is_some_and(|edges| edges.contains(&v));
+                if component.len() > 1 || self_loop {
+                    components.push(component);
+                }
+            }
+        }
+    }
+    components
+}
@@ -1,11 +1,17 @@
-use std::fmt::{Display, Formatter, Result};
+use std::{
+    borrow::Cow,
+    fmt::{Display, Formatter, Result},
+};
 
 use crate::typers::{
+    grading::Grade,
     parser::{AstNode, BinOp},
     rules::{RuleExpr, TypeExpr},
     tree::Tree,
 };
 
+use super::text::{render_tree, TextOptions};
+
 impl Display for BinOp {
     fn fmt(&self, f: &mut Formatter) -> Result {
         match self {
@@ -15,50 +21,94 @@ impl Display for BinOp {
     }
 }
 
+/// A piece of the output, either finished text or a node which still needs to be written.
+enum Item<'a> {
+    Text(Cow<'a, str>),
+    Ast(&'a AstNode),
+}
+
+impl<'a> Item<'a> {
+    fn text(text: &'a str) -> Self {
+        Item::Text(Cow::Borrowed(text))
+    }
+
+    fn owned(text: String) -> Self {
+        Item::Text(Cow::Owned(text))
+    }
+}
+
+/// Writes the item with an explicit stack instead of recursion, so deep trees cannot overflow the call stack.
+fn write_item(f: &mut Formatter, item: Item) -> Result {
+    let mut stack = vec![item];
+    while let Some(item) = stack.pop() {
+        let pieces = match item {
+            Item::Text(text) => {
+                f.write_str(&text)?;
+                continue;
+            }
+            Item::Ast(ast) => ast_pieces(ast),
+        };
+        stack.extend(pieces.into_iter().rev());
+    }
+    Ok(())
+}
+
+fn ast_pieces(ast: &AstNode) -> Vec<Item<'_>> {
+    match ast {
+        AstNode::Var(var) => vec![Item::text(var)],
+        AstNode::Abs { var, body } => vec![Item::owned(format!("\\{} -> ", var)), Item::Ast(body)],
+        AstNode::App { fun, arg } => vec![
+            Item::text("("),
+            Item::Ast(fun),
+            Item::text(" "),
+            Item::Ast(arg),
+            Item::text(")"),
+        ],
+        AstNode::IsZero(expr) => vec![Item::text("iszero "), Item::Ast(expr)],
+        AstNode::Int(int) => vec![Item::owned(int.to_string())],
+        AstNode::True => vec![Item::text("true")],
+        AstNode::False => vec![Item::text("false")],
+        AstNode::BinOp { op, lhs, rhs } => vec![
+            Item::text("("),
+            Item::Ast(lhs),
+            Item::owned(format!(" {} ", op)),
+            Item::Ast(rhs),
+            Item::text(")"),
+        ],
+        AstNode::IfThenElse { cond, then, else_ } => vec![
+            Item::text("if "),
+            Item::Ast(cond),
+            Item::text(" then "),
+            Item::Ast(then),
+            Item::text(" else "),
+            Item::Ast(else_),
+        ],
+        AstNode::Tuple { fst, snd } => vec![
+            Item::text("("),
+            Item::Ast(fst),
+            Item::text(", "),
+            Item::Ast(snd),
+            Item::text(")"),
+        ],
+        AstNode::Fst(expr) => vec![Item::text("fst "), Item::Ast(expr)],
+        AstNode::Snd(expr) => vec![Item::text("snd "), Item::Ast(expr)],
+        AstNode::Hole(name) => vec![Item::owned(format!("_{}", name))],
+    }
+}
+
 impl Display for AstNode {
     fn fmt(&self, f: &mut Formatter) -> Result {
-        match self {
-            AstNode::Var(var) => write!(f, "{}", var),
-            AstNode::Abs { var, body } => write!(f, "\\{} -> {}", var, body),
-            AstNode::App { fun, arg } => write!(f, "({} {})", fun, arg),
-            AstNode::IsZero(expr) => write!(f, "iszero {}", expr),
-            AstNode::Int(int) => write!(f, "{}", int),
-            AstNode::True => write!(f, "true"),
-            AstNode::False => write!(f, "false"),
-            AstNode::BinOp { op, lhs, rhs } => write!(f, "({} {} {})", lhs, op, rhs),
-            AstNode::IfThenElse { cond, then, else_ } => {
-                write!(f, "if {} then {} else {}", cond, then, else_)
-            }
-            AstNode::Tuple { fst, snd } => write!(f, "({}, {})", fst, snd),
-            AstNode::Fst(expr) => write!(f, "fst {}", expr),
-            AstNode::Snd(expr) => write!(f, "snd {}", expr),
-        }
+        write_item(f, Item::Ast(self))
     }
 }
 
 impl Display for Tree {
+    /// Displays the tree as a two dimensional proof tree, see `text.rs`
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let gamma = self
-            .gamma
-            .iter()
-            .map(|(k, v)| format!("{}: {}", k, v))
-            .collect::<Vec<String>>()
-            .join(", ");
-        let bottom = format!("{} ⊢ {} :: {}", gamma, self.expr.0, self.expr.1);
-        let num_constraints = self.constraints.len();
-        let constraints = self
-            .constraints
-            .iter()
-            .map(|a| format!("{}", a))
-            .collect::<Vec<String>>()
-            .join("\n");
-        write!(
-            f,
-            "{}\nrule(n: {}, \"{}\", label:\"{}\"),",
-            constraints, num_constraints, bottom, self.expr.0
-        )
+        f.write_str(&render_tree(self, &TextOptions::default()))
     }
 }
+
 impl AstNode {
     pub fn name(&self) -> String {
         let res = match self {
@@ -74,6 +124,7 @@ impl AstNode {
             AstNode::Tuple { .. } => "tuple",
             AstNode::Fst(_) => "fst",
             AstNode::Snd(_) => "snd",
+            AstNode::Hole(_) => "Hole",
         };
         res.to_string()
     }
@@ -118,3 +169,31 @@ impl Display for RuleExpr {
         write!(f, "t{} = {}", self.var, self.rhs)
     }
 }
+
+impl Display for Grade {
+    /// A report for the terminal, one line per constraint of the student and per missing constraint
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for ((a, b), (c, d)) in &self.correct {
+            writeln!(f, "correct  {a} = {b}, as {c} = {d}")?;
+        }
+        for ((a, b), (c, d)) in &self.wrong {
+            writeln!(f, "wrong    {a} = {b}, expected {c} = {d}")?;
+        }
+        for (a, b) in &self.extra {
+            writeln!(f, "extra    {a} = {b}")?;
+        }
+        for (a, b) in &self.missing {
+            writeln!(f, "missing  {a} = {b}")?;
+        }
+        let expected = match &self.expected_type {
+            Some(typ) => typ.to_string(),
+            None => "no type".to_string(),
+        };
+        let verdict = if self.type_correct {
+            "correct"
+        } else {
+            "wrong"
+        };
+        write!(f, "type     {verdict}, expected {expected}")
+    }
+}
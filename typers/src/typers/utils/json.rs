@@ -0,0 +1,158 @@
+use std::io::{self, Write};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::typers::{
+    config::Config, parser::AstNode, rules::TypeExpr, solver::Solution, tree::Tree,
+};
+
+/// Version of the JSON schema in `schema/fmfp.v2.schema.json`.
+/// It is increased whenever the serialized form of one of the core types changes.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// All results of the pipeline for a single expression, this is the top level object of the JSON schema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Export {
+    /// Version of the schema the document was written with
+    pub version: u32,
+    /// The expression as it was entered
+    pub input: String,
+    pub ast: AstNode,
+    pub tree: Tree,
+    /// All constraints collected while building the tree, as pairs of type expressions which have to be equal
+    pub constraints: Vec<(TypeExpr, TypeExpr)>,
+    pub solution: Solution,
+}
+
+// only the version, so documents of other versions are rejected before their content is interpreted
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+impl Export {
+    /// Serializes the export as compact JSON, fails if it is longer than `max_output_length` of the configuration
+    pub fn to_json(&self, config: &Config) -> Result<String, String> {
+        let mut writer = LimitedWriter::new(config.max_output_length);
+        let result = serde_json::to_writer(&mut writer, self);
+        writer.finish(result)
+    }
+
+    /// Serializes the export like `to_json`, but indented for reading.
+    /// The indentation grows with the depth of every node, so deep expressions reach the limit much sooner.
+    pub fn to_json_pretty(&self, config: &Config) -> Result<String, String> {
+        let mut writer = LimitedWriter::new(config.max_output_length);
+        let result = serde_json::to_writer_pretty(&mut writer, self);
+        writer.finish(result)
+    }
+
+    /// Reads an export written by `to_json`.
+    /// Documents nested deeper than an expression allowed by the configuration could be are rejected, so reading them cannot overflow the stack.
+    pub fn from_json(json: &str, config: &Config) -> Result<Export, String> {
+        let header: Header = from_str(json, config)?;
+        if header.version != SCHEMA_VERSION {
+            return Err(format!(
+                "the document uses version {} of the schema, but only version {} is supported",
+                header.version, SCHEMA_VERSION
+            ));
+        }
+        parse(json)
+    }
+}
+
+// collects the output of the serializer and stops it as soon as the output is longer than the limit, before it takes up the memory
+struct LimitedWriter {
+    buffer: Vec<u8>,
+    limit: usize,
+    exceeded: bool,
+}
+
+impl LimitedWriter {
+    fn new(limit: usize) -> Self {
+        LimitedWriter {
+            buffer: vec![],
+            limit,
+            exceeded: false,
+        }
+    }
+
+    // turns the collected output into the document, or the reason why there is none
+    fn finish(self, result: serde_json::Result<()>) -> Result<String, String> {
+        if self.exceeded {
+            return Err(format!(
+                "the JSON export is longer than {} bytes",
+                self.limit
+            ));
+        }
+        result.map_err(|e| format!("could not serialize: {}", e))?;
+        String::from_utf8(self.buffer).map_err(|e| format!("could not serialize: {}", e))
+    }
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() + buf.len() > self.limit {
+            self.exceeded = true;
+            return Err(io::Error::other("the output is too long"));
+        }
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads any of the core types from JSON, with the same limit on the nesting depth as `Export::from_json`
+pub fn from_str<T: DeserializeOwned>(json: &str, config: &Config) -> Result<T, String> {
+    check_depth(json, config)?;
+    parse(json)
+}
+
+// serde_json stops at 128 levels by default, which only covers small expressions, the depth is checked by `check_depth` instead
+fn parse<'a, T: Deserialize<'a>>(json: &'a str) -> Result<T, String> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    deserializer.disable_recursion_limit();
+    let value =
+        T::deserialize(&mut deserializer).map_err(|e| format!("invalid document: {}", e))?;
+    deserializer
+        .end()
+        .map_err(|e| format!("invalid document: {}", e))?;
+    Ok(value)
+}
+
+// A level of the expression becomes two levels of JSON, the variant and its fields in the AST or the node and its list of premises in the tree.
+// Types can be nested deeper than the expression, so twice that is allowed.
+fn check_depth(json: &str, config: &Config) -> Result<(), String> {
+    let max_depth = 4 * config.max_ast_depth + 16;
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in json.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '[' | '{' => {
+                depth += 1;
+                if depth > max_depth {
+                    return Err(format!(
+                        "the document is nested more than {} levels deep",
+                        max_depth
+                    ));
+                }
+            }
+            ']' | '}' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+    Ok(())
+}
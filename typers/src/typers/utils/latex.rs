@@ -0,0 +1,281 @@
+use crate::typers::{
+    parser::AstNode,
+    rules::{RuleExpr, TypeExpr},
+    solver::Solution,
+    tree::Tree,
+};
+
+use super::mathjax::MathJax;
+
+pub trait Latex {
+    fn to_latex(&self) -> String;
+}
+
+impl Latex for AstNode {
+    // MathJax understands a subset of LaTeX, so the expression can be reused as it is
+    fn to_latex(&self) -> String {
+        self.to_mathjax()
+    }
+}
+
+impl Latex for TypeExpr {
+    // translates the type expression to LaTeX math, the base types are set upright
+    fn to_latex(&self) -> String {
+        match self {
+            TypeExpr::Function(left, right) => {
+                format!("({} \\to {})", left.to_latex(), right.to_latex())
+            }
+            TypeExpr::Tuple(left, right) => {
+                format!("({}, {})", left.to_latex(), right.to_latex())
+            }
+            TypeExpr::Var(x) => format!("t_{{{}}}", x),
+            TypeExpr::Bool => "\\mathsf{Bool}".to_string(),
+            TypeExpr::Int => "\\mathsf{Int}".to_string(),
+        }
+    }
+}
+
+impl Latex for RuleExpr {
+    fn to_latex(&self) -> String {
+        format!("t_{{{}}} = {}", self.var, self.rhs.to_latex())
+    }
+}
+
+impl Latex for Tree {
+    // translates the tree to the commands of the `bussproofs` package, without the surrounding environment
+    fn to_latex(&self) -> String {
+        // bussproofs expects the premises before their conclusion, so the nodes are written in post order.
+        // An explicit stack is used, so deep trees cannot overflow the call stack.
+        let mut out = String::new();
+        let mut stack = vec![(self, false)];
+        while let Some((tree, visited)) = stack.pop() {
+            if !visited {
+                stack.push((tree, true));
+                stack.extend(
+                    tree.constraints
+                        .iter()
+                        .rev()
+                        .map(|premise| (premise, false)),
+                );
+                continue;
+            }
+            let inference = match tree.constraints.len() {
+                0 => {
+                    // an axiom gets an empty premise, so the rule label can be placed next to its line
+                    out.push_str("\\AxiomC{}\n");
+                    "UnaryInfC"
+                }
+                1 => "UnaryInfC",
+                2 => "BinaryInfC",
+                3 => "TrinaryInfC",
+                4 => "QuaternaryInfC",
+                _ => "QuinaryInfC",
+            };
+            out.push_str(&format!(
+                "\\RightLabel{{\\scriptsize\\textsf{{{}}}}}\n\\{}{{${}$}}\n",
+                tree.expr.0.name(),
+                inference,
+                judgment(tree)
+            ));
+        }
+        out
+    }
+}
+
+// the conclusion `gamma ⊢ expr :: type` of a node
+fn judgment(tree: &Tree) -> String {
+    let gamma = tree
+        .sorted_gamma()
+        .iter()
+        .map(|(k, v)| format!("{}: {}", k, v.to_latex()))
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!(
+        "{} \\vdash {} :: {}",
+        gamma,
+        tree.expr.0.to_latex(),
+        tree.expr.1.to_latex()
+    )
+}
+
+/// The derivation tree as a displayed proof, scaled down to fit on a single page if it is too large
+pub fn proof_tree(tree: &Tree) -> String {
+    format!(
+        "\\begin{{center}}\n\\begin{{adjustbox}}{{max totalsize={{\\linewidth}}{{0.9\\textheight}}}}\n{}\\DisplayProof\n\\end{{adjustbox}}\n\\end{{center}}\n",
+        tree.to_latex()
+    )
+}
+
+/// All constraints of the tree as a numbered table, which may break across pages
+pub fn constraint_table(constraints: &[(TypeExpr, TypeExpr)]) -> String {
+    let mut out =
+        String::from("\\begin{longtable}{rl}\n\\# & Constraint \\\\\n\\hline\n\\endhead\n");
+    for (i, (a, b)) in constraints.iter().enumerate() {
+        out.push_str(&format!(
+            "{} & ${} = {}$ \\\\\n",
+            i + 1,
+            a.to_latex(),
+            b.to_latex()
+        ));
+    }
+    out.push_str("\\end{longtable}\n");
+    out
+}
+
+// a set of rules as an aligned display, one rule per line
+fn rule_set(rules: &[RuleExpr]) -> String {
+    if rules.is_empty() {
+        return "\\emph{No rules left.}\n\n".to_string();
+    }
+    let lines = rules
+        .iter()
+        .map(|rule| format!("t_{{{}}} &= {}", rule.var, rule.rhs.to_latex()))
+        .collect::<Vec<String>>()
+        .join(" \\\\\n");
+    format!("\\begin{{align*}}\n{}\n\\end{{align*}}\n", lines)
+}
+
+// a list of rules inline, separated by commas
+fn rule_list(rules: &[RuleExpr]) -> String {
+    rules
+        .iter()
+        .map(|rule| format!("${}$", rule.to_latex()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// a list of equations inline, separated by commas
+fn equation_list(equations: &[(TypeExpr, TypeExpr)]) -> String {
+    equations
+        .iter()
+        .map(|(a, b)| format!("${} = {}$", a.to_latex(), b.to_latex()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// One subsection per step of the solver, in the order the steps were taken, followed by the result
+pub fn solution_steps(solution: &Solution) -> String {
+    let mut out = String::from("\\subsection*{Initial rules}\n");
+    out.push_str(&rule_set(&solution.rules));
+    if !solution.equations.is_empty() {
+        out.push_str("The equations\n");
+        out.push_str(&equation_list(&solution.equations));
+        out.push_str(" have no variable on either side and are decomposed first.\n");
+    }
+
+    // the working set is replayed alongside, instead of reconstructing it from scratch for every step
+    let mut rules = solution.rules.clone();
+
+    for id in 0..solution.num_steps() as i32 {
+        let number = id + 1;
+        if let Some(step) = solution.result_accumulate_steps.iter().find(|s| s.id == id) {
+            let (a, b) = &step.rules_compared;
+            out.push_str(&format!(
+                "\\subsection*{{Step {}: Accumulate}}\nComparing ${}$ and ${}$ ",
+                number,
+                a.to_latex(),
+                b.to_latex()
+            ));
+            if step.rules_added.is_empty() {
+                out.push_str("does not yield any new rules.\n");
+            } else {
+                out.push_str(&format!("yields {}.\n", rule_list(&step.rules_added)));
+            }
+            step.delta.apply(&mut rules);
+        } else if let Some(step) = solution.result_remove_steps.iter().find(|s| s.id == id) {
+            out.push_str(&format!("\\subsection*{{Step {}: Remove}}\n", number));
+            match step.delta.renamed {
+                Some((from, to)) => out.push_str(&format!(
+                    "Removing {} and replacing $t_{{{}}}$ with $t_{{{}}}$ in all rules.\n",
+                    rule_list(&step.rules_removed()),
+                    from,
+                    to
+                )),
+                None => out.push_str(&format!(
+                    "Removing the trivial rule {}.\n",
+                    rule_list(&step.rules_removed())
+                )),
+            }
+            step.delta.apply(&mut rules);
+        } else if let Some(step) = solution.result_decompose_steps.iter().find(|s| s.id == id) {
+            out.push_str(&format!(
+                "\\subsection*{{Step {}: Decompose}}\nDecomposing {} yields ",
+                number,
+                equation_list(std::slice::from_ref(&step.equation))
+            ));
+            let parts: Vec<String> = step
+                .rules_added
+                .iter()
+                .map(|rule| format!("${}$", rule.to_latex()))
+                .chain(
+                    step.equations_added
+                        .iter()
+                        .map(|(a, b)| format!("${} = {}$", a.to_latex(), b.to_latex())),
+                )
+                .collect();
+            if parts.is_empty() {
+                out.push_str("nothing new.\n");
+            } else {
+                out.push_str(&format!("{}.\n", parts.join(", ")));
+            }
+            step.delta.apply(&mut rules);
+        } else if let Some(step) = solution.result_clash_steps.iter().find(|s| s.id == id) {
+            out.push_str(&format!(
+                "\\subsection*{{Step {}: Clash}}\nThe sides of {} have different forms, so they cannot be equal.\n",
+                number,
+                equation_list(std::slice::from_ref(&step.equation))
+            ));
+            continue;
+        } else if let Some(step) = solution.result_substitute_steps.iter().find(|s| s.id == id) {
+            out.push_str(&format!(
+                "\\subsection*{{Step {}: Substitute}}\nUsing ${}$ the goal rule becomes\n\\begin{{align*}}\n& {} \\\\\n\\leadsto\\quad & {}\n\\end{{align*}}\n",
+                number,
+                step.rule_used.to_latex(),
+                step.rule_goal_before.to_latex(),
+                step.rule_goal_after.to_latex()
+            ));
+            continue;
+        }
+        out.push_str("The rules are now\n");
+        out.push_str(&rule_set(&rules));
+    }
+
+    out.push_str("\\subsection*{Result}\n");
+    match &solution.result {
+        Some(Ok(rule)) => out.push_str(&format!("\\[\n{}\n\\]\n", rule.to_latex())),
+        Some(Err(e)) => out.push_str(&format!("The constraints cannot be solved: {}\n", e)),
+        None => out.push_str("The solver did not produce a result.\n"),
+    }
+    out
+}
+
+/// A standalone document with the derivation tree, the constraints and the solution, it compiles with `pdflatex`
+pub fn document(
+    ast: &AstNode,
+    tree: &Tree,
+    constraints: &[(TypeExpr, TypeExpr)],
+    solution: &Solution,
+) -> String {
+    let mut out = String::from(
+        "\\documentclass{article}\n\
+         \\usepackage[a4paper,margin=2cm]{geometry}\n\
+         \\usepackage{amsmath,amssymb}\n\
+         \\usepackage{bussproofs}\n\
+         \\usepackage{adjustbox}\n\
+         \\usepackage{longtable}\n\
+         \\allowdisplaybreaks\n\
+         \\begin{document}\n\n",
+    );
+    out.push_str(&format!(
+        "\\section*{{Type inference for ${}$}}\n\n",
+        ast.to_latex()
+    ));
+    out.push_str("\\subsection*{Derivation tree}\n");
+    out.push_str(&proof_tree(tree));
+    out.push_str("\n\\subsection*{Constraints}\n");
+    out.push_str(&constraint_table(constraints));
+    out.push_str("\n\\section*{Solution}\n");
+    out.push_str(&solution_steps(solution));
+    out.push_str("\n\\end{document}\n");
+    out
+}
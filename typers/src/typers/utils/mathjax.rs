@@ -1,6 +1,9 @@
+use std::borrow::Cow;
+
 use crate::typers::{
+    holes::TypedHole,
     parser::AstNode,
-    rules::{RuleExpr, TypeExpr},
+    rules::{Mismatch, RuleExpr, TypeExpr},
     tree::Tree,
 };
 
@@ -8,54 +11,199 @@ pub trait MathJax {
     fn to_mathjax(&self) -> String;
 }
 
-impl MathJax for AstNode {
-    //translates the AST to a string that can be rendered by MathJax
-    fn to_mathjax(&self) -> String {
-        match self {
-            AstNode::Var(var) => var.clone(),
-            AstNode::Abs { var, body } => format!("\\lambda {} \\ . \\ {}", var, body.to_mathjax()),
-            AstNode::App { fun, arg } => format!("({} \\ {})", fun.to_mathjax(), arg.to_mathjax()),
-            AstNode::IsZero(expr) => format!("\\mathsf{{iszero}} \\ {}", expr.to_mathjax()),
-            AstNode::Int(int) => int.to_string(),
-            AstNode::True => "\\mathsf{{true}}".to_string(),
-            AstNode::False => "\\mathsf{{false}}".to_string(),
-            AstNode::BinOp { op, lhs, rhs } => {
-                format!("({} {} {})", lhs.to_mathjax(), op, rhs.to_mathjax())
+/// A piece of the MathJax output, either finished text or a node which still needs to be rendered.
+enum Item<'a> {
+    Text(Cow<'a, str>),
+    Ast(&'a AstNode),
+    Tree(&'a Tree),
+    Type(&'a TypeExpr),
+}
+
+impl<'a> Item<'a> {
+    fn text(text: &'a str) -> Self {
+        Item::Text(Cow::Borrowed(text))
+    }
+
+    fn owned(text: String) -> Self {
+        Item::Text(Cow::Owned(text))
+    }
+}
+
+/// Labels and highlights for the nodes of a derivation tree, the nodes are numbered in pre-order.
+/// Without annotations every node is labeled with the rule of its expression.
+pub struct Annotations<'a> {
+    /// The name of the rule next to each node
+    pub labels: &'a [String],
+    /// The node which is shown in red
+    pub highlight: Option<usize>,
+}
+
+/// Renders the item with an explicit stack instead of recursion, so deep trees cannot overflow the call stack.
+/// Every node pushes its pieces in reverse order, the output is then written from left to right in linear time.
+/// Trees are therefore visited in pre-order, which is the order the annotations are numbered in.
+fn render(item: Item, annotations: Option<&Annotations>) -> String {
+    let mut out = String::new();
+    let mut stack = vec![item];
+    let mut node = 0;
+    while let Some(item) = stack.pop() {
+        let pieces = match item {
+            Item::Text(text) => {
+                out.push_str(&text);
+                continue;
             }
-            AstNode::IfThenElse { cond, then, else_ } => format!(
-                "\\mathsf{{if}} \\ {} \\ \\mathsf{{then}} \\ {} \\ \\mathsf{{else}} \\ {}",
-                cond.to_mathjax(),
-                then.to_mathjax(),
-                else_.to_mathjax()
-            ),
-            AstNode::Tuple { fst, snd } => {
-                format!("({},\\ {})", fst.to_mathjax(), snd.to_mathjax())
+            Item::Ast(ast) => ast_pieces(ast),
+            Item::Type(typ) => type_pieces(typ),
+            Item::Tree(tree) => {
+                let label = annotations.and_then(|a| a.labels.get(node).cloned());
+                let highlight = annotations.is_some_and(|a| a.highlight == Some(node));
+                node += 1;
+                tree_pieces(tree, label.unwrap_or_else(|| tree.expr.0.name()), highlight)
             }
-            AstNode::Fst(expr) => format!("\\mathsf{{fst}} \\ {}", expr.to_mathjax()),
-            AstNode::Snd(expr) => format!("\\mathsf{{snd}} \\ {}", expr.to_mathjax()),
+        };
+        stack.extend(pieces.into_iter().rev());
+    }
+    out
+}
+
+//translates the AST to a string that can be rendered by MathJax
+fn ast_pieces(ast: &AstNode) -> Vec<Item<'_>> {
+    match ast {
+        AstNode::Var(var) => vec![Item::text(var)],
+        AstNode::Abs { var, body } => vec![
+            Item::owned(format!("\\lambda {} \\ . \\ ", var)),
+            Item::Ast(body),
+        ],
+        AstNode::App { fun, arg } => vec![
+            Item::text("("),
+            Item::Ast(fun),
+            Item::text(" \\ "),
+            Item::Ast(arg),
+            Item::text(")"),
+        ],
+        AstNode::IsZero(expr) => vec![Item::text("\\mathsf{iszero} \\ "), Item::Ast(expr)],
+        AstNode::Int(int) => vec![Item::owned(int.to_string())],
+        AstNode::True => vec![Item::text("\\mathsf{{true}}")],
+        AstNode::False => vec![Item::text("\\mathsf{{false}}")],
+        AstNode::BinOp { op, lhs, rhs } => vec![
+            Item::text("("),
+            Item::Ast(lhs),
+            Item::owned(format!(" {} ", op)),
+            Item::Ast(rhs),
+            Item::text(")"),
+        ],
+        AstNode::IfThenElse { cond, then, else_ } => vec![
+            Item::text("\\mathsf{if} \\ "),
+            Item::Ast(cond),
+            Item::text(" \\ \\mathsf{then} \\ "),
+            Item::Ast(then),
+            Item::text(" \\ \\mathsf{else} \\ "),
+            Item::Ast(else_),
+        ],
+        AstNode::Tuple { fst, snd } => vec![
+            Item::text("("),
+            Item::Ast(fst),
+            Item::text(",\\ "),
+            Item::Ast(snd),
+            Item::text(")"),
+        ],
+        AstNode::Fst(expr) => vec![Item::text("\\mathsf{fst} \\ "), Item::Ast(expr)],
+        AstNode::Snd(expr) => vec![Item::text("\\mathsf{snd} \\ "), Item::Ast(expr)],
+        AstNode::Hole(name) => vec![Item::owned(format!("\\_{}", name))],
+    }
+}
+
+// translates the type expression to a string that can be rendered by MathJax
+fn type_pieces(typ: &TypeExpr) -> Vec<Item<'_>> {
+    match typ {
+        TypeExpr::Function(left, right) => vec![
+            Item::text("("),
+            Item::Type(left),
+            Item::text(" \\to "),
+            Item::Type(right),
+            Item::text(")"),
+        ],
+        TypeExpr::Tuple(left, right) => vec![
+            Item::text("("),
+            Item::Type(left),
+            Item::text(", "),
+            Item::Type(right),
+            Item::text(")"),
+        ],
+        TypeExpr::Var(x) => vec![Item::owned(format!("t_{{{}}}", x))],
+        TypeExpr::Bool => vec![Item::text("Bool")],
+        TypeExpr::Int => vec![Item::text("Int")],
+    }
+}
+
+// translates the tree to a string that can be rendered by MathJax, the conclusion of a highlighted node is red
+fn tree_pieces(tree: &Tree, label: String, highlight: bool) -> Vec<Item<'_>> {
+    let gamma = tree
+        .sorted_gamma()
+        .iter()
+        .map(|(k, v)| format!("{}: {}", k, v.to_mathjax()))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let mut pieces = vec![Item::text("\\dfrac{")];
+    for (i, premise) in tree.constraints.iter().enumerate() {
+        if i > 0 {
+            pieces.push(Item::text("\\qquad"));
         }
+        pieces.push(Item::Tree(premise));
+    }
+    let (open, close) = if highlight {
+        ("{\\color{red}", "}")
+    } else {
+        ("", "")
+    };
+    pieces.push(Item::owned(format!("}} {{{}{} \\vdash ", open, gamma)));
+    pieces.push(Item::Ast(&tree.expr.0));
+    pieces.push(Item::owned(format!(
+        " :: {}{}}} \\textsf{{{}{}{}}}",
+        tree.expr.1.to_mathjax(),
+        close,
+        open,
+        label,
+        close
+    )));
+    pieces
+}
+
+/// Renders a derivation tree for MathJax with the labels and the highlight of the annotations
+pub fn annotated_tree(tree: &Tree, annotations: &Annotations) -> String {
+    render(Item::Tree(tree), Some(annotations))
+}
+
+impl MathJax for AstNode {
+    //translates the AST to a string that can be rendered by MathJax
+    fn to_mathjax(&self) -> String {
+        render(Item::Ast(self), None)
     }
 }
 
 impl MathJax for TypeExpr {
     // translates the type expression to a string that can be rendered by MathJax
     fn to_mathjax(&self) -> String {
-        match self {
-            TypeExpr::Function(left, right) => {
-                format!("({} \\to {})", left.to_mathjax(), right.to_mathjax())
-            }
-            TypeExpr::Tuple(left, right) => {
-                format!("({}, {})", left.to_mathjax(), right.to_mathjax())
-            }
-            TypeExpr::Var(x) => format!("t_{{{}}}", x),
-            TypeExpr::Bool => "Bool".to_string(),
-            TypeExpr::Int => "Int".to_string(),
-        }
+        render(Item::Type(self), None)
     }
 }
 
 impl MathJax for Tree {
     // translates the tree to a string that can be rendered by MathJax
+    fn to_mathjax(&self) -> String {
+        render(Item::Tree(self), None)
+    }
+}
+
+impl MathJax for RuleExpr {
+    // translates the rule expression to a string that can be rendered by MathJax
+    fn to_mathjax(&self) -> String {
+        format!("t_{{{}}} = {}", self.var, self.rhs.to_mathjax())
+    }
+}
+
+impl MathJax for TypedHole {
+    // the judgment of the hole with its resolved context and type
     fn to_mathjax(&self) -> String {
         let gamma = self
             .gamma
@@ -63,30 +211,26 @@ impl MathJax for Tree {
             .map(|(k, v)| format!("{}: {}", k, v.to_mathjax()))
             .collect::<Vec<String>>()
             .join(", ");
-        let expr = format!(
-            "{} :: {}",
-            self.expr.0.to_mathjax(),
-            self.expr.1.to_mathjax()
-        );
-        let constraints = self
-            .constraints
-            .iter()
-            .map(|a| a.to_mathjax())
-            .collect::<Vec<String>>()
-            .join("\\qquad");
         format!(
-            "\\dfrac{{{}}} {{{} \\vdash {}}} \\textsf{{{}}}",
-            constraints,
+            "{} \\vdash \\_{} :: {}",
             gamma,
-            expr,
-            self.expr.0.name()
+            self.name,
+            self.typ.to_mathjax()
         )
     }
 }
 
-impl MathJax for RuleExpr {
-    // translates the rule expression to a string that can be rendered by MathJax
+impl MathJax for Mismatch {
+    // names the clashing parts of both types and where they are
     fn to_mathjax(&self) -> String {
-        format!("t_{{{}}} = {}", self.var, self.rhs.to_mathjax())
+        let clash = format!(
+            "\\({}\\) clashes with \\({}\\)",
+            self.expected.to_mathjax(),
+            self.found.to_mathjax()
+        );
+        match self.position() {
+            Some(position) => format!("{} in {}", clash, position),
+            None => clash,
+        }
     }
 }
@@ -1,3 +1,8 @@
+pub mod dot;
 pub mod format;
+pub mod json;
+pub mod latex;
 pub mod mathjax;
+pub mod text;
+pub mod typst;
 pub mod wasm;
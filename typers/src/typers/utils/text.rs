@@ -0,0 +1,406 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::typers::{rules::TypeExpr, tree::Tree};
+
+/// Options for rendering a derivation tree as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextOptions {
+    /// Maximum number of columns, wider trees are abbreviated and split up
+    pub max_width: Option<usize>,
+    /// Only use ASCII characters for the turnstile, the bars and the contexts
+    pub ascii: bool,
+}
+
+/// Space between two premises
+const GAP: usize = 3;
+
+/// What a block shows, subtrees which do not fit are replaced by references in several stages
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// A rule with all its premises
+    Rule,
+    /// The conclusion of a subtree which is shown separately, with its number above it
+    Reference(usize),
+    /// Only the number of a subtree which is shown separately
+    Bare,
+}
+
+/// A rectangular piece of text, every line is at most `width` characters long.
+/// The last line is the conclusion of the derivation, it starts at column `start` and is `len` characters long.
+struct Block {
+    lines: Vec<String>,
+    width: usize,
+    start: usize,
+    len: usize,
+    kind: Kind,
+}
+
+impl Block {
+    fn empty() -> Self {
+        Block {
+            lines: vec![],
+            width: 0,
+            start: 0,
+            len: 0,
+            kind: Kind::Bare,
+        }
+    }
+
+    fn is_axiom(&self) -> bool {
+        self.kind == Kind::Rule && self.lines.len() <= 2
+    }
+}
+
+/// Renders the tree as a two dimensional proof tree, the premises of a rule are placed next to each other above its bar.
+/// If the tree is wider than `max_width`, contexts are replaced by names which are listed below the tree.
+/// Subtrees which still do not fit are moved below the tree and referenced by a number, judgments are shortened as a last resort.
+pub fn render_tree(tree: &Tree, options: &TextOptions) -> String {
+    match Layout::new(options, false).render(tree) {
+        Some(full) => full,
+        None => Layout::new(options, true).render(tree).unwrap_or_default(),
+    }
+}
+
+struct Layout<'a> {
+    options: &'a TextOptions,
+    /// Whether contexts are replaced by names and too wide subtrees are split off
+    abbreviate: bool,
+    /// Names and contents of the contexts in the order they were first used
+    contexts: Vec<(String, String)>,
+    /// Index into `contexts` for every context of the tree, equal contexts share a name
+    names: HashMap<*const HashMap<String, TypeExpr>, usize>,
+    /// Subtrees moved out of their parent, with their reference number
+    detached: Vec<(usize, Block)>,
+}
+
+impl<'a> Layout<'a> {
+    fn new(options: &'a TextOptions, abbreviate: bool) -> Self {
+        Layout {
+            options,
+            abbreviate,
+            contexts: vec![],
+            names: HashMap::new(),
+            detached: vec![],
+        }
+    }
+
+    // returns `None` if the tree is not abbreviated and does not fit
+    fn render(mut self, tree: &Tree) -> Option<String> {
+        if self.abbreviate {
+            self.name_contexts(tree);
+        }
+        let root = self.layout(tree)?;
+
+        let mut out = String::new();
+        write_block(&mut out, &root);
+        for (number, block) in &self.detached {
+            out.push_str(&format!("\n[{}]\n", number));
+            write_block(&mut out, block);
+        }
+        if !self.contexts.is_empty() {
+            out.push('\n');
+            for (name, gamma) in &self.contexts {
+                out.push_str(&format!("{} = {}\n", name, gamma));
+            }
+        }
+        Some(out)
+    }
+
+    // names the non empty contexts in pre order, so the names increase from the root to the leaves
+    fn name_contexts(&mut self, tree: &Tree) {
+        let mut by_content = HashMap::<String, usize>::new();
+        let mut stack = vec![tree];
+        while let Some(tree) = stack.pop() {
+            stack.extend(tree.constraints.iter().rev());
+            let key = Rc::as_ptr(&tree.gamma);
+            if tree.gamma.is_empty() || self.names.contains_key(&key) {
+                continue;
+            }
+            let gamma = self.gamma(&tree.gamma);
+            let index = *by_content.entry(gamma.clone()).or_insert_with(|| {
+                let number = self.contexts.len() + 1;
+                let name = if self.options.ascii {
+                    format!("G{}", number)
+                } else {
+                    format!("Γ{}", subscript(number))
+                };
+                self.contexts.push((name, gamma));
+                number - 1
+            });
+            self.names.insert(key, index);
+        }
+    }
+
+    fn gamma(&self, gamma: &HashMap<String, TypeExpr>) -> String {
+        let mut gamma: Vec<(&String, &TypeExpr)> = gamma.iter().collect();
+        gamma.sort_by(|a, b| a.0.cmp(b.0));
+        gamma
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k, v))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    // the conclusion `gamma ⊢ expr :: type` of a node, shortened if it would not fit
+    fn judgment(&self, tree: &Tree, label: usize) -> String {
+        let gamma = if tree.gamma.is_empty() {
+            String::new()
+        } else if self.abbreviate {
+            self.names
+                .get(&Rc::as_ptr(&tree.gamma))
+                .map(|&i| self.contexts[i].0.clone())
+                .unwrap_or_default()
+        } else {
+            self.gamma(&tree.gamma)
+        };
+        let turnstile = if self.options.ascii { "|-" } else { "⊢" };
+        let prefix = if gamma.is_empty() {
+            format!("{} ", turnstile)
+        } else {
+            format!("{} {} ", gamma, turnstile)
+        };
+        let expr = tree.expr.0.to_string();
+        let suffix = format!(" :: {}", tree.expr.1);
+
+        let max_width = match self.options.max_width {
+            Some(max_width) if self.abbreviate => max_width.saturating_sub(label),
+            _ => return format!("{}{}{}", prefix, expr, suffix),
+        };
+        let fixed = prefix.chars().count() + suffix.chars().count();
+        if fixed + expr.chars().count() <= max_width {
+            format!("{}{}{}", prefix, expr, suffix)
+        } else if fixed < max_width {
+            // the expression is shortened in the middle, so both its start and its end stay visible
+            format!(
+                "{}{}{}",
+                prefix,
+                shorten(&expr, max_width - fixed, self.options.ascii),
+                suffix
+            )
+        } else {
+            shorten(
+                &format!("{}{}{}", prefix, expr, suffix),
+                max_width.max(1),
+                self.options.ascii,
+            )
+        }
+    }
+
+    // lays out all nodes in post order, so the blocks of the premises are ready when their conclusion is placed
+    // Without abbreviations the layout is aborted as soon as a subtree is too wide, since its parents are at least as wide
+    fn layout(&mut self, tree: &Tree) -> Option<Block> {
+        let mut stack = vec![(tree, false)];
+        let mut blocks = Vec::<Block>::new();
+        while let Some((tree, visited)) = stack.pop() {
+            if !visited {
+                stack.push((tree, true));
+                stack.extend(tree.constraints.iter().rev().map(|a| (a, false)));
+                continue;
+            }
+            let premises = blocks.split_off(blocks.len() - tree.constraints.len());
+            let block = self.node(tree, premises);
+            match self.options.max_width {
+                Some(max_width) if !self.abbreviate && block.width > max_width => return None,
+                _ => blocks.push(block),
+            }
+        }
+        blocks.pop()
+    }
+
+    fn node(&mut self, tree: &Tree, mut premises: Vec<Block>) -> Block {
+        let label = format!(" ({})", tree.expr.0.name());
+        let label_len = label.chars().count();
+        let conclusion = self.judgment(tree, label_len);
+
+        let max_width = match self.options.max_width {
+            Some(max_width) if self.abbreviate => max_width,
+            _ => return self.combine(premises, conclusion, &label),
+        };
+
+        // Split off the widest premises until the rule fits. Subtrees are replaced by their conclusion first,
+        // if that is not enough the conclusions are dropped as well, and axioms are the last to go
+        while self.width(&premises, &conclusion, label_len) > max_width {
+            let widest = |filter: &dyn Fn(&Block) -> bool| {
+                premises
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, block)| filter(block))
+                    .max_by_key(|(_, block)| block.width)
+                    .map(|(i, _)| i)
+            };
+            if let Some(i) = widest(&|b| b.kind == Kind::Rule && !b.is_axiom()) {
+                let block = std::mem::replace(&mut premises[i], Block::empty());
+                let number = self.detach(block);
+                premises[i] = reference_block(&self.detached[number - 1].1, number);
+            } else if let Some(i) = widest(&|b| matches!(b.kind, Kind::Reference(_))) {
+                if let Kind::Reference(number) = premises[i].kind {
+                    premises[i] = bare_block(number);
+                }
+            } else if let Some(i) = widest(&|b| b.is_axiom()) {
+                let block = std::mem::replace(&mut premises[i], Block::empty());
+                let number = self.detach(block);
+                premises[i] = bare_block(number);
+            } else {
+                break;
+            }
+        }
+        self.combine(premises, conclusion, &label)
+    }
+
+    // moves the block below the tree and returns its number
+    fn detach(&mut self, block: Block) -> usize {
+        let number = self.detached.len() + 1;
+        self.detached.push((number, block));
+        number
+    }
+
+    // the width of the block `combine` would build, without building it
+    fn width(&self, premises: &[Block], conclusion: &str, label_len: usize) -> usize {
+        let row: usize = premises.iter().map(|b| b.width).sum::<usize>()
+            + GAP * premises.len().saturating_sub(1);
+        let (span_start, span) = span(premises);
+        let len = conclusion.chars().count();
+        let bar = span.max(len);
+        let bar_start = span_start as isize - (bar - span) as isize / 2;
+        let shift = (-bar_start).max(0) as usize;
+        (row + shift).max((bar_start + shift as isize) as usize + bar + label_len)
+    }
+
+    fn combine(&self, premises: Vec<Block>, conclusion: String, label: &str) -> Block {
+        let bar_char = if self.options.ascii { "-" } else { "─" };
+        let len = conclusion.chars().count();
+
+        // Place the premises next to each other, aligned at the bottom
+        let height = premises.iter().map(|b| b.lines.len()).max().unwrap_or(0);
+        let row_width: usize = premises.iter().map(|b| b.width).sum::<usize>()
+            + GAP * premises.len().saturating_sub(1);
+        let (span_start, span) = span(&premises);
+        let mut rows = vec![String::new(); height];
+        for (i, block) in premises.iter().enumerate() {
+            let offset = height - block.lines.len();
+            for (r, row) in rows.iter_mut().enumerate() {
+                if i > 0 {
+                    row.push_str(&" ".repeat(GAP));
+                }
+                let line = if r >= offset {
+                    block.lines[r - offset].as_str()
+                } else {
+                    ""
+                };
+                row.push_str(line);
+                let missing = block.width - line.chars().count();
+                row.push_str(&" ".repeat(missing));
+            }
+        }
+
+        // The bar spans the conclusions of all premises and the own conclusion, which is centered below it
+        let bar = span.max(len);
+        let bar_start = span_start as isize - (bar - span) as isize / 2;
+        let shift = (-bar_start).max(0) as usize;
+        let bar_start = (bar_start + shift as isize) as usize;
+        let start = bar_start + (bar - len) / 2;
+
+        let mut lines: Vec<String> = rows
+            .into_iter()
+            .map(|row| format!("{}{}", " ".repeat(shift), row))
+            .collect();
+        lines.push(format!(
+            "{}{}{}",
+            " ".repeat(bar_start),
+            bar_char.repeat(bar),
+            label
+        ));
+        lines.push(format!("{}{}", " ".repeat(start), conclusion));
+
+        let width = (row_width + shift)
+            .max(bar_start + bar + label.chars().count())
+            .max(start + len);
+        Block {
+            lines,
+            width,
+            start,
+            len,
+            kind: Kind::Rule,
+        }
+    }
+}
+
+// the columns covered by the conclusions of the premises, as start and length
+fn span(premises: &[Block]) -> (usize, usize) {
+    if premises.is_empty() {
+        return (0, 0);
+    }
+    let mut offset = 0;
+    let mut first = None;
+    let mut last = 0;
+    for block in premises {
+        first.get_or_insert(offset + block.start);
+        last = offset + block.start + block.len;
+        offset += block.width + GAP;
+    }
+    let first = first.unwrap_or(0);
+    (first, last - first)
+}
+
+// a premise which only shows the conclusion of a subtree and the number where the full subtree can be found
+fn reference_block(block: &Block, number: usize) -> Block {
+    let reference = format!("[{}]", number);
+    let conclusion = block.lines.last().map(|l| l.trim()).unwrap_or_default();
+    let len = block.len;
+    let reference_len = reference.chars().count();
+    let width = len.max(reference_len);
+    Block {
+        lines: vec![
+            format!("{}{}", " ".repeat((width - reference_len) / 2), reference),
+            format!("{}{}", " ".repeat((width - len) / 2), conclusion),
+        ],
+        width,
+        start: (width - len) / 2,
+        len,
+        kind: Kind::Reference(number),
+    }
+}
+
+// a premise which only shows the number where the full subtree can be found
+fn bare_block(number: usize) -> Block {
+    let reference = format!("[{}]", number);
+    let len = reference.chars().count();
+    Block {
+        lines: vec![reference],
+        width: len,
+        start: 0,
+        len,
+        kind: Kind::Bare,
+    }
+}
+
+fn write_block(out: &mut String, block: &Block) {
+    for line in &block.lines {
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+}
+
+// shortens the text to `max` characters by replacing its middle with an ellipsis
+fn shorten(text: &str, max: usize, ascii: bool) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max {
+        return text.to_string();
+    }
+    let ellipsis = if ascii { "..." } else { "…" };
+    let keep = max.saturating_sub(ellipsis.chars().count());
+    let head = keep - keep / 2;
+    let tail = keep / 2;
+    let mut out: String = chars[..head].iter().collect();
+    out.push_str(ellipsis);
+    out.extend(&chars[chars.len() - tail..]);
+    out
+}
+
+fn subscript(number: usize) -> String {
+    number
+        .to_string()
+        .chars()
+        .map(|c| char::from_u32('₀' as u32 + c.to_digit(10).unwrap_or(0)).unwrap_or(c))
+        .collect()
+}
@@ -0,0 +1,350 @@
+use std::borrow::Cow;
+
+use crate::typers::{
+    parser::AstNode,
+    rules::{RuleExpr, TypeExpr},
+    solver::Solution,
+    tree::Tree,
+};
+
+pub trait Typst {
+    fn to_typst(&self) -> String;
+}
+
+/// A piece of the Typst output, either finished text or a node which still needs to be rendered.
+enum Item<'a> {
+    Text(Cow<'a, str>),
+    Ast(&'a AstNode),
+}
+
+impl<'a> Item<'a> {
+    fn text(text: &'a str) -> Self {
+        Item::Text(Cow::Borrowed(text))
+    }
+
+    fn owned(text: String) -> Self {
+        Item::Text(Cow::Owned(text))
+    }
+}
+
+/// Renders the expression with an explicit stack instead of recursion, so deep expressions cannot overflow the call stack.
+fn render(ast: &AstNode) -> String {
+    let mut out = String::new();
+    let mut stack = vec![Item::Ast(ast)];
+    while let Some(item) = stack.pop() {
+        match item {
+            Item::Text(text) => out.push_str(&text),
+            Item::Ast(ast) => stack.extend(ast_pieces(ast).into_iter().rev()),
+        }
+    }
+    out
+}
+
+// translates the AST to Typst math, variables consist of a single character and can be written as they are
+fn ast_pieces(ast: &AstNode) -> Vec<Item<'_>> {
+    match ast {
+        AstNode::Var(var) => vec![Item::text(var)],
+        AstNode::Abs { var, body } => {
+            vec![Item::owned(format!("lambda {} . ", var)), Item::Ast(body)]
+        }
+        AstNode::App { fun, arg } => vec![
+            Item::text("("),
+            Item::Ast(fun),
+            Item::text(" med "),
+            Item::Ast(arg),
+            Item::text(")"),
+        ],
+        AstNode::IsZero(expr) => vec![Item::text("sans(\"iszero\") med "), Item::Ast(expr)],
+        AstNode::Int(int) => vec![Item::owned(int.to_string())],
+        AstNode::True => vec![Item::text("sans(\"true\")")],
+        AstNode::False => vec![Item::text("sans(\"false\")")],
+        AstNode::BinOp { op, lhs, rhs } => vec![
+            Item::text("("),
+            Item::Ast(lhs),
+            Item::owned(format!(" {} ", op)),
+            Item::Ast(rhs),
+            Item::text(")"),
+        ],
+        AstNode::IfThenElse { cond, then, else_ } => vec![
+            Item::text("sans(\"if\") med "),
+            Item::Ast(cond),
+            Item::text(" med sans(\"then\") med "),
+            Item::Ast(then),
+            Item::text(" med sans(\"else\") med "),
+            Item::Ast(else_),
+        ],
+        AstNode::Tuple { fst, snd } => vec![
+            Item::text("("),
+            Item::Ast(fst),
+            Item::text(", "),
+            Item::Ast(snd),
+            Item::text(")"),
+        ],
+        AstNode::Fst(expr) => vec![Item::text("sans(\"fst\") med "), Item::Ast(expr)],
+        AstNode::Snd(expr) => vec![Item::text("sans(\"snd\") med "), Item::Ast(expr)],
+        // the name of a hole can be longer than one character, so it is written as text
+        AstNode::Hole(name) => vec![Item::owned(format!("\"_{}\"", name))],
+    }
+}
+
+impl Typst for AstNode {
+    fn to_typst(&self) -> String {
+        render(self)
+    }
+}
+
+impl Typst for TypeExpr {
+    // translates the type expression to Typst math, subscripts are wrapped in parenthesis so multi digit variables stay together
+    fn to_typst(&self) -> String {
+        match self {
+            TypeExpr::Function(left, right) => {
+                format!("({} -> {})", left.to_typst(), right.to_typst())
+            }
+            TypeExpr::Tuple(left, right) => {
+                format!("({}, {})", left.to_typst(), right.to_typst())
+            }
+            TypeExpr::Var(x) => format!("t_({})", x),
+            TypeExpr::Bool => "sans(\"Bool\")".to_string(),
+            TypeExpr::Int => "sans(\"Int\")".to_string(),
+        }
+    }
+}
+
+impl Typst for RuleExpr {
+    fn to_typst(&self) -> String {
+        format!("t_({}) = {}", self.var, self.rhs.to_typst())
+    }
+}
+
+impl Typst for Tree {
+    // translates the tree to nested `rule` calls of the `curryst` package, every premise is an argument of its conclusion
+    fn to_typst(&self) -> String {
+        // Written in pre order with an explicit stack, closing a call is a separate task after all premises
+        enum Task<'a> {
+            Open(&'a Tree, usize),
+            Close(usize),
+        }
+        let mut out = String::new();
+        let mut stack = vec![Task::Open(self, 0)];
+        while let Some(task) = stack.pop() {
+            match task {
+                Task::Open(tree, depth) => {
+                    let indent = "  ".repeat(depth);
+                    out.push_str(&format!(
+                        "{indent}rule(\n{indent}  name: [#smallcaps[{}]],\n{indent}  ${}$,\n",
+                        tree.expr.0.name(),
+                        judgment(tree)
+                    ));
+                    stack.push(Task::Close(depth));
+                    stack.extend(
+                        tree.constraints
+                            .iter()
+                            .rev()
+                            .map(|premise| Task::Open(premise, depth + 1)),
+                    );
+                }
+                Task::Close(depth) => {
+                    out.push_str(&"  ".repeat(depth));
+                    out.push_str(if depth == 0 { ")\n" } else { "),\n" });
+                }
+            }
+        }
+        out
+    }
+}
+
+// the conclusion `gamma ⊢ expr :: type` of a node
+fn judgment(tree: &Tree) -> String {
+    let gamma = tree
+        .sorted_gamma()
+        .iter()
+        .map(|(k, v)| format!("{}: {}", k, v.to_typst()))
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!(
+        "{} tack {} colon.double {}",
+        gamma,
+        tree.expr.0.to_typst(),
+        tree.expr.1.to_typst()
+    )
+}
+
+// escapes text, so it can be used inside a Typst string literal
+fn string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The derivation tree as a displayed proof, scaled down to the width of the page if it is too wide
+pub fn proof_tree(tree: &Tree) -> String {
+    format!(
+        "#align(center, layout(size => {{\n  let tree = prooftree(\n{}  )\n  let width = measure(tree).width\n  if width > size.width {{ scale(size.width / width * 100%, reflow: true, tree) }} else {{ tree }}\n}}))\n",
+        tree.to_typst()
+    )
+}
+
+/// All constraints of the tree as a numbered table
+pub fn constraint_table(constraints: &[(TypeExpr, TypeExpr)]) -> String {
+    let mut out = String::from(
+        "#table(\n  columns: 2,\n  align: (right, left),\n  table.header([*\\#*], [*Constraint*]),\n",
+    );
+    for (i, (a, b)) in constraints.iter().enumerate() {
+        out.push_str(&format!(
+            "  [{}], [${} = {}$],\n",
+            i + 1,
+            a.to_typst(),
+            b.to_typst()
+        ));
+    }
+    out.push_str(")\n");
+    out
+}
+
+// a set of rules as an aligned display, one rule per line
+fn rule_set(rules: &[RuleExpr]) -> String {
+    if rules.is_empty() {
+        return "_No rules left._\n\n".to_string();
+    }
+    let lines = rules
+        .iter()
+        .map(|rule| format!("  t_({}) &= {}", rule.var, rule.rhs.to_typst()))
+        .collect::<Vec<String>>()
+        .join(" \\\n");
+    format!("$\n{}\n$\n\n", lines)
+}
+
+// a list of rules inline, separated by commas
+fn rule_list(rules: &[RuleExpr]) -> String {
+    rules
+        .iter()
+        .map(|rule| format!("${}$", rule.to_typst()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// a list of equations inline, separated by commas
+fn equation_list(equations: &[(TypeExpr, TypeExpr)]) -> String {
+    equations
+        .iter()
+        .map(|(a, b)| format!("${} = {}$", a.to_typst(), b.to_typst()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// One section per step of the solver, in the order the steps were taken, followed by the result
+pub fn solution_steps(solution: &Solution) -> String {
+    let mut out = String::from("=== Initial rules\n");
+    out.push_str(&rule_set(&solution.rules));
+    if !solution.equations.is_empty() {
+        out.push_str("The equations\n");
+        out.push_str(&equation_list(&solution.equations));
+        out.push_str(" have no variable on either side and are decomposed first.\n");
+    }
+
+    // the working set is replayed alongside, instead of reconstructing it from scratch for every step
+    let mut rules = solution.rules.clone();
+    for id in 0..solution.num_steps() as i32 {
+        let number = id + 1;
+        if let Some(step) = solution.result_accumulate_steps.iter().find(|s| s.id == id) {
+            let (a, b) = &step.rules_compared;
+            out.push_str(&format!(
+                "=== Step {}: Accumulate\nComparing ${}$ and ${}$ ",
+                number,
+                a.to_typst(),
+                b.to_typst()
+            ));
+            if step.rules_added.is_empty() {
+                out.push_str("does not yield any new rules.\n");
+            } else {
+                out.push_str(&format!("yields {}.\n", rule_list(&step.rules_added)));
+            }
+            step.delta.apply(&mut rules);
+        } else if let Some(step) = solution.result_remove_steps.iter().find(|s| s.id == id) {
+            out.push_str(&format!("=== Step {}: Remove\n", number));
+            match step.delta.renamed {
+                Some((from, to)) => out.push_str(&format!(
+                    "Removing {} and replacing $t_({})$ with $t_({})$ in all rules.\n",
+                    rule_list(&step.rules_removed()),
+                    from,
+                    to
+                )),
+                None => out.push_str(&format!(
+                    "Removing the trivial rule {}.\n",
+                    rule_list(&step.rules_removed())
+                )),
+            }
+            step.delta.apply(&mut rules);
+        } else if let Some(step) = solution.result_decompose_steps.iter().find(|s| s.id == id) {
+            out.push_str(&format!(
+                "=== Step {}: Decompose\nDecomposing {} yields ",
+                number,
+                equation_list(std::slice::from_ref(&step.equation))
+            ));
+            let parts: Vec<String> = step
+                .rules_added
+                .iter()
+                .map(|rule| format!("${}$", rule.to_typst()))
+                .chain(
+                    step.equations_added
+                        .iter()
+                        .map(|(a, b)| format!("${} = {}$", a.to_typst(), b.to_typst())),
+                )
+                .collect();
+            if parts.is_empty() {
+                out.push_str("nothing new.\n");
+            } else {
+                out.push_str(&format!("{}.\n", parts.join(", ")));
+            }
+            step.delta.apply(&mut rules);
+        } else if let Some(step) = solution.result_clash_steps.iter().find(|s| s.id == id) {
+            out.push_str(&format!(
+                "=== Step {}: Clash\nThe sides of {} have different forms, so they cannot be equal.\n",
+                number,
+                equation_list(std::slice::from_ref(&step.equation))
+            ));
+            continue;
+        } else if let Some(step) = solution.result_substitute_steps.iter().find(|s| s.id == id) {
+            out.push_str(&format!(
+                "=== Step {}: Substitute\nUsing ${}$ the goal rule becomes\n$\n  & {} \\\n  ~> & {}\n$\n\n",
+                number,
+                step.rule_used.to_typst(),
+                step.rule_goal_before.to_typst(),
+                step.rule_goal_after.to_typst()
+            ));
+            continue;
+        }
+        out.push_str("\nThe rules are now\n");
+        out.push_str(&rule_set(&rules));
+    }
+
+    out.push_str("=== Result\n");
+    match &solution.result {
+        Some(Ok(rule)) => out.push_str(&format!("$ {} $\n", rule.to_typst())),
+        Some(Err(e)) => out.push_str(&format!(
+            "The constraints cannot be solved: #raw({})\n",
+            string(e)
+        )),
+        None => out.push_str("The solver did not produce a result.\n"),
+    }
+    out
+}
+
+/// A standalone document with the derivation tree, the constraints and the solution, it compiles with `typst compile`
+pub fn document(
+    ast: &AstNode,
+    tree: &Tree,
+    constraints: &[(TypeExpr, TypeExpr)],
+    solution: &Solution,
+) -> String {
+    let mut out = String::from(
+        "#import \"@preview/curryst:0.5.0\": rule, prooftree\n\
+         #set page(paper: \"a4\", margin: 2cm)\n\n",
+    );
+    out.push_str(&format!("= Type inference for ${}$\n\n", ast.to_typst()));
+    out.push_str("== Derivation tree\n");
+    out.push_str(&proof_tree(tree));
+    out.push_str("\n== Constraints\n");
+    out.push_str(&constraint_table(constraints));
+    out.push_str("\n== Solution\n");
+    out.push_str(&solution_steps(solution));
+    out
+}
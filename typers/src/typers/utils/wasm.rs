@@ -1,18 +1,61 @@
-use wasm_bindgen::prelude::wasm_bindgen;
+use std::cell::OnceCell;
 
-use crate::typers::solver::{
-    ResultAccumulateStep, ResultRemoveStep, ResultSubstituteStep, Solution,
+use serde::de::DeserializeOwned;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+
+use crate::typers::{
+    config::Config,
+    rules::TypeExpr,
+    solver::{
+        ResultAccumulateStep, ResultClashStep, ResultDecomposeStep, ResultRemoveStep,
+        ResultSubstituteStep, Solution,
+    },
 };
 
-use super::mathjax::MathJax;
+use super::{json, mathjax::MathJax};
+
+#[wasm_bindgen]
+extern "C" {
+    /// An `AstNode` passed in from JavaScript, as returned by `parse`
+    #[wasm_bindgen(typescript_type = "AstNode")]
+    pub type AstNodeJs;
+
+    /// A `TypeExpr` passed in from JavaScript
+    #[wasm_bindgen(typescript_type = "TypeExpr")]
+    pub type TypeExprJs;
+
+    /// A `RuleExpr` passed in from JavaScript
+    #[wasm_bindgen(typescript_type = "RuleExpr")]
+    pub type RuleExprJs;
+
+    /// A `Tree` passed in from JavaScript, as returned by `infer`
+    #[wasm_bindgen(typescript_type = "Tree")]
+    pub type TreeJs;
+
+    /// An `Action` chosen by the student
+    #[wasm_bindgen(typescript_type = "Action")]
+    pub type ActionJs;
+
+    /// The constraints passed in from JavaScript, as returned by `infer`
+    #[wasm_bindgen(typescript_type = "[TypeExpr, TypeExpr][]")]
+    pub type ConstraintsJs;
+}
+
+/// Converts a value passed in from JavaScript into one of the core types.
+/// The value goes through JSON, so it is subject to the same depth limit as `json::from_str` and cannot overflow the stack.
+pub fn from_js<T: DeserializeOwned>(value: &JsValue, config: &Config) -> Result<T, String> {
+    let text = js_sys::JSON::stringify(value)
+        .ok()
+        .and_then(|text| text.as_string())
+        .ok_or_else(|| "the value cannot be converted to JSON".to_string())?;
+    json::from_str(&text, config)
+}
 
 #[wasm_bindgen(getter_with_clone)]
 #[derive(Debug, Clone)]
 // Represents the ResultRemoveStep in a format which can be used with web assembly.
 pub struct ResultRemoveStepTS {
     pub id: i32,
-    pub rules_before: Vec<String>,
-    pub rules_after: Vec<String>,
     pub text: Option<String>,
     pub rules_removed: Vec<String>,
 }
@@ -21,10 +64,8 @@ impl From<ResultRemoveStep> for ResultRemoveStepTS {
     fn from(val: ResultRemoveStep) -> Self {
         ResultRemoveStepTS {
             id: val.id,
-            rules_before: val.rules_before.iter().map(|x| x.to_mathjax()).collect(),
-            rules_after: val.rules_after.iter().map(|x| x.to_mathjax()).collect(),
+            rules_removed: val.rules_removed().iter().map(|x| x.to_mathjax()).collect(),
             text: val.text,
-            rules_removed: val.rules_removed.iter().map(|x| x.to_mathjax()).collect(),
         }
     }
 }
@@ -34,8 +75,6 @@ impl From<ResultRemoveStep> for ResultRemoveStepTS {
 // Represents the ResultAccumulateStep in a format which can be used with web assembly.
 pub struct ResultAccumulateStepTS {
     pub id: i32,
-    pub rules_before: Vec<String>,
-    pub rules_after: Vec<String>,
     pub text: Option<String>,
     pub rules_added: Vec<String>,
     pub rules_compared: Vec<String>,
@@ -45,8 +84,6 @@ impl From<ResultAccumulateStep> for ResultAccumulateStepTS {
     fn from(val: ResultAccumulateStep) -> Self {
         ResultAccumulateStepTS {
             id: val.id,
-            rules_before: val.rules_before.iter().map(|x| x.to_mathjax()).collect(),
-            rules_after: val.rules_after.iter().map(|x| x.to_mathjax()).collect(),
             text: val.text,
             rules_added: val.rules_added.iter().map(|x| x.to_mathjax()).collect(),
             rules_compared: vec![
@@ -63,7 +100,6 @@ impl From<ResultAccumulateStep> for ResultAccumulateStepTS {
 pub struct ResultSubstituteStepTS {
     pub id: i32,
     pub goal_id: usize,
-    pub rules_available: Vec<String>,
     pub rule_goal_before: String,
     pub rule_goal_after: String,
     pub rule_used: String,
@@ -75,7 +111,6 @@ impl From<ResultSubstituteStep> for ResultSubstituteStepTS {
         ResultSubstituteStepTS {
             id: val.id,
             goal_id: val.goal_id,
-            rules_available: val.rules_available.iter().map(|x| x.to_mathjax()).collect(),
             rule_goal_before: val.rule_goal_before.to_mathjax(),
             rule_goal_after: val.rule_goal_after.to_mathjax(),
             rule_used: val.rule_used.to_mathjax(),
@@ -84,17 +119,108 @@ impl From<ResultSubstituteStep> for ResultSubstituteStepTS {
     }
 }
 
+// an equation between two types for MathJax
+fn equation_to_mathjax((a, b): &(TypeExpr, TypeExpr)) -> String {
+    format!("{} = {}", a.to_mathjax(), b.to_mathjax())
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone)]
+// Represents the ResultDecomposeStep in a format which can be used with web assembly.
+pub struct ResultDecomposeStepTS {
+    pub id: i32,
+    pub text: Option<String>,
+    pub equation: String,
+    pub equations_added: Vec<String>,
+    pub rules_added: Vec<String>,
+}
+
+impl From<ResultDecomposeStep> for ResultDecomposeStepTS {
+    fn from(val: ResultDecomposeStep) -> Self {
+        ResultDecomposeStepTS {
+            id: val.id,
+            text: val.text,
+            equation: equation_to_mathjax(&val.equation),
+            equations_added: val
+                .equations_added
+                .iter()
+                .map(equation_to_mathjax)
+                .collect(),
+            rules_added: val.rules_added.iter().map(|x| x.to_mathjax()).collect(),
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone)]
+// Represents the ResultClashStep in a format which can be used with web assembly.
+pub struct ResultClashStepTS {
+    pub id: i32,
+    pub text: Option<String>,
+    pub equation: String,
+}
+
+impl From<ResultClashStep> for ResultClashStepTS {
+    fn from(val: ResultClashStep) -> Self {
+        ResultClashStepTS {
+            id: val.id,
+            text: val.text,
+            equation: equation_to_mathjax(&val.equation),
+        }
+    }
+}
+
 #[wasm_bindgen(getter_with_clone)]
 #[derive(Debug, Clone)]
 // Represents the Solution in a format which can be used with web assembly.
+// The rules before and after each step are not stored in the steps, they are replayed once on the first call of
+// `rules_before` or `rules_after` and kept for the later calls.
 pub struct SolutionTS {
     pub rules: Vec<String>,
+    pub equations: Vec<String>,
     pub variables: Vec<String>,
     pub result_remove_steps: Vec<ResultRemoveStepTS>,
     pub result_accumulate_steps: Vec<ResultAccumulateStepTS>,
     pub result_substitute_steps: Vec<ResultSubstituteStepTS>,
+    pub result_decompose_steps: Vec<ResultDecomposeStepTS>,
+    pub result_clash_steps: Vec<ResultClashStepTS>,
     pub result_error: Option<String>,
     pub result: Option<String>,
+    /// Where two types clash, if the solver failed because of it
+    pub mismatch: Option<String>,
+    solution: Solution,
+    rule_sets: OnceCell<Vec<Vec<String>>>,
+}
+
+#[wasm_bindgen]
+impl SolutionTS {
+    /// The rules right before the step with the given id, empty if there is no such step
+    pub fn rules_before(&self, step_id: i32) -> Vec<String> {
+        if step_id < 0 || step_id as usize >= self.solution.num_steps() {
+            return vec![];
+        }
+        self.rule_sets()[step_id as usize].clone()
+    }
+
+    /// The rules right after the step with the given id, empty if there is no such step
+    pub fn rules_after(&self, step_id: i32) -> Vec<String> {
+        if step_id < 0 || step_id as usize >= self.solution.num_steps() {
+            return vec![];
+        }
+        self.rule_sets()[step_id as usize + 1].clone()
+    }
+}
+
+impl SolutionTS {
+    fn rule_sets(&self) -> &Vec<Vec<String>> {
+        self.rule_sets.get_or_init(|| {
+            self.solution
+                .rule_sets()
+                .iter()
+                .map(|rules| rules.iter().map(|x| x.to_mathjax()).collect())
+                .collect()
+        })
+    }
 }
 
 impl From<Solution> for SolutionTS {
@@ -111,7 +237,10 @@ impl From<Solution> for SolutionTS {
             None => None,
         };
 
+        let mismatch = val.mismatch.as_ref().map(|m| m.to_mathjax());
+
         let rules = val.rules.iter().map(|x| x.to_mathjax()).collect();
+        let equations = val.equations.iter().map(equation_to_mathjax).collect();
         let variables = val
             .variables
             .iter()
@@ -132,15 +261,31 @@ impl From<Solution> for SolutionTS {
             .iter()
             .map(|x| (*x).clone().into())
             .collect();
+        let result_decompose_steps = val
+            .result_decompose_steps
+            .iter()
+            .map(|x| (*x).clone().into())
+            .collect();
+        let result_clash_steps = val
+            .result_clash_steps
+            .iter()
+            .map(|x| (*x).clone().into())
+            .collect();
 
         SolutionTS {
             rules,
+            equations,
             variables,
             result_remove_steps,
             result_accumulate_steps,
             result_substitute_steps,
+            result_decompose_steps,
+            result_clash_steps,
             result_error,
             result,
+            mismatch,
+            solution: val,
+            rule_sets: OnceCell::new(),
         }
     }
 }
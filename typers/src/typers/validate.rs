@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use super::{parser::AstNode, rules::TypeExpr, tree::Tree, utils::mathjax::MathJax};
+
+/// Replaces every variable which the substitution maps by its type, the substitution is applied only once
+pub fn apply_substitution(typ: &TypeExpr, substitution: &HashMap<usize, TypeExpr>) -> TypeExpr {
+    match typ {
+        TypeExpr::Function(a, b) => TypeExpr::Function(
+            Box::new(apply_substitution(a, substitution)),
+            Box::new(apply_substitution(b, substitution)),
+        ),
+        TypeExpr::Tuple(a, b) => TypeExpr::Tuple(
+            Box::new(apply_substitution(a, substitution)),
+            Box::new(apply_substitution(b, substitution)),
+        ),
+        TypeExpr::Var(x) => substitution.get(x).cloned().unwrap_or(TypeExpr::Var(*x)),
+        TypeExpr::Bool => TypeExpr::Bool,
+        TypeExpr::Int => TypeExpr::Int,
+    }
+}
+
+/// Checks that every node of the derivation tree obeys the typing rule of its expression once the substitution is applied to all types.
+/// The substitution should map every variable to its final type, like the solution of the constraints of the tree does.
+/// It does not rely on `TypeInference`, so it can check trees from any source against the rules of Mini-Haskell.
+/// Returns the first node which breaks its rule.
+pub fn validate_tree(tree: &Tree, substitution: &HashMap<usize, TypeExpr>) -> Result<(), String> {
+    let apply = |typ: &TypeExpr| apply_substitution(typ, substitution);
+    let mut stack = vec![tree];
+    while let Some(tree) = stack.pop() {
+        stack.extend(tree.constraints.iter().rev());
+
+        let ast = tree.expr.0.as_ref();
+        let typ = apply(&tree.expr.1);
+        let premises: Vec<TypeExpr> = tree.constraints.iter().map(|p| apply(&p.expr.1)).collect();
+        let fail = |reason: String| {
+            Err(format!(
+                "the node \\({}\\) breaks the rule {}: {}",
+                ast.to_mathjax(),
+                ast.name(),
+                reason
+            ))
+        };
+
+        let children = ast.children();
+        if premises.len() != children.len() {
+            return fail(format!(
+                "it has {} premises instead of {}",
+                premises.len(),
+                children.len()
+            ));
+        }
+        for (premise, child) in tree.constraints.iter().zip(&children) {
+            if premise.expr.0.as_ref() != child.as_ref() {
+                return fail(format!(
+                    "the premise \\({}\\) is not about \\({}\\)",
+                    premise.expr.0.to_mathjax(),
+                    child.to_mathjax()
+                ));
+            }
+        }
+
+        // only an abstraction extends the context of its premise
+        let gamma: HashMap<&String, TypeExpr> =
+            tree.gamma.iter().map(|(k, v)| (k, apply(v))).collect();
+        for premise in &tree.constraints {
+            let mut expected = gamma.clone();
+            if let AstNode::Abs { var, .. } = ast {
+                match premise.gamma.get(var) {
+                    Some(sigma) => expected.insert(var, apply(sigma)),
+                    None => return fail(format!("the premise does not bind \\({}\\)", var)),
+                };
+            }
+            let actual: HashMap<&String, TypeExpr> =
+                premise.gamma.iter().map(|(k, v)| (k, apply(v))).collect();
+            if actual != expected {
+                return fail("the premise has the wrong context".to_string());
+            }
+        }
+
+        // the type of the node, computed from its premises like the rule does
+        let expected = match ast {
+            AstNode::Var(var) => match gamma.get(var) {
+                Some(typ) => typ.clone(),
+                None => return fail(format!("\\({}\\) is not in the context", var)),
+            },
+            AstNode::Abs { var, .. } => {
+                let sigma = match tree.constraints[0].gamma.get(var) {
+                    Some(sigma) => apply(sigma),
+                    None => return fail(format!("the premise does not bind \\({}\\)", var)),
+                };
+                TypeExpr::Function(Box::new(sigma), Box::new(premises[0].clone()))
+            }
+            AstNode::App { .. } => {
+                let function =
+                    TypeExpr::Function(Box::new(premises[1].clone()), Box::new(typ.clone()));
+                if premises[0] != function {
+                    return fail(format!(
+                        "the function has the type \\({}\\) instead of \\({}\\)",
+                        premises[0].to_mathjax(),
+                        function.to_mathjax()
+                    ));
+                }
+                typ.clone()
+            }
+            AstNode::IsZero(_) | AstNode::BinOp { .. } => {
+                if let Some(premise) = premises.iter().find(|p| **p != TypeExpr::Int) {
+                    return fail(format!(
+                        "a premise has the type \\({}\\) instead of \\(Int\\)",
+                        premise.to_mathjax()
+                    ));
+                }
+                match ast {
+                    AstNode::IsZero(_) => TypeExpr::Bool,
+                    _ => TypeExpr::Int,
+                }
+            }
+            AstNode::Int(_) => TypeExpr::Int,
+            AstNode::True | AstNode::False => TypeExpr::Bool,
+            // a hole can have any type
+            AstNode::Hole(_) => typ.clone(),
+            AstNode::IfThenElse { .. } => {
+                if premises[0] != TypeExpr::Bool {
+                    return fail(format!(
+                        "the condition has the type \\({}\\) instead of \\(Bool\\)",
+                        premises[0].to_mathjax()
+                    ));
+                }
+                if premises[1] != premises[2] {
+                    return fail(format!(
+                        "the branches have different types \\({}\\) and \\({}\\)",
+                        premises[1].to_mathjax(),
+                        premises[2].to_mathjax()
+                    ));
+                }
+                premises[1].clone()
+            }
+            AstNode::Tuple { .. } => {
+                TypeExpr::Tuple(Box::new(premises[0].clone()), Box::new(premises[1].clone()))
+            }
+            AstNode::Fst(_) | AstNode::Snd(_) => match &premises[0] {
+                TypeExpr::Tuple(first, second) => match ast {
+                    AstNode::Fst(_) => *first.clone(),
+                    _ => *second.clone(),
+                },
+                other => {
+                    return fail(format!(
+                        "the premise has the type \\({}\\), which is not a tuple",
+                        other.to_mathjax()
+                    ))
+                }
+            },
+        };
+        if typ != expected {
+            return fail(format!(
+                "the node has the type \\({}\\) instead of \\({}\\)",
+                typ.to_mathjax(),
+                expected.to_mathjax()
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use fmfp::{
+    parse_with_config,
+    typers::{
+        config::Config, rules::TypeExpr, solver::solve_equations_with_config, tree::TypeInference,
+    },
+};
+
+// the default stack size of Wasm, recursing once per level of the expression would overflow it
+const STACK_SIZE: usize = 1 << 20;
+
+const DEPTH: usize = 8_000;
+
+// `\f -> \x -> (f (f ... (f x)))` with `n` applications
+fn application_chain(n: usize) -> String {
+    let mut expr = "x".to_string();
+    for _ in 0..n {
+        expr = format!("(f {})", expr);
+    }
+    format!("(\\f -> (\\x -> {}))", expr)
+}
+
+// `(1, (1, ... (1, true)))` with `n` tuples
+fn tuple_chain(n: usize) -> String {
+    let mut expr = "true".to_string();
+    for _ in 0..n {
+        expr = format!("(1, {})", expr);
+    }
+    expr
+}
+
+// parses the input and solves its constraints without recording the steps, on a thread with the stack of Wasm
+fn solve_deep(input: String) -> TypeExpr {
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let config = Config {
+                max_input_length: usize::MAX,
+                max_ast_depth: usize::MAX,
+                max_type_vars: usize::MAX,
+                max_constraints: usize::MAX,
+                record_steps: false,
+                ..Config::default()
+            };
+            let ast = parse_with_config(&input, &config).unwrap();
+            let (_, constraints) = TypeInference::infer_with_config(ast, &config).unwrap();
+            let solution = solve_equations_with_config(constraints, 0, &config);
+            *solution.result.unwrap().unwrap().rhs
+        })
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+#[test]
+fn deeply_nested_expressions_are_parsed_and_solved() {
+    let typ = solve_deep(application_chain(DEPTH));
+    let x = TypeExpr::Var(3);
+    let f = TypeExpr::Function(Box::new(x.clone()), Box::new(x.clone()));
+    assert_eq!(typ, TypeExpr::Function(Box::new(f.clone()), Box::new(f)));
+
+    // the type is as deep as the expression
+    let mut typ = solve_deep(tuple_chain(DEPTH));
+    for _ in 0..DEPTH {
+        let TypeExpr::Tuple(first, second) = typ else {
+            panic!("the type has less than {} components", DEPTH);
+        };
+        assert_eq!(*first, TypeExpr::Int);
+        typ = *second;
+    }
+    assert_eq!(typ, TypeExpr::Bool);
+}
+
+#[test]
+fn the_depth_limit_rejects_deep_expressions_before_parsing() {
+    let config = Config::default();
+    let depth = config.max_ast_depth;
+    assert!(parse_with_config(&application_chain(depth - 2), &config).is_ok());
+    let e = parse_with_config(&application_chain(depth - 1), &config).unwrap_err();
+    assert!(e.contains("nested more than"), "{}", e);
+}
@@ -0,0 +1,215 @@
+use fmfp::{
+    grade_tree,
+    typers::{grading::MistakeKind, rules::TypeExpr, type_parser::TypeParser},
+};
+
+const INPUT: &str = "((\\x -> (x + 1)) 2)";
+
+// the example of the readme
+const TREE: &str = "\
+[App] |- ((\\x -> (x + 1)) 2) :: t0
+  [Abs] |- (\\x -> (x + 1)) :: t1 -> t0
+    [BinOp] x: t1 |- (x + 1) :: t0
+      [Var] x: t1 |- x :: Int
+      [Int] x: t1 |- 1 :: Int
+  [Int] |- 2 :: t1
+";
+
+const FST: &str = "\
+[fst] |- (fst (1, true)) :: t0
+  [tuple] |- (1, true) :: (t0, t2)
+    [Int] |- 1 :: t0
+    [True] |- true :: t2
+";
+
+const SND: &str = "\
+[snd] |- (snd (1, true)) :: t2
+  [tuple] |- (1, true) :: (t0, t2)
+    [Int] |- 1 :: t0
+    [True] |- true :: t2
+";
+
+// the first mistake of the tree, as its node, line and kind
+fn mistake(input: &str, tree: &str) -> Option<(usize, usize, MistakeKind)> {
+    grade_tree(input, tree)
+        .unwrap()
+        .mistake
+        .map(|mistake| (mistake.node, mistake.line, mistake.kind))
+}
+
+#[test]
+fn the_tree_of_the_readme_is_correct() {
+    assert_eq!(mistake(INPUT, TREE), None);
+    assert_eq!(mistake("(fst (1, true))", FST), None);
+    assert_eq!(mistake("(snd (1, true))", SND), None);
+}
+
+#[test]
+fn a_wrong_rule_is_reported() {
+    let tree = TREE.replace("[Var]", "[Int]");
+    assert_eq!(mistake(INPUT, &tree), Some((3, 4, MistakeKind::WrongRule)));
+    // a binary operation may also be named by its operator
+    assert_eq!(mistake(INPUT, &TREE.replace("[BinOp]", "[+]")), None);
+}
+
+#[test]
+fn a_wrong_context_is_reported() {
+    // the body of the abstraction has to know the type of x
+    let tree = TREE.replace("[BinOp] x: t1 |-", "[BinOp] |-");
+    assert_eq!(
+        mistake(INPUT, &tree),
+        Some((1, 2, MistakeKind::WrongContext))
+    );
+
+    // the premises of a binary operation use the context of the conclusion
+    let tree = TREE.replace("[Int] x: t1 |- 1", "[Int] x: Int |- 1");
+    assert_eq!(
+        mistake(INPUT, &tree),
+        Some((2, 3, MistakeKind::WrongContext))
+    );
+}
+
+#[test]
+fn a_wrong_premise_of_an_application_is_reported() {
+    // the function has to map the type of the argument to the type of the application
+    let tree = TREE.replace(":: t1 -> t0", ":: t1 -> t5");
+    assert_eq!(
+        mistake(INPUT, &tree),
+        Some((0, 1, MistakeKind::WrongPremise))
+    );
+    let grade = grade_tree(INPUT, &tree).unwrap();
+    assert!(grade
+        .mistake
+        .unwrap()
+        .message
+        .contains("has to have the type"));
+
+    // an application has two premises
+    let tree: String = TREE
+        .lines()
+        .take(5)
+        .map(|line| line.to_string() + "\n")
+        .collect();
+    assert_eq!(
+        mistake(INPUT, &tree),
+        Some((0, 1, MistakeKind::WrongPremise))
+    );
+}
+
+#[test]
+fn a_wrong_premise_of_fst_and_snd_is_reported() {
+    // the first component has to be the type of the conclusion
+    let tree = FST.replace(":: (t0, t2)", ":: (t1, t2)");
+    assert_eq!(
+        mistake("(fst (1, true))", &tree),
+        Some((0, 1, MistakeKind::WrongPremise))
+    );
+    // and the second one for snd
+    let tree = SND.replace(":: t2\n  [tuple]", ":: t0\n  [tuple]");
+    assert_eq!(
+        mistake("(snd (1, true))", &tree),
+        Some((0, 1, MistakeKind::WrongPremise))
+    );
+}
+
+#[test]
+fn the_line_of_the_mistake_counts_empty_lines() {
+    let tree = TREE
+        .replace("  [Abs]", "\n\n  [Abs]")
+        .replace("[Int] |- 2", "[Bool] |- 2");
+    assert_eq!(mistake(INPUT, &tree), Some((5, 8, MistakeKind::WrongRule)));
+
+    // the root has to derive the expression of the exercise
+    assert_eq!(
+        mistake("(\\x -> (x + 1))", TREE),
+        Some((0, 1, MistakeKind::WrongConclusion))
+    );
+}
+
+#[test]
+fn derivations_are_parsed_in_pre_order() {
+    let derivation = TypeParser::parse_derivation(TREE).unwrap();
+    assert_eq!(
+        derivation.rules,
+        ["App", "Abs", "BinOp", "Var", "Int", "Int"]
+    );
+    assert_eq!(derivation.lines, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(derivation.tree.constraints.len(), 2);
+    assert_eq!(
+        derivation.tree.constraints[0].constraints[0]
+            .constraints
+            .len(),
+        2
+    );
+    let var = &derivation.tree.constraints[0].constraints[0].gamma;
+    assert_eq!(var.get("x"), Some(&TypeExpr::Var(1)));
+
+    let e = TypeParser::parse_derivation("[Int] |- 1 :: Int\n[Int] |- 2 :: Int").unwrap_err();
+    assert!(e.starts_with("line 2: the tree has only one root"), "{}", e);
+    let e = TypeParser::parse_derivation("[Int] |- 1 :: Int\n  Int |- 2 :: Int").unwrap_err();
+    assert!(e.starts_with("line 2: a judgment starts with"), "{}", e);
+    let e = TypeParser::parse_derivation("[Var] x: t1, x: t2 |- x :: t1").unwrap_err();
+    assert!(e.contains("x is bound twice"), "{}", e);
+}
+
+#[test]
+fn types_and_constraints_are_parsed_like_they_are_displayed() {
+    let typ = TypeParser::parse_type("t_{1} -> (Int, t2) -> Bool").unwrap();
+    assert_eq!(typ.to_string(), "t1 -> (Int, t2) -> Bool");
+    let typ = TypeParser::parse_type("(t1 -> t2) -> t3").unwrap();
+    assert!(matches!(typ, TypeExpr::Function(ref a, _) if matches!(**a, TypeExpr::Function(_, _))));
+
+    let constraints = TypeParser::parse_constraints("\nt0 = t1 -> t2; t_2 = Int\n\n").unwrap();
+    assert_eq!(
+        constraints,
+        [
+            (
+                TypeExpr::Var(0),
+                TypeParser::parse_type("t1 -> t2").unwrap()
+            ),
+            (TypeExpr::Var(2), TypeExpr::Int),
+        ]
+    );
+    assert!(TypeParser::parse_constraints("t0 = ").is_err());
+}
+
+#[test]
+fn a_conclusion_which_contradicts_its_rule_is_reported() {
+    let wrong_type = Some((0, 1, MistakeKind::WrongType));
+    assert_eq!(mistake("1", "[Int] |- 1 :: Bool"), wrong_type);
+    assert_eq!(mistake("true", "[True] |- true :: Int"), wrong_type);
+    assert_eq!(mistake("false", "[False] |- false :: t0 -> t1"), wrong_type);
+    assert_eq!(
+        mistake(
+            "(iszero 0)",
+            "[IsZero] |- (iszero 0) :: Int\n  [Int] |- 0 :: Int"
+        ),
+        wrong_type
+    );
+    let tree = "[BinOp] |- (1 + 2) :: Bool\n  [Int] |- 1 :: Int\n  [Int] |- 2 :: Int";
+    assert_eq!(mistake("(1 + 2)", tree), wrong_type);
+
+    // a variable has the type of the context, unless that one is still open
+    let tree = "[Abs] |- (\\x -> x) :: t0\n  [Var] x: Int |- x :: Bool";
+    assert_eq!(
+        mistake("(\\x -> x)", tree),
+        Some((1, 2, MistakeKind::WrongType))
+    );
+    let tree = "[Abs] |- (\\x -> x) :: t1 -> Int\n  [Var] x: t1 |- x :: Int";
+    assert_eq!(mistake("(\\x -> x)", tree), None);
+
+    // an abstraction maps the type of its parameter to the type of its body
+    let tree = "[Abs] |- (\\x -> x) :: t1 -> t0\n  [Var] x: t1 |- x :: Bool";
+    assert_eq!(mistake("(\\x -> x)", tree), wrong_type);
+    let tree = "[Abs] |- (\\x -> x) :: t2 -> Bool\n  [Var] x: t1 |- x :: Bool";
+    assert_eq!(mistake("(\\x -> x)", tree), wrong_type);
+    let tree = "[Abs] |- (\\x -> x) :: t0\n  [Var] x: t1 |- x :: Bool";
+    assert_eq!(mistake("(\\x -> x)", tree), None);
+
+    // a tuple has the types of its components
+    let tree = FST.replace(":: (t0, t2)", ":: (t0, Bool)");
+    assert_eq!(
+        mistake("(fst (1, true))", &tree),
+        Some((1, 2, MistakeKind::WrongType))
+    );
+}
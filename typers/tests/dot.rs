@@ -0,0 +1,109 @@
+use fmfp::{
+    run_pipeline, solve_to_dot,
+    typers::{
+        config::Config,
+        rules::RuleExpr,
+        type_parser::TypeParser,
+        utils::dot::{constraint_graph, DotGraph},
+    },
+};
+
+fn rule(var: usize, rhs: &str) -> RuleExpr {
+    RuleExpr {
+        var,
+        rhs: Box::new(TypeParser::parse_type(rhs).unwrap()),
+        origin: None,
+    }
+}
+
+// the attributes of the statement starting with `start`, e.g. a node `t1 [` or an edge `t1 -> t2`
+fn attributes<'a>(dot: &'a str, start: &str) -> &'a str {
+    dot.lines()
+        .map(str::trim_start)
+        .find(|line| {
+            line.strip_prefix(start)
+                .is_some_and(|rest| rest.starts_with(' ') || rest.starts_with(';'))
+        })
+        .unwrap_or_else(|| panic!("{} is missing in\n{}", start, dot))
+}
+
+#[test]
+fn the_cycle_and_the_goal_of_self_application_are_highlighted() {
+    let dot = solve_to_dot("(\\x -> (x x))", DotGraph::Constraints).unwrap();
+    let expected = concat!(
+        "digraph constraints {\n",
+        "  rankdir=LR;\n",
+        "  node [shape=circle, fontname=\"monospace\"];\n",
+        "  t0 [label=\"t0\\n= t1 -> t2\", shape=box, peripheries=2, style=bold];\n",
+        "  t1 [label=\"t1\\n= t3 -> t2\", shape=box];\n",
+        "  t2 [label=\"t2\"];\n",
+        "  t3 [label=\"t3\\n= t3 -> t2\", shape=box, color=red, fontcolor=red];\n",
+        "  t0 -> t1;\n",
+        "  t0 -> t2;\n",
+        "  t1 -> t2;\n",
+        "  t1 -> t3;\n",
+        "  t3 -> t2;\n",
+        "  t3 -> t3 [color=red, penwidth=2];\n",
+        "}\n",
+    );
+    assert_eq!(dot, expected);
+
+    let dot = solve_to_dot("(\\x -> x)", DotGraph::Constraints).unwrap();
+    assert!(!dot.contains("red"), "{}", dot);
+    assert!(attributes(&dot, "t0").contains("peripheries=2"), "{}", dot);
+}
+
+#[test]
+fn only_the_edges_within_a_cycle_are_red() {
+    // t1 and t2 depend on each other, t0 only leads into the cycle and t4 is the goal outside of all rules
+    let rules = vec![
+        rule(0, "t1 -> Int"),
+        rule(1, "t2 -> Bool"),
+        rule(2, "(t1, t3)"),
+    ];
+    let dot = constraint_graph(&rules, 4);
+    for red in ["t1", "t2", "t1 -> t2", "t2 -> t1"] {
+        assert!(attributes(&dot, red).contains("color=red"), "{}", red);
+    }
+    for plain in ["t0", "t3", "t0 -> t1", "t2 -> t3"] {
+        assert!(!attributes(&dot, plain).contains("red"), "{}", plain);
+    }
+    let goal = attributes(&dot, "t4");
+    assert!(
+        goal.contains("peripheries=2") && !goal.contains("shape=box"),
+        "{}",
+        goal
+    );
+    assert_eq!(dot.matches("peripheries=2").count(), 1);
+}
+
+#[test]
+fn trees_have_one_node_per_judgment_and_edges_towards_the_root() {
+    let input = "(\\f -> (\\x -> (if (iszero (f x)) then (f 1) else (fst (x, x)))))";
+    let run = run_pipeline(input, &Config::default()).unwrap();
+    let nodes = run.tree.preorder().len();
+
+    let dot = solve_to_dot(input, DotGraph::Tree).unwrap();
+    assert!(dot.starts_with("digraph derivation {\n  rankdir=BT;\n"));
+    assert_eq!(dot.matches(" [label=").count(), nodes);
+    assert!(
+        attributes(&dot, "n0").ends_with(", style=bold];"),
+        "{}",
+        dot
+    );
+    assert!(attributes(&dot, "n0").contains("\"⊢ \\\\f -> "), "{}", dot);
+    // every node but the root is the premise of exactly one conclusion
+    for id in 0..nodes {
+        let outgoing = dot.matches(&format!("  n{} -> ", id)).count();
+        assert_eq!(outgoing, usize::from(id != 0), "n{}", id);
+    }
+
+    let dot = solve_to_dot(input, DotGraph::Ast).unwrap();
+    assert!(dot.starts_with("digraph ast {\n"));
+    let edges = dot.lines().filter(|line| line.contains(" -> ")).count();
+    assert_eq!(dot.matches(" [label=").count(), 2 * edges + 1);
+    for edge in ["body", "fun", "arg", "cond", "then", "else", "fst", "snd"] {
+        assert!(dot.contains(&format!("[label=\"{}\"];", edge)), "{}", edge);
+    }
+    assert!(attributes(&dot, "n0").contains("label=\"λf\""), "{}", dot);
+}
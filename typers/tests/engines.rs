@@ -0,0 +1,76 @@
+use fmfp::{
+    compare_engines,
+    typers::{engines::canonical, rules::TypeExpr, type_parser::TypeParser},
+};
+
+const TYPABLE: &[&str] = &[
+    "1",
+    "(\\x -> x)",
+    "(\\x -> (x + 1))",
+    "(\\p -> ((fst p) + (snd p)))",
+    "(\\f -> (\\g -> (\\x -> (f (g x)))))",
+    "((\\f -> (f (iszero 0))) (\\b -> (if b then 1 else 2)))",
+    "(\\f -> (\\x -> ((f x), (f 1))))",
+    "(\\f -> (if _c then (f _a) else (snd _)))",
+];
+
+// Expressions with a clash, an infinite type and a free variable
+const UNTYPABLE: &[&str] = &[
+    "(1 + true)",
+    "(if 1 then 2 else 3)",
+    "(\\f -> ((f 1), (f true)))",
+    "(\\x -> (x x))",
+    "(y + 1)",
+];
+
+fn typ(input: &str) -> TypeExpr {
+    TypeParser::parse_type(input).unwrap()
+}
+
+#[test]
+fn all_engines_agree_on_typable_expressions() {
+    for input in TYPABLE {
+        let comparison = compare_engines(input).unwrap();
+        assert_eq!(comparison.runs.len(), 4);
+        assert!(comparison.agree, "{}", input);
+        let first = canonical(comparison.runs[0].result.as_ref().unwrap());
+        for run in &comparison.runs {
+            let result = run.result.as_ref().unwrap_or_else(|e| {
+                panic!("{} failed on {}: {}", run.engine, input, e);
+            });
+            assert_eq!(canonical(result), first, "{} on {}", run.engine, input);
+            assert!(!run.trace.is_empty());
+        }
+    }
+
+    let comparison = compare_engines("(\\f -> (\\g -> (\\x -> (f (g x)))))").unwrap();
+    assert_eq!(
+        canonical(comparison.runs[1].result.as_ref().unwrap()),
+        typ("(t0 -> t1) -> (t2 -> t0) -> t2 -> t1")
+    );
+}
+
+#[test]
+fn all_engines_agree_that_untypable_expressions_have_no_type() {
+    for input in UNTYPABLE {
+        let comparison = compare_engines(input).unwrap();
+        assert!(comparison.agree, "{}", input);
+        for run in &comparison.runs {
+            assert!(
+                run.result.is_err(),
+                "{} found a type for {}",
+                run.engine,
+                input
+            );
+        }
+    }
+}
+
+#[test]
+fn canonical_types_are_numbered_from_left_to_right() {
+    assert_eq!(
+        canonical(&typ("(t7 -> t3) -> (t3, t7) -> Int")),
+        typ("(t0 -> t1) -> (t1, t0) -> Int")
+    );
+    assert_ne!(canonical(&typ("t4 -> t4")), canonical(&typ("t4 -> t5")));
+}
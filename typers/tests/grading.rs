@@ -0,0 +1,88 @@
+use fmfp::{
+    grade,
+    typers::{rules::TypeExpr, type_parser::TypeParser},
+};
+
+const TWICE: &str = "(\\f -> (\\x -> (f (f x))))";
+
+fn constraint(input: &str) -> (TypeExpr, TypeExpr) {
+    TypeParser::parse_constraints(input).unwrap().remove(0)
+}
+
+#[test]
+fn a_correct_answer_may_name_the_variables_differently() {
+    let answer =
+        "t10 = t11 -> t12; t12 = t13 -> t14; t11 = t15 -> t14; t11 = t16 -> t15; t13 = t16";
+    let grade = grade(TWICE, answer, Some("(t7 -> t7) -> t7 -> t7".to_string())).unwrap();
+    assert!(grade.is_correct(), "{:?}", grade);
+    assert_eq!(grade.correct.len(), 5);
+    let renaming: Vec<(usize, usize)> = grade.renaming.into_iter().collect();
+    assert_eq!(
+        renaming,
+        [
+            (10, 0),
+            (11, 1),
+            (12, 2),
+            (13, 3),
+            (14, 4),
+            (15, 5),
+            (16, 6)
+        ]
+    );
+}
+
+#[test]
+fn constraints_may_be_written_the_other_way_around() {
+    let answer = "t1 -> t2 = t0; t3 -> t4 = t2; t1 = t5 -> t4; t6 -> t5 = t1; t6 = t3";
+    let grade = grade(TWICE, answer, Some("(t3 -> t3) -> t3 -> t3".to_string())).unwrap();
+    assert!(grade.is_correct(), "{:?}", grade);
+    assert_eq!(grade.correct[0].0, constraint("t1 -> t2 = t0"));
+    assert_eq!(grade.correct[0].1, constraint("t0 = t1 -> t2"));
+}
+
+#[test]
+fn duplicate_and_trivial_constraints_are_ignored() {
+    // the expected constraints contain the trivial `Int = Int`
+    let answer = "t0 = t1 -> t2; t2 = Int; t1 = Int; Int = t1; t0 = t1 -> t2; t5 = t5";
+    let grade = grade("(\\x -> (x + 1))", answer, Some("Int -> Int".to_string())).unwrap();
+    assert!(grade.is_correct(), "{:?}", grade);
+    assert_eq!(grade.correct.len(), 3);
+}
+
+#[test]
+fn a_wrong_constraint_is_told_apart_from_an_extra_one() {
+    let answer = "t0 = t1 -> t2; t2 = Bool; t1 = Int; t9 = Int";
+    let grade = grade("(\\x -> (x + 1))", answer, Some("Int -> Int".to_string())).unwrap();
+    assert!(!grade.is_correct());
+    assert_eq!(grade.correct.len(), 2);
+    // `t2 = Bool` is about the variable of the expected `t2 = Int`
+    assert_eq!(
+        grade.wrong,
+        [(constraint("t2 = Bool"), constraint("t2 = Int"))]
+    );
+    // `t9` is not one of the expected variables
+    assert_eq!(grade.extra, [constraint("t9 = Int")]);
+    assert!(grade.missing.is_empty());
+    assert!(grade.type_correct);
+}
+
+#[test]
+fn a_wrong_final_type_is_reported() {
+    let constraints = "t0 = t1 -> t2; t2 = t3 -> t4; t1 = t5 -> t4; t1 = t6 -> t5; t3 = t6";
+    for (final_type, correct) in [
+        (Some("(t7 -> t7) -> t7 -> t7"), true),
+        // the variables have to be renamed consistently
+        (Some("(t7 -> t8) -> t7 -> t8"), false),
+        (Some("(Int -> Int) -> Int -> Int"), false),
+        (None, false),
+    ] {
+        let grade = grade(TWICE, constraints, final_type.map(str::to_string)).unwrap();
+        assert_eq!(grade.type_correct, correct, "{:?}", final_type);
+        assert_eq!(grade.correct.len(), 5);
+    }
+
+    // claiming that there is no type is correct if there is none
+    let grade = grade("(1 + true)", "", None).unwrap();
+    assert!(grade.type_correct);
+    assert!(grade.expected_type.is_none());
+}
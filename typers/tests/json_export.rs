@@ -0,0 +1,40 @@
+use fmfp::{
+    export, solve_to_json_with_config,
+    typers::{config::Config, utils::json::Export},
+};
+
+#[test]
+fn exports_are_compact_unless_asked_otherwise() {
+    let config = Config::default();
+    let json = solve_to_json_with_config("(\\x -> (x + 1))", &config).unwrap();
+    assert!(!json.contains('\n'));
+    let pretty = export("(\\x -> (x + 1))", &config)
+        .unwrap()
+        .to_json_pretty(&config)
+        .unwrap();
+    assert!(pretty.contains('\n'));
+
+    let read = Export::from_json(&json, &config).unwrap();
+    assert_eq!(read.to_json(&config).unwrap(), json);
+}
+
+#[test]
+fn long_exports_are_rejected() {
+    let config = Config {
+        max_output_length: 1_000,
+        ..Config::default()
+    };
+    let e = solve_to_json_with_config("(\\f -> (\\x -> ((f x), (f 1))))", &config).unwrap_err();
+    assert_eq!(e, "the JSON export is longer than 1000 bytes");
+
+    // a short input whose derivation tree repeats a deep subexpression at every node
+    let mut input = "1".to_string();
+    for _ in 0..499 {
+        input = format!("({}, 1)", input);
+    }
+    let config = Config::default();
+    let json = solve_to_json_with_config(&input, &config).unwrap();
+    assert!(json.len() <= config.max_output_length);
+    let pretty = export(&input, &config).unwrap().to_json_pretty(&config);
+    assert!(pretty.is_err());
+}
@@ -0,0 +1,151 @@
+use fmfp::{
+    run_pipeline,
+    typers::{
+        config::Config,
+        utils::latex::{constraint_table, document, proof_tree, solution_steps},
+    },
+};
+
+const INPUTS: &[&str] = &[
+    "1",
+    "((\\x -> (x + 1)) 2)",
+    "(\\f -> (\\x -> (if (iszero (f x)) then (f 1) else (fst (x, x)))))",
+    "(snd (true, _))",
+    // untypable, the document ends with the error of the solver
+    "(\\x -> (x x))",
+    "(if 1 then 2 else 3)",
+];
+
+// checks that every brace, environment and formula which is opened is closed again, in the right order
+fn balanced(latex: &str) -> Result<(), String> {
+    let mut open: Vec<String> = vec![];
+    let mut rest = latex;
+    while let Some(c) = rest.chars().next() {
+        let mut skip = c.len_utf8();
+        match c {
+            // a command, `\{` and `\$` are escaped characters, `\(` and `\)` delimit a formula
+            '\\' => {
+                let command: String = rest[1..]
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphabetic())
+                    .collect();
+                skip += command.len().max(1);
+                match (command.as_str(), rest[1..].chars().next()) {
+                    ("begin" | "end", _) => {
+                        let name = rest[skip..]
+                            .strip_prefix('{')
+                            .and_then(|name| name.split_once('}'))
+                            .map(|(name, _)| name.to_string())
+                            .ok_or("an environment without a name")?;
+                        skip += name.len() + 2;
+                        if command == "begin" {
+                            open.push(name);
+                        } else if open.pop() != Some(name.clone()) {
+                            return Err(format!("\\end{{{}}} closes something else", name));
+                        }
+                    }
+                    ("", Some('(')) => open.push("\\(".to_string()),
+                    ("", Some(')')) if open.pop().as_deref() != Some("\\(") => {
+                        return Err("\\) without \\(".to_string())
+                    }
+                    _ => (),
+                }
+            }
+            '{' => open.push("{".to_string()),
+            '}' if open.pop().as_deref() != Some("{") => return Err("} without {".to_string()),
+            '$' if open.last().map(String::as_str) == Some("$") => {
+                open.pop();
+            }
+            '$' => open.push("$".to_string()),
+            _ => (),
+        }
+        rest = &rest[skip..];
+    }
+    match open.last() {
+        Some(unclosed) => Err(format!("{} is not closed", unclosed)),
+        None => Ok(()),
+    }
+}
+
+#[test]
+fn documents_are_balanced() {
+    for input in INPUTS {
+        let run = run_pipeline(input, &Config::default()).unwrap();
+        let latex = document(&run.ast, &run.tree, &run.constraints, &run.solution);
+        balanced(&latex).unwrap_or_else(|e| panic!("{}: {}\n{}", input, e, latex));
+        assert!(latex.starts_with("\\documentclass{article}\n"));
+        assert!(latex.ends_with("\\end{document}\n"));
+    }
+    assert!(balanced("\\begin{center}{x}\\end{align*}").is_err());
+    assert!(balanced("${x}").is_err());
+}
+
+#[test]
+fn proof_trees_leave_exactly_one_conclusion_on_the_bussproofs_stack() {
+    for input in INPUTS {
+        let run = run_pipeline(input, &Config::default()).unwrap();
+        let latex = proof_tree(&run.tree);
+
+        // every axiom pushes a proof, every inference replaces as many proofs as it has premises by its conclusion
+        let mut proofs = 0;
+        let mut inferences = 0;
+        for line in latex.lines() {
+            let premises = if line.starts_with("\\AxiomC") {
+                proofs += 1;
+                continue;
+            } else if line.starts_with("\\UnaryInfC") {
+                1
+            } else if line.starts_with("\\BinaryInfC") {
+                2
+            } else if line.starts_with("\\TrinaryInfC") {
+                3
+            } else {
+                continue;
+            };
+            assert!(
+                proofs >= premises,
+                "{}: an inference lacks a premise",
+                input
+            );
+            proofs -= premises - 1;
+            inferences += 1;
+        }
+        assert_eq!(proofs, 1, "{}", input);
+        assert_eq!(inferences, run.tree.preorder().len(), "{}", input);
+        assert!(latex.contains("\\DisplayProof"));
+    }
+}
+
+#[test]
+fn the_constraints_and_steps_are_numbered_from_one() {
+    let run = run_pipeline(INPUTS[2], &Config::default()).unwrap();
+    let table = constraint_table(&run.constraints);
+    let rows: Vec<&str> = table
+        .lines()
+        .filter(|line| line.ends_with("\\\\"))
+        .collect();
+    // the header is a row as well
+    assert_eq!(rows.len(), run.constraints.len() + 1);
+    for (i, row) in rows[1..].iter().enumerate() {
+        assert!(row.starts_with(&format!("{} & $", i + 1)), "{}", row);
+    }
+
+    let steps = solution_steps(&run.solution);
+    let headings: Vec<&str> = steps
+        .lines()
+        .filter(|line| line.starts_with("\\subsection*{Step"))
+        .collect();
+    assert_eq!(headings.len(), run.solution.num_steps());
+    for (i, heading) in headings.iter().enumerate() {
+        assert!(
+            heading.starts_with(&format!("\\subsection*{{Step {}: ", i + 1)),
+            "{}",
+            heading
+        );
+    }
+    assert!(steps.ends_with("\\]\n"), "{}", steps);
+
+    let run = run_pipeline(INPUTS[4], &Config::default()).unwrap();
+    let steps = solution_steps(&run.solution);
+    assert!(steps.contains("\\subsection*{Result}\nThe constraints cannot be solved: "));
+}
@@ -0,0 +1,62 @@
+use fmfp::typers::{
+    rules::{Mismatch, TypeExpr, TypePosition},
+    solver::solve_equations,
+    type_parser::TypeParser,
+};
+
+fn typ(input: &str) -> TypeExpr {
+    TypeParser::parse_type(input).unwrap()
+}
+
+#[test]
+fn the_position_is_named_from_the_inside_out() {
+    let mismatch = Mismatch {
+        path: vec![TypePosition::Argument, TypePosition::Result],
+        expected: TypeExpr::Int,
+        found: TypeExpr::Bool,
+    };
+    assert_eq!(
+        mismatch.position().as_deref(),
+        Some("the result of the argument")
+    );
+
+    let mismatch = Mismatch::new(&TypeExpr::Int, &TypeExpr::Bool).inside(TypePosition::Second);
+    assert_eq!(mismatch.position().as_deref(), Some("the second component"));
+}
+
+#[test]
+fn outermost_types_which_differ_have_no_position() {
+    let mismatch = Mismatch::new(&typ("Int -> t1"), &typ("(Int, t1)"));
+    assert!(mismatch.path.is_empty());
+    assert_eq!(mismatch.position(), None);
+}
+
+#[test]
+fn comparing_types_finds_the_path_to_the_clash() {
+    let mismatch = typ("(t5 -> Int) -> t1")
+        .compare_types(&typ("(t6 -> Bool) -> t2"))
+        .unwrap_err();
+    assert_eq!(
+        mismatch.path,
+        [TypePosition::Argument, TypePosition::Result]
+    );
+    assert_eq!(mismatch.expected, TypeExpr::Int);
+    assert_eq!(mismatch.found, TypeExpr::Bool);
+
+    // the solver keeps the mismatch of the two rules it could not combine
+    let solution = solve_equations(
+        vec![
+            (typ("t1"), typ("t5 -> Int")),
+            (typ("t1"), typ("t6 -> Bool")),
+        ],
+        1,
+    );
+    let mismatch = solution.mismatch.unwrap();
+    assert_eq!(mismatch.position().as_deref(), Some("the result"));
+    let e = solution.result.unwrap().unwrap_err();
+    assert!(
+        e.ends_with("clashes with \\(Bool\\) in the result"),
+        "{}",
+        e
+    );
+}
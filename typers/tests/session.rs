@@ -0,0 +1,123 @@
+use fmfp::{
+    parse,
+    typers::{
+        config::{Config, SolverStrategy},
+        rules::{RuleExpr, TypeExpr},
+        session::SolverSession,
+        solver::{solve_equations_with_config, Action},
+        tree::TypeInference,
+    },
+};
+
+const EXPRESSIONS: &[&str] = &[
+    "(\\x -> (x + 1))",
+    "(\\f -> (\\x -> ((f x), (f 1))))",
+    "(\\p -> ((fst p) + (snd p)))",
+    "((\\f -> (f (iszero 0))) (\\b -> (if b then 1 else 2)))",
+    "(\\x -> (x x))",
+    "(if 1 then 2 else 3)",
+];
+
+fn constraints(input: &str) -> Vec<(TypeExpr, TypeExpr)> {
+    TypeInference::infer(parse(input).unwrap()).unwrap().1
+}
+
+fn rule(var: usize, rhs: TypeExpr) -> RuleExpr {
+    RuleExpr {
+        var,
+        rhs: Box::new(rhs),
+        origin: None,
+    }
+}
+
+#[test]
+fn following_the_hints_gives_the_trace_of_the_solver() {
+    for input in EXPRESSIONS {
+        for strategy in SolverStrategy::all() {
+            let config = Config {
+                strategy,
+                ..Config::default()
+            };
+            let mut session = SolverSession::from_equations(constraints(input), 0, &config);
+            while let Some(hint) = session.hint() {
+                session.apply_action(&hint.action).unwrap();
+            }
+            let expected = solve_equations_with_config(constraints(input), 0, &config);
+            assert_eq!(
+                serde_json::to_value(session.solution()).unwrap(),
+                serde_json::to_value(&expected).unwrap(),
+                "{} with {}",
+                input,
+                strategy.name()
+            );
+        }
+    }
+}
+
+#[test]
+fn previous_step_undoes_one_step() {
+    let config = Config::default();
+    let mut session = SolverSession::from_equations(constraints(EXPRESSIONS[1]), 0, &config);
+    session.next_step().unwrap();
+    let rules = session.current_rules().0;
+    session.next_step().unwrap();
+    assert!(session.previous_step());
+    assert_eq!(session.num_steps(), 1);
+    assert_eq!(session.current_rules().0, rules);
+    assert_eq!(session.solution().num_steps(), 1);
+    assert!(session.previous_step());
+    assert!(!session.previous_step());
+}
+
+#[test]
+fn rejected_actions_change_nothing() {
+    let config = Config::default();
+    let mut session = SolverSession::from_rules(
+        vec![rule(0, TypeExpr::Var(1)), rule(1, TypeExpr::Int)],
+        0,
+        &config,
+    );
+    assert!(session.apply_action(&Action::Remove { rule: 1 }).is_err());
+    assert!(session
+        .apply_action(&Action::Accumulate {
+            first: 0,
+            second: 1
+        })
+        .is_err());
+    assert_eq!(session.num_steps(), 0);
+    assert!(!session.previous_step());
+}
+
+#[test]
+fn an_action_which_stops_the_solver_can_be_undone() {
+    let config = Config::default();
+    let rules = vec![
+        rule(0, TypeExpr::Var(1)),
+        rule(1, TypeExpr::Int),
+        rule(1, TypeExpr::Bool),
+    ];
+    let mut session = SolverSession::from_rules(rules, 0, &config);
+    session
+        .apply_action(&Action::Remove { rule: 0 })
+        .unwrap()
+        .unwrap();
+    let after_remove = session.current_rules().0;
+
+    // comparing the clashing rules shows that there is no type
+    let step = session
+        .apply_action(&Action::Accumulate {
+            first: 0,
+            second: 1,
+        })
+        .unwrap();
+    assert!(step.is_none());
+    assert!(session.is_done());
+    assert!(matches!(session.solution().result, Some(Err(_))));
+
+    // one undo goes back to right before the failed comparison
+    assert!(session.previous_step());
+    assert!(!session.is_done());
+    assert_eq!(session.current_rules().0, after_remove);
+    assert_eq!(session.num_steps(), 1);
+    assert!(session.solution().result.is_none());
+}
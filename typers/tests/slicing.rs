@@ -0,0 +1,90 @@
+use fmfp::{
+    run_pipeline, slice_error,
+    typers::{
+        config::Config,
+        rules::TypeExpr,
+        slicing::{self, unsatisfiable_core},
+        solver::solve_equations_with_config,
+    },
+};
+
+const UNTYPABLE: &[&str] = &[
+    "(1 + true)",
+    "(if 1 then 2 else 3)",
+    "(\\f -> ((f 1), (f true)))",
+    "(\\x -> (x x))",
+    "(\\f -> (\\x -> (if (iszero (f x)) then (f true) else (x + 1))))",
+    "((\\p -> (fst p)) (\\y -> y))",
+];
+
+fn solvable(constraints: Vec<(TypeExpr, TypeExpr)>) -> bool {
+    let config = Config {
+        record_steps: false,
+        ..Config::default()
+    };
+    matches!(
+        solve_equations_with_config(constraints, 0, &config).result,
+        Some(Ok(_))
+    )
+}
+
+#[test]
+fn the_core_is_a_minimal_unsolvable_set_of_constraints() {
+    for input in UNTYPABLE {
+        let constraints = run_pipeline(input, &Config::default()).unwrap().constraints;
+        let core = unsatisfiable_core(&constraints).unwrap();
+        let slice: Vec<_> = core.iter().map(|i| constraints[*i].clone()).collect();
+        assert!(!solvable(slice.clone()), "{}", input);
+        for (left_out, index) in core.iter().enumerate() {
+            let mut rest = slice.clone();
+            rest.remove(left_out);
+            assert!(
+                solvable(rest),
+                "{} stays unsolvable without constraint {}",
+                input,
+                index
+            );
+        }
+
+        let error = slice_error(input).unwrap();
+        assert_eq!(error.indices, core);
+        assert_eq!(error.constraints, slice);
+        assert_eq!(error.expressions.len(), core.len());
+    }
+}
+
+#[test]
+fn solvable_constraints_have_no_core() {
+    let constraints = run_pipeline("(\\f -> (\\x -> (f (f x))))", &Config::default())
+        .unwrap()
+        .constraints;
+    assert_eq!(unsatisfiable_core(&constraints), None);
+    assert!(slice_error("(\\x -> x)").is_err());
+}
+
+#[test]
+fn missing_origins_keep_the_slice_aligned() {
+    let run = run_pipeline("(\\f -> ((f 1), (f true)))", &Config::default()).unwrap();
+    let full = slicing::slice_error(&run.tree, &run.constraints, &run.origins).unwrap();
+    assert!(full.expressions.iter().all(Option::is_some));
+
+    // origins only up to the first constraint of the slice, none of its constraints has one
+    let mut origins = run.origins.clone();
+    origins.truncate(full.indices[0]);
+    let slice = slicing::slice_error(&run.tree, &run.constraints, &origins).unwrap();
+    assert_eq!(slice.origins.len(), slice.constraints.len());
+    assert_eq!(slice.expressions.len(), slice.constraints.len());
+    assert!(slice.expressions.iter().all(Option::is_none));
+
+    // an origin whose node does not exist only loses its own expression
+    let mut origins = run.origins.clone();
+    let last = *full.indices.last().unwrap();
+    origins[full.indices[0]].node = usize::MAX;
+    let slice = slicing::slice_error(&run.tree, &run.constraints, &origins).unwrap();
+    assert_eq!(slice.expressions[0], None);
+    assert_eq!(slice.expressions[1..], full.expressions[1..]);
+    assert_eq!(
+        slice.origins.last().unwrap().as_ref(),
+        run.origins.get(last)
+    );
+}
@@ -0,0 +1,404 @@
+use fmfp::{
+    parse, run_pipeline,
+    typers::{
+        config::{Config, Generation, SolverStrategy},
+        engines::canonical,
+        rules::{Origin, RuleExpr, TypeExpr},
+        solver::{
+            compare_strategies, solve_equations, solve_equations_with_config,
+            solve_equations_with_origins, Solution, Step,
+        },
+        tree::TypeInference,
+        type_parser::TypeParser,
+    },
+};
+
+const TYPABLE: &[&str] = &[
+    "1",
+    "(\\x -> x)",
+    "(\\x -> (x + 1))",
+    "(\\p -> ((fst p) + (snd p)))",
+    "(\\f -> (\\g -> (\\x -> (f (g x)))))",
+    "((\\f -> (f (iszero 0))) (\\b -> (if b then 1 else 2)))",
+    "(\\x -> (if x then (\\y -> y) else (\\z -> z)))",
+    "(\\f -> (\\x -> ((f x), (f 1))))",
+    "(\\f -> (if _c then (f _a) else (snd _)))",
+];
+
+// Expressions with a clash and expressions which need an infinite type
+const UNTYPABLE: &[&str] = &[
+    "(1 + true)",
+    "(if 1 then 2 else 3)",
+    "(\\f -> ((f 1), (f true)))",
+    "(fst (\\x -> x))",
+    "(\\x -> (x x))",
+    "(\\x -> (x (\\y -> x)))",
+];
+
+// the type of the solution with its variables renamed in order, or `None` if there is none
+fn final_type(solution: &Solution) -> Option<TypeExpr> {
+    match solution.result.as_ref().expect("the solver did not finish") {
+        Ok(rule) => Some(canonical(&rule.rhs)),
+        Err(_) => None,
+    }
+}
+
+fn typ(input: &str) -> TypeExpr {
+    TypeParser::parse_type(input).unwrap()
+}
+
+fn rule(var: usize, rhs: TypeExpr) -> RuleExpr {
+    RuleExpr {
+        var,
+        rhs: Box::new(rhs),
+        origin: None,
+    }
+}
+
+#[test]
+fn the_step_limit_allows_exactly_that_many_steps() {
+    let ast = parse("(\\f -> (\\x -> ((f x), (f 1))))").unwrap();
+    let (_, constraints) = TypeInference::infer(ast).unwrap();
+    let needed =
+        solve_equations_with_config(constraints.clone(), 0, &Config::default()).num_steps();
+    assert!(needed > 0);
+
+    for max_solver_steps in [needed, needed + 1] {
+        let config = Config {
+            max_solver_steps,
+            ..Config::default()
+        };
+        let solution = solve_equations_with_config(constraints.clone(), 0, &config);
+        assert!(
+            matches!(solution.result, Some(Ok(_))),
+            "{:?}",
+            solution.result
+        );
+        assert_eq!(solution.num_steps(), needed);
+    }
+
+    let config = Config {
+        max_solver_steps: needed - 1,
+        ..Config::default()
+    };
+    let solution = solve_equations_with_config(constraints, 0, &config);
+    assert_eq!(
+        solution.result,
+        Some(Err(format!(
+            "the solver did not finish within {} steps, aborting ...",
+            needed - 1
+        )))
+    );
+}
+
+#[test]
+fn a_single_step_fits_a_limit_of_one() {
+    let config = Config {
+        max_solver_steps: 1,
+        ..Config::default()
+    };
+    let run = run_pipeline("(\\x -> x)", &config).unwrap();
+    assert_eq!(run.solution.num_steps(), 1);
+    assert!(matches!(run.solution.result, Some(Ok(_))));
+
+    // no step at all is needed here
+    let config = Config {
+        max_solver_steps: 0,
+        ..Config::default()
+    };
+    let run = run_pipeline("1", &config).unwrap();
+    assert!(matches!(run.solution.result, Some(Ok(_))));
+}
+
+// the constraints and the type of every node in pre order, as they are written in the exercise solutions
+fn numbering(input: &str, generation: Generation) -> (Vec<String>, Vec<String>) {
+    let config = Config {
+        generation,
+        ..Config::default()
+    };
+    let (tree, constraints) =
+        TypeInference::infer_with_config(parse(input).unwrap(), &config).unwrap();
+    let constraints = constraints
+        .iter()
+        .map(|(a, b)| format!("{} = {}", a, b))
+        .collect();
+    let types = tree
+        .preorder()
+        .iter()
+        .map(|node| format!("{} :: {}", node.expr.0, node.expr.1))
+        .collect();
+    (constraints, types)
+}
+
+#[test]
+fn every_generation_numbers_the_variables_depth_first_from_left_to_right() {
+    // the abstraction is expected to be a function and the projected variable a tuple, the argument is neither
+    let input = "((\\x -> (fst x)) (1, true))";
+
+    let (constraints, types) = numbering(input, Generation::AlwaysFresh);
+    assert_eq!(
+        constraints,
+        [
+            "t1 -> t0 = t2 -> t3",
+            "t2 = (t3, t4)",
+            "t1 = (t5, t6)",
+            "t5 = Int",
+            "t6 = Bool"
+        ]
+    );
+    assert_eq!(
+        types,
+        [
+            "(\\x -> fst x (1, true)) :: t0",
+            "\\x -> fst x :: t1 -> t0",
+            "fst x :: t3",
+            "x :: (t3, t4)",
+            "(1, true) :: (t5, t6)",
+            "1 :: t5",
+            "true :: t6"
+        ]
+    );
+
+    let (constraints, types) = numbering(input, Generation::ReuseStructure);
+    assert_eq!(
+        constraints,
+        ["t1 = (t0, t2)", "t1 = (t3, t4)", "t3 = Int", "t4 = Bool"]
+    );
+    assert_eq!(
+        types,
+        [
+            "(\\x -> fst x (1, true)) :: t0",
+            "\\x -> fst x :: t1 -> t0",
+            "fst x :: t0",
+            "x :: (t0, t2)",
+            "(1, true) :: (t3, t4)",
+            "1 :: t3",
+            "true :: t4"
+        ]
+    );
+
+    // the same variables, but the tuple is concluded with the type the application expects of its argument
+    let (constraints, types) = numbering(input, Generation::CourseConvention);
+    assert_eq!(
+        constraints,
+        ["t1 = (t0, t2)", "t1 = (t3, t4)", "t3 = Int", "t4 = Bool"]
+    );
+    assert_eq!(
+        types,
+        [
+            "(\\x -> fst x (1, true)) :: t0",
+            "\\x -> fst x :: t1 -> t0",
+            "fst x :: t0",
+            "x :: (t0, t2)",
+            "(1, true) :: t1",
+            "1 :: t3",
+            "true :: t4"
+        ]
+    );
+}
+
+#[test]
+fn the_fast_solver_finds_the_same_type_as_the_step_solver() {
+    for input in TYPABLE.iter().chain(UNTYPABLE) {
+        for generation in [
+            Generation::AlwaysFresh,
+            Generation::ReuseStructure,
+            Generation::CourseConvention,
+        ] {
+            let config = Config {
+                generation,
+                ..Config::default()
+            };
+            let (_, constraints) =
+                TypeInference::infer_with_config(parse(input).unwrap(), &config).unwrap();
+            let fast = solve_equations_with_config(
+                constraints.clone(),
+                0,
+                &Config {
+                    record_steps: false,
+                    ..config
+                },
+            );
+            assert_eq!(fast.num_steps(), 0);
+            assert_eq!(
+                final_type(&fast).is_none(),
+                UNTYPABLE.contains(input),
+                "{}",
+                input
+            );
+
+            for strategy in SolverStrategy::all() {
+                let config = Config {
+                    strategy,
+                    record_steps: true,
+                    ..config
+                };
+                let steps = solve_equations_with_config(constraints.clone(), 0, &config);
+                assert_eq!(
+                    final_type(&fast),
+                    final_type(&steps),
+                    "{} with {}",
+                    input,
+                    strategy.name()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn all_strategies_find_the_same_result() {
+    let mut differ = false;
+    for input in TYPABLE.iter().chain(UNTYPABLE) {
+        let (_, constraints) = TypeInference::infer(parse(input).unwrap()).unwrap();
+        let comparison = compare_strategies(constraints, 0, &Config::default());
+        assert_eq!(comparison.runs.len(), 4);
+        assert!(comparison.same_result, "{}", input);
+        assert_eq!(comparison.runs[0].first_difference, None);
+        for run in &comparison.runs {
+            let result = run.result.as_ref().expect("the solver did not finish");
+            assert_eq!(result.is_err(), UNTYPABLE.contains(input), "{}", input);
+            differ |= run.first_difference.is_some();
+        }
+    }
+    // the traces do differ, only the results are the same
+    assert!(differ);
+}
+
+#[test]
+fn an_equation_without_variables_on_either_side_is_decomposed() {
+    let equation = (typ("Int -> t2"), typ("t3 -> Bool"));
+    let solution = solve_equations(vec![equation.clone()], 2);
+    let steps = solution.steps();
+    let Step::Decompose(step) = &steps[0] else {
+        panic!("{:?}", steps[0]);
+    };
+    assert_eq!(step.equation, equation);
+    // `Int = t3` is turned around, so both parts become rules
+    assert_eq!(
+        step.rules_added,
+        [rule(3, TypeExpr::Int), rule(2, TypeExpr::Bool)]
+    );
+    assert!(step.equations_added.is_empty());
+    assert_eq!(solution.result_decompose_steps.len(), 1);
+    assert_eq!(*solution.result.unwrap().unwrap().rhs, TypeExpr::Bool);
+}
+
+#[test]
+fn nested_equations_are_decomposed_again() {
+    let equation = (
+        typ("(Int, t1 -> t2) -> t3"),
+        typ("(t4, Bool -> Int) -> Bool"),
+    );
+    let solution = solve_equations(vec![equation], 1);
+    let decomposed: Vec<_> = solution
+        .result_decompose_steps
+        .iter()
+        .map(|step| (step.equations_added.clone(), step.rules_added.clone()))
+        .collect();
+    assert_eq!(
+        decomposed,
+        [
+            (
+                vec![(typ("(Int, t1 -> t2)"), typ("(t4, Bool -> Int)"))],
+                vec![rule(3, TypeExpr::Bool)]
+            ),
+            (
+                vec![(typ("t1 -> t2"), typ("Bool -> Int"))],
+                vec![rule(4, TypeExpr::Int)]
+            ),
+            (
+                vec![],
+                vec![rule(1, TypeExpr::Bool), rule(2, TypeExpr::Int)]
+            ),
+        ]
+    );
+    assert_eq!(*solution.result.unwrap().unwrap().rhs, TypeExpr::Bool);
+}
+
+#[test]
+fn a_function_and_a_tuple_clash() {
+    let equation = (typ("Int -> t1"), typ("(Int, Bool)"));
+    let solution = solve_equations(vec![(typ("t0"), typ("t1")), equation.clone()], 0);
+    let steps = solution.steps();
+    let Some(Step::Clash(step)) = steps.last() else {
+        panic!("{:?}", steps.last());
+    };
+    assert_eq!(step.equation, equation);
+    assert_eq!(solution.result_clash_steps.len(), 1);
+    let e = solution.result.unwrap().unwrap_err();
+    assert!(e.contains("their outermost types differ"), "{}", e);
+}
+
+#[test]
+fn decomposed_and_accumulated_rules_keep_the_origin_of_their_constraint() {
+    let origin = |node: usize, rule: &str| Origin {
+        node,
+        rule: rule.to_string(),
+    };
+    let equations = vec![
+        (typ("t0"), typ("Int -> t1")),
+        (typ("t0"), typ("t2 -> Bool")),
+        (typ("(Int, t3)"), typ("(t4, Bool)")),
+    ];
+    let origins = [origin(0, "App"), origin(1, "Abs"), origin(2, "tuple")];
+    let solution = solve_equations_with_origins(equations, &origins, 0, &Config::default());
+    assert!(matches!(solution.result, Some(Ok(_))));
+
+    let decompose = &solution.result_decompose_steps[0];
+    assert_eq!(
+        decompose.rules_added,
+        [rule(4, TypeExpr::Int), rule(3, TypeExpr::Bool)]
+    );
+    for added in &decompose.rules_added {
+        assert_eq!(added.origin, Some(origin(2, "tuple")));
+    }
+
+    // the new rules replace the second of the compared rules
+    let accumulate = &solution.result_accumulate_steps[0];
+    assert_eq!(accumulate.rules_compared.1.origin, Some(origin(1, "Abs")));
+    assert_eq!(
+        accumulate.rules_added,
+        [rule(2, TypeExpr::Int), rule(1, TypeExpr::Bool)]
+    );
+    for added in &accumulate.rules_added {
+        assert_eq!(added.origin, Some(origin(1, "Abs")));
+    }
+}
+
+#[test]
+fn rules_of_an_expression_keep_the_origin_of_their_constraint() {
+    let ast = parse("((\\f -> (f 1)) (\\x -> (x + 1)))").unwrap();
+    let (_, constraints, origins) =
+        TypeInference::infer_with_origins(ast, &Config::default()).unwrap();
+    let solution = solve_equations_with_origins(constraints, &origins, 0, &Config::default());
+    assert!(!solution.result_accumulate_steps.is_empty());
+    for step in &solution.result_accumulate_steps {
+        for added in &step.rules_added {
+            assert!(added.origin.is_some());
+            assert_eq!(added.origin, step.rules_compared.1.origin);
+        }
+    }
+}
+
+#[test]
+fn the_rule_sets_of_one_replay_match_the_rules_around_each_step() {
+    for input in TYPABLE.iter().chain(UNTYPABLE) {
+        let (_, constraints) = TypeInference::infer(parse(input).unwrap()).unwrap();
+        let solution = solve_equations(constraints, 0);
+        let sets = solution.rule_sets();
+        assert_eq!(sets.len(), solution.num_steps() + 1, "{}", input);
+        assert_eq!(sets[0], solution.rules);
+        for id in 0..solution.num_steps() {
+            let step = id as i32;
+            assert_eq!(solution.rules_before(step).unwrap(), sets[id], "{}", input);
+            assert_eq!(
+                solution.rules_after(step).unwrap(),
+                sets[id + 1],
+                "{}",
+                input
+            );
+        }
+        assert_eq!(*sets.last().unwrap(), solution.simplified_rules());
+    }
+}
@@ -0,0 +1,126 @@
+use fmfp::{
+    run_pipeline,
+    typers::{
+        config::Config,
+        utils::text::{render_tree, TextOptions},
+    },
+};
+
+const INPUTS: &[&str] = &[
+    "((\\x -> (x + 1)) 2)",
+    "(\\f -> (\\x -> (if (iszero (f x)) then (f (x, 1)) else (fst (x, x)))))",
+];
+
+fn render(input: &str, max_width: Option<usize>, ascii: bool) -> String {
+    let run = run_pipeline(input, &Config::default()).unwrap();
+    render_tree(&run.tree, &TextOptions { max_width, ascii })
+}
+
+// the tokens of the form `[n]` and `G1` or `Γ₁` in a line
+fn tokens(line: &str, ascii: bool) -> (Vec<String>, Vec<String>) {
+    let (context, digits): (char, fn(char) -> bool) = if ascii {
+        ('G', |c| c.is_ascii_digit())
+    } else {
+        ('Γ', |c| ('₀'..='₉').contains(&c))
+    };
+    let mut references = vec![];
+    let mut contexts = vec![];
+    let chars: Vec<char> = line.chars().collect();
+    for (i, &c) in chars.iter().enumerate() {
+        let number: String = chars[i + 1..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        if c == '[' && !number.is_empty() && chars.get(i + 1 + number.len()) == Some(&']') {
+            references.push(format!("[{}]", number));
+        }
+        let name: String = chars[i + 1..].iter().take_while(|c| digits(**c)).collect();
+        if c == context && !name.is_empty() {
+            contexts.push(format!("{}{}", context, name));
+        }
+    }
+    (references, contexts)
+}
+
+#[test]
+fn an_unbounded_tree_is_rendered_in_one_piece() {
+    let expected = concat!(
+        "----------------- (Var)   ----------------- (Int)\n",
+        "x: t1 |- x :: Int         x: t1 |- 1 :: Int\n",
+        "------------------------------------------- (BinOp)\n",
+        "          x: t1 |- (x + 1) :: t0\n",
+        "       ---------------------------- (Abs)             ---------- (Int)\n",
+        "       |- \\x -> (x + 1) :: t1 -> t0                   |- 2 :: t1\n",
+        "       --------------------------------------------------------- (App)\n",
+        "                      |- (\\x -> (x + 1) 2) :: t0\n",
+    );
+    assert_eq!(render(INPUTS[0], None, true), expected);
+
+    let unicode = render(INPUTS[0], None, false);
+    assert!(unicode.contains("x: t1 ⊢ (x + 1) :: t0"), "{}", unicode);
+    assert!(unicode.contains("─── (App)"), "{}", unicode);
+    assert!(
+        !unicode.contains("|-") && !unicode.contains("--"),
+        "{}",
+        unicode
+    );
+
+    // a width which the whole tree fits into changes nothing
+    for input in INPUTS {
+        for ascii in [true, false] {
+            let full = render(input, None, ascii);
+            let width = full.lines().map(|line| line.chars().count()).max().unwrap();
+            assert_eq!(render(input, Some(width), ascii), full, "{}", input);
+            assert_ne!(render(input, Some(width - 1), ascii), full, "{}", input);
+        }
+    }
+}
+
+#[test]
+fn narrow_trees_are_split_into_referenced_parts_within_the_width() {
+    for input in INPUTS {
+        for width in [30, 50] {
+            for ascii in [true, false] {
+                let text = render(input, Some(width), ascii);
+                assert!(!ascii || text.is_ascii(), "{}", text);
+
+                let mut sections = vec![];
+                let mut references = vec![];
+                let mut definitions = vec![];
+                let mut contexts = vec![];
+                let mut previous = "";
+                for line in text.lines() {
+                    assert!(line.chars().count() <= width, "{}:\n{}", input, text);
+                    // a part starts with its number on a line of its own after an empty line
+                    if previous.is_empty() && line.starts_with('[') && line.ends_with(']') {
+                        sections.push(line.to_string());
+                    } else if let Some((name, _)) = line.split_once(" = ") {
+                        definitions.push(name.to_string());
+                    } else {
+                        let (r, c) = tokens(line, ascii);
+                        references.extend(r);
+                        contexts.extend(c);
+                    }
+                    previous = line;
+                }
+
+                // every part which is referenced is shown exactly once, and every part is referenced
+                assert!(!sections.is_empty(), "{}", text);
+                references.sort();
+                references.dedup();
+                let mut unique = sections.clone();
+                unique.sort();
+                unique.dedup();
+                assert_eq!(unique.len(), sections.len(), "{}", text);
+                assert_eq!(unique, references, "{}", text);
+
+                // every context name is listed below the tree
+                contexts.sort();
+                contexts.dedup();
+                definitions.sort();
+                assert!(!definitions.is_empty(), "{}", text);
+                assert_eq!(contexts, definitions, "{}", text);
+            }
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use fmfp::{
+    find_holes, parse, run_pipeline,
+    typers::{config::Config, parser::AstNode, rules::TypeExpr},
+};
+
+fn function(a: TypeExpr, b: TypeExpr) -> TypeExpr {
+    TypeExpr::Function(Box::new(a), Box::new(b))
+}
+
+#[test]
+fn holes_are_parsed_with_their_name() {
+    assert_eq!(parse("_").unwrap(), AstNode::Hole(String::new()));
+    assert_eq!(parse("_goal").unwrap(), AstNode::Hole("goal".to_string()));
+    assert_eq!(parse("_goal").unwrap().to_string(), "_goal");
+}
+
+#[test]
+fn holes_get_the_type_and_context_they_need() {
+    let found = find_holes("(\\f -> (\\x -> (if _c then ((f _a) + x) else _)))").unwrap();
+    let names: Vec<&str> = found.holes.iter().map(|h| h.name.as_str()).collect();
+    assert_eq!(names, ["c", "a", ""]);
+    assert_eq!(found.holes[0].typ, TypeExpr::Bool);
+    assert_eq!(found.holes[2].typ, TypeExpr::Int);
+
+    let a = &found.holes[1];
+    let f = &a.gamma[0];
+    assert_eq!(f.0, "f");
+    assert_eq!(f.1, function(a.typ.clone(), TypeExpr::Int));
+    assert_eq!(a.gamma[1], ("x".to_string(), TypeExpr::Int));
+}
+
+#[test]
+fn an_unconstrained_hole_has_any_type() {
+    let run = run_pipeline("_", &Config::default()).unwrap();
+    run.solution.verify(&run.constraints).unwrap();
+    let found = find_holes("(fst _)").unwrap();
+    assert!(matches!(found.holes[0].typ, TypeExpr::Tuple(_, _)));
+}
+
+#[test]
+fn holes_of_an_expression_without_a_type_are_not_reported() {
+    assert!(find_holes("(_ + true)").is_err());
+}
@@ -0,0 +1,121 @@
+use fmfp::{
+    run_pipeline,
+    typers::{
+        config::Config,
+        utils::typst::{constraint_table, document, proof_tree, solution_steps},
+    },
+};
+
+const INPUTS: &[&str] = &[
+    "1",
+    "((\\x -> (x + 1)) 2)",
+    "(\\f -> (\\x -> (if (iszero (f x)) then (f 1) else (fst (x, x)))))",
+    "(snd (true, _hole))",
+    // untypable, the error of the solver contains backslashes and has to be escaped inside a string
+    "(\\x -> (x x))",
+    "(if 1 then 2 else 3)",
+];
+
+// checks that the brackets and formulas are closed in the right order and that every string literal ends,
+// which is what Typst needs to parse the document
+fn balanced(typst: &str) -> Result<(), String> {
+    let mut open: Vec<char> = vec![];
+    let mut chars = typst.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // an escaped character, such as `\#` or the line break `\`
+            '\\' => {
+                chars.next();
+            }
+            '"' => loop {
+                match chars.next() {
+                    Some('\\') => {
+                        chars.next();
+                    }
+                    Some('"') => break,
+                    Some(_) => (),
+                    None => return Err("a string is not closed".to_string()),
+                }
+            },
+            '(' | '[' | '{' => open.push(c),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if open.pop() != Some(expected) {
+                    return Err(format!("{} closes something else", c));
+                }
+            }
+            '$' if open.last() == Some(&'$') => {
+                open.pop();
+            }
+            '$' => open.push('$'),
+            _ => (),
+        }
+    }
+    match open.last() {
+        Some(unclosed) => Err(format!("{} is not closed", unclosed)),
+        None => Ok(()),
+    }
+}
+
+#[test]
+fn documents_are_balanced() {
+    for input in INPUTS {
+        let run = run_pipeline(input, &Config::default()).unwrap();
+        let typst = document(&run.ast, &run.tree, &run.constraints, &run.solution);
+        balanced(&typst).unwrap_or_else(|e| panic!("{}: {}\n{}", input, e, typst));
+        assert!(typst.starts_with("#import \"@preview/curryst:0.5.0\": rule, prooftree\n"));
+    }
+    assert!(balanced("[$(a]$").is_err());
+    assert!(balanced("#raw(\"\\\")").is_err());
+}
+
+#[test]
+fn every_node_is_one_rule_of_the_proof_tree() {
+    for input in INPUTS {
+        let run = run_pipeline(input, &Config::default()).unwrap();
+        let typst = proof_tree(&run.tree);
+        let rules = typst
+            .lines()
+            .filter(|line| line.trim_start() == "rule(")
+            .count();
+        assert_eq!(rules, run.tree.preorder().len(), "{}", input);
+        assert!(typst.starts_with("#align(center, layout(size => {\n  let tree = prooftree(\n"));
+    }
+}
+
+#[test]
+fn the_constraints_and_steps_are_numbered_from_one() {
+    let run = run_pipeline(INPUTS[2], &Config::default()).unwrap();
+    let table = constraint_table(&run.constraints);
+    let rows: Vec<&str> = table
+        .lines()
+        .filter(|line| line.starts_with("  [") && !line.contains("header"))
+        .collect();
+    assert_eq!(rows.len(), run.constraints.len());
+    for (i, row) in rows.iter().enumerate() {
+        assert!(row.starts_with(&format!("  [{}], [$", i + 1)), "{}", row);
+    }
+
+    let steps = solution_steps(&run.solution);
+    let headings: Vec<&str> = steps
+        .lines()
+        .filter(|line| line.starts_with("=== Step"))
+        .collect();
+    assert_eq!(headings.len(), run.solution.num_steps());
+    for (i, heading) in headings.iter().enumerate() {
+        assert!(
+            heading.starts_with(&format!("=== Step {}: ", i + 1)),
+            "{}",
+            heading
+        );
+    }
+    assert!(steps.contains("=== Result\n$ t_(0) = "), "{}", steps);
+
+    let run = run_pipeline(INPUTS[4], &Config::default()).unwrap();
+    let steps = solution_steps(&run.solution);
+    assert!(steps.contains("=== Result\nThe constraints cannot be solved: #raw(\""));
+}
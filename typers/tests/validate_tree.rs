@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use fmfp::{
+    parse,
+    typers::{rules::TypeExpr, tree::TypeInference, unify::Unifier, validate::validate_tree},
+};
+
+// Typable expressions which together use every rule, also nested inside each other
+const EXPRESSIONS: &[&str] = &[
+    "1",
+    "true",
+    "(\\x -> x)",
+    "(\\x -> (x + 1))",
+    "((\\x -> (x * 2)) 3)",
+    "(iszero 1)",
+    "((\\x -> (iszero x)) 1)",
+    "(\\f -> (iszero (f 1)))",
+    "(if (iszero 0) then (iszero 1) else false)",
+    "((\\x -> (x, (iszero x))) 1)",
+    "(fst (1, true))",
+    "(snd (1, (iszero 2)))",
+    "(\\p -> ((fst p) + (snd p)))",
+    "(\\x -> (\\x -> (x, true)))",
+    "(\\f -> (\\g -> (\\x -> (f (g x)))))",
+    "((\\f -> (f (iszero 0))) (\\b -> (if b then 1 else 2)))",
+    "(\\x -> (if x then (\\y -> y) else (\\z -> z)))",
+    "(\\f -> (if _c then (f _a) else (snd _)))",
+];
+
+// Solves the constraints with the unifier, every variable is mapped to its final type
+fn solve(constraints: &[(TypeExpr, TypeExpr)]) -> Option<HashMap<usize, TypeExpr>> {
+    let mut unifier = Unifier::default();
+    for (a, b) in constraints {
+        unifier.unify(a, b).ok()?;
+    }
+    unifier.check_cycles().ok()?;
+    let mut vars: Vec<usize> = constraints
+        .iter()
+        .flat_map(|(a, b)| a.all_vars().into_iter().chain(b.all_vars()))
+        .collect();
+    vars.sort();
+    vars.dedup();
+    vars.into_iter()
+        .map(|v| Some((v, unifier.resolve(&TypeExpr::Var(v)).ok()?)))
+        .collect()
+}
+
+#[test]
+fn inferred_trees_obey_the_rules() {
+    for input in EXPRESSIONS {
+        let ast = parse(input).unwrap();
+        let (tree, constraints) = TypeInference::infer(ast).unwrap();
+        let substitution = solve(&constraints).unwrap_or_else(|| panic!("{} has no type", input));
+        if let Err(e) = validate_tree(&tree, &substitution) {
+            panic!("{}: {}", input, e);
+        }
+    }
+}
+
+#[test]
+fn wrong_types_are_found() {
+    let ast = parse("((\\x -> (x + 1)) 2)").unwrap();
+    let (mut tree, constraints) = TypeInference::infer(ast).unwrap();
+    let substitution = solve(&constraints).unwrap();
+    assert!(validate_tree(&tree, &substitution).is_ok());
+
+    // the argument is an integer, claiming it is a boolean breaks the application
+    tree.constraints[1].expr.1 = TypeExpr::Bool;
+    assert!(validate_tree(&tree, &substitution).is_err());
+}
@@ -0,0 +1,100 @@
+use fmfp::{
+    run_pipeline,
+    typers::{
+        config::{Config, Generation, SolverStrategy},
+        rules::{RuleExpr, TypeExpr},
+        solver::solve_constraints,
+        unify::solve_constraints_fast,
+    },
+};
+
+// Typable expressions whose constraints need every kind of step, including trivial constraints and decompositions
+const EXPRESSIONS: &[&str] = &[
+    "1",
+    "(\\x -> x)",
+    "(\\x -> (x + 1))",
+    "((\\x -> (x * 2)) 3)",
+    "(\\f -> (iszero (f 1)))",
+    "(if (iszero 0) then (iszero 1) else false)",
+    "(\\p -> ((fst p) + (snd p)))",
+    "(\\f -> (\\g -> (\\x -> (f (g x)))))",
+    "((\\f -> (f (iszero 0))) (\\b -> (if b then 1 else 2)))",
+    "(\\x -> (if x then (\\y -> y) else (\\z -> z)))",
+    "(\\f -> (\\x -> ((f x), (f 1))))",
+    "(\\f -> (if _c then (f _a) else (snd _)))",
+];
+
+#[test]
+fn solutions_satisfy_all_constraints() {
+    for input in EXPRESSIONS {
+        for generation in [
+            Generation::AlwaysFresh,
+            Generation::ReuseStructure,
+            Generation::CourseConvention,
+        ] {
+            for strategy in SolverStrategy::all() {
+                let config = Config {
+                    generation,
+                    strategy,
+                    ..Config::default()
+                };
+                let run = run_pipeline(input, &config).unwrap();
+                if let Err(e) = run.solution.verify(&run.constraints) {
+                    panic!("{} with {}: {}", input, strategy.name(), e);
+                }
+            }
+
+            // the fast solver records no steps, its substitution comes from the unifier
+            let config = Config {
+                generation,
+                record_steps: false,
+                ..Config::default()
+            };
+            let run = run_pipeline(input, &config).unwrap();
+            assert_eq!(run.solution.num_steps(), 0);
+            if let Err(e) = run.solution.verify(&run.constraints) {
+                panic!("{} without steps: {}", input, e);
+            }
+        }
+    }
+}
+
+#[test]
+fn broken_constraints_are_reported() {
+    let run = run_pipeline("(\\x -> (x + 1))", &Config::default()).unwrap();
+    let mut constraints = run.constraints.clone();
+    constraints.push((TypeExpr::Var(0), TypeExpr::Int));
+    let e = run.solution.verify(&constraints).unwrap_err();
+    assert!(e.starts_with(&format!("constraint {}", constraints.len())));
+
+    // without a type there is no substitution to check
+    let run = run_pipeline("(1 + true)", &Config::default()).unwrap();
+    assert!(run.solution.verify(&run.constraints).is_err());
+}
+
+#[test]
+fn a_renamed_goal_variable_keeps_its_type() {
+    // the remove step replaces the goal t5 with t2, whose rule gives the type
+    let rules = vec![
+        RuleExpr {
+            var: 5,
+            rhs: Box::new(TypeExpr::Var(2)),
+            origin: None,
+        },
+        RuleExpr {
+            var: 2,
+            rhs: Box::new(TypeExpr::Int),
+            origin: None,
+        },
+    ];
+    let constraints: Vec<(TypeExpr, TypeExpr)> = rules
+        .iter()
+        .map(|rule| (TypeExpr::Var(rule.var), *rule.rhs.clone()))
+        .collect();
+    let steps = solve_constraints(rules.clone(), 5);
+    let fast = solve_constraints_fast(rules, &[], 5, &Config::default());
+    assert_eq!(steps.result, fast.result);
+    assert_eq!(*steps.result.clone().unwrap().unwrap().rhs, TypeExpr::Int);
+    steps.verify(&constraints).unwrap();
+    fast.verify(&constraints).unwrap();
+}
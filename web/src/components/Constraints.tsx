@@ -5,11 +5,13 @@ import Card from "./Card";
 type ConstraintsProps = {
   constraints: string[];
   constraints_without_trivial: string[];
+  constraint_origins: string[];
 };
 
 const Constraints = ({
   constraints,
   constraints_without_trivial,
+  constraint_origins,
 }: ConstraintsProps) => {
   return (
     <Card title="Constraints">
@@ -21,19 +23,26 @@ const Constraints = ({
             <Text>Constraints</Text>
           </Center>
           {constraints.map((constraint, index) => {
+            const origin = constraint_origins[index] && (
+              <Text size="xs" c="dimmed">
+                <MathJax inline>{constraint_origins[index]}</MathJax>
+              </Text>
+            );
             if (constraints_without_trivial.includes(constraint)) {
               return (
-                <Center>
-                  <MathJax key={index}>{"\\(" + constraint + " \\)"}</MathJax>
-                </Center>
+                <Stack gap={0} align="center" key={index}>
+                  <MathJax>{"\\(" + constraint + " \\)"}</MathJax>
+                  {origin}
+                </Stack>
               );
             } else {
               return (
-                <Center>
-                  <MathJax key={index} style={{ color: "red" }}>
+                <Stack gap={0} align="center" key={index}>
+                  <MathJax style={{ color: "red" }}>
                     {"\\(" + constraint + " \\)"}
                   </MathJax>
-                </Center>
+                  {origin}
+                </Stack>
               );
             }
           })}
@@ -0,0 +1,23 @@
+import { Stack } from "@mantine/core";
+import { MathJax } from "better-react-mathjax";
+
+import Card from "./Card";
+
+type HolesProps = {
+  holes: string[];
+};
+
+// the type and the context every typed hole needs after solving
+const Holes = ({ holes }: HolesProps) => {
+  return (
+    <Card title="Typed holes">
+      <Stack align="center">
+        {holes.map((hole, index) => (
+          <MathJax key={index}>{"\\(" + hole + "\\)"}</MathJax>
+        ))}
+      </Stack>
+    </Card>
+  );
+};
+
+export default Holes;
@@ -68,6 +68,11 @@ const MiniHaskell = () => {
           "\\(\\dfrac{ \\Gamma \\vdash t_1 :: (\\tau_1, \\tau_2) } {\\Gamma \\vdash {\\textbf{snd} \\: t :: \\tau_2}} \\textsf{snd}\\)"
         }
       </MathJax>
+      <MathJax>
+        {
+          "\\(\\dfrac{ } {\\Gamma \\vdash {\\_ \\: :: \\: \\tau}} \\textsf{Hole}\\)"
+        }
+      </MathJax>
     </Flex>
   );
 };
@@ -17,6 +17,7 @@ import Constraints from "./Constraints";
 import Tree from "./Tree";
 import Variables from "./Variables";
 import Solution from "./Solution";
+import Holes from "./Holes";
 import Steps from "./steps/Steps";
 import Info from "./Info";
 import MiniHaskell from "./MiniHaskell";
@@ -108,6 +109,7 @@ const Solver = () => {
                   constraints_without_trivial={
                     parsed.constraints_without_trivial
                   }
+                  constraint_origins={parsed.constraint_origins ?? []}
                 />
               )}
               {parsed.solution && parsed.solution.variables && (
@@ -116,6 +118,9 @@ const Solver = () => {
               {parsed.solution && parsed.solution.result && (
                 <Solution solution={parsed.solution.result} />
               )}
+              {parsed.holes && parsed.holes.length > 0 && (
+                <Holes holes={parsed.holes} />
+              )}
               {parsed.solution && parsed.solution && (
                 <Steps steps={parsed.solution} />
               )}
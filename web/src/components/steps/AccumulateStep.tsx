@@ -1,12 +1,15 @@
 import { Center, Flex, SimpleGrid, Stack, Text, Title } from "@mantine/core";
-import { ResultAccumulateStepTS } from "FMFP";
+import { ResultAccumulateStepTS, SolutionTS } from "FMFP";
 import { MathJax } from "better-react-mathjax";
 
 type AccumulateStepProps = {
   step: ResultAccumulateStepTS;
+  solution: SolutionTS;
 };
 
-const AccumulateStep = ({ step }: AccumulateStepProps) => {
+const AccumulateStep = ({ step, solution }: AccumulateStepProps) => {
+  const rules_before = solution.rules_before(step.id);
+  const rules_after = solution.rules_after(step.id);
   return (
     <Stack w={"100%"}>
       <Title order={3}>Accumulate Step</Title>
@@ -14,7 +17,7 @@ const AccumulateStep = ({ step }: AccumulateStepProps) => {
       <SimpleGrid cols={3} w={"100%"}>
         <Stack>
           <Text>Old Rules</Text>
-          {step.rules_before.map((rule) => {
+          {rules_before.map((rule) => {
             if (step.rules_compared.includes(rule)) {
               return (
                 <MathJax style={{ color: "blue" }}>
@@ -43,7 +46,7 @@ const AccumulateStep = ({ step }: AccumulateStepProps) => {
         </Stack>
         <Stack>
           <Text>Next Rules</Text>
-          {step.rules_after.map((rule) => {
+          {rules_after.map((rule) => {
             if (step.rules_added.includes(rule)) {
               return (
                 <MathJax style={{ color: "green" }}>
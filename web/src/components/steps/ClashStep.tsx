@@ -0,0 +1,22 @@
+import { Stack, Title } from "@mantine/core";
+import { ResultClashStepTS } from "FMFP";
+import { MathJax } from "better-react-mathjax";
+
+type ClashStepProps = {
+  step: ResultClashStepTS;
+};
+
+const ClashStep = ({ step }: ClashStepProps) => {
+  return (
+    <Stack w={"100%"}>
+      <Title order={3}>Clash Step</Title>
+
+      <MathJax style={{ color: "red" }}>
+        {"\\(" + step.equation + "\\)"}
+      </MathJax>
+      <MathJax>{step.text}</MathJax>
+    </Stack>
+  );
+};
+
+export default ClashStep;
@@ -0,0 +1,50 @@
+import { SimpleGrid, Stack, Text, Title } from "@mantine/core";
+import { ResultDecomposeStepTS, SolutionTS } from "FMFP";
+import { MathJax } from "better-react-mathjax";
+
+type DecomposeStepProps = {
+  step: ResultDecomposeStepTS;
+  solution: SolutionTS;
+};
+
+const DecomposeStep = ({ step, solution }: DecomposeStepProps) => {
+  const rules_after = solution.rules_after(step.id);
+  return (
+    <Stack w={"100%"}>
+      <Title order={3}>Decompose Step</Title>
+
+      <MathJax>{step.text}</MathJax>
+
+      <SimpleGrid cols={2} w={"100%"}>
+        <Stack>
+          <Text>Decomposed Equation</Text>
+          <MathJax style={{ color: "blue" }}>
+            {"\\(" + step.equation + "\\)"}
+          </MathJax>
+          {step.equations_added.length > 0 && <Text>Equations Left</Text>}
+          {step.equations_added.map((equation) => (
+            <MathJax style={{ color: "green" }}>
+              {"\\(" + equation + "\\)"}
+            </MathJax>
+          ))}
+        </Stack>
+        <Stack>
+          <Text>Next Rules</Text>
+          {rules_after.map((rule) => {
+            if (step.rules_added.includes(rule)) {
+              return (
+                <MathJax style={{ color: "green" }}>
+                  {"\\(" + rule + "\\)"}
+                </MathJax>
+              );
+            } else {
+              return <MathJax>{"\\(" + rule + "\\)"}</MathJax>;
+            }
+          })}
+        </Stack>
+      </SimpleGrid>
+    </Stack>
+  );
+};
+
+export default DecomposeStep;
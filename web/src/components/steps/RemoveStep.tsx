@@ -1,13 +1,16 @@
 import { Center, Flex, SimpleGrid, Stack, Text, Title } from "@mantine/core";
 import { IconArrowRight } from "@tabler/icons-react";
-import { ResultRemoveStepTS } from "FMFP";
+import { ResultRemoveStepTS, SolutionTS } from "FMFP";
 import { MathJax } from "better-react-mathjax";
 
 type RemoveStepProps = {
   step: ResultRemoveStepTS;
+  solution: SolutionTS;
 };
 
-const RemoveStep = ({ step }: RemoveStepProps) => {
+const RemoveStep = ({ step, solution }: RemoveStepProps) => {
+  const rules_before = solution.rules_before(step.id);
+  const rules_after = solution.rules_after(step.id);
   return (
     <Stack w={"100%"}>
       <Title order={3}>Remove Step</Title>
@@ -16,7 +19,7 @@ const RemoveStep = ({ step }: RemoveStepProps) => {
 
       <SimpleGrid cols={3} w={"100%"}>
         <Stack>
-          {step.rules_before.map((rule) => {
+          {rules_before.map((rule) => {
             if (step.rules_removed.includes(rule)) {
               return (
                 <MathJax style={{ color: "red" }}>
@@ -29,8 +32,8 @@ const RemoveStep = ({ step }: RemoveStepProps) => {
           })}
         </Stack>
         <Stack>
-          {step.rules_after.map((rule) => {
-            if (step.rules_before.includes(rule)) {
+          {rules_after.map((rule) => {
+            if (rules_before.includes(rule)) {
               return <MathJax>{"\\(" + rule + "\\)"}</MathJax>;
             } else {
               return (
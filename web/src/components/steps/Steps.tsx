@@ -14,6 +14,8 @@ import { ResultAccumulateStepTS, ResultRemoveStepTS, SolutionTS } from "FMFP";
 import { useState } from "react";
 import { ResultSubstituteStepTS } from "FMFP";
 import AccumulateStep from "./AccumulateStep";
+import ClashStep from "./ClashStep";
+import DecomposeStep from "./DecomposeStep";
 import RemoveStep from "./RemoveStep";
 import SubstituteStep from "./SubstituteStep";
 
@@ -31,24 +33,36 @@ const Solution = ({ steps }: StepsProps) => {
     let num_steps =
       steps.result_accumulate_steps.length +
       steps.result_remove_steps.length +
-      steps.result_substitute_steps.length;
+      steps.result_substitute_steps.length +
+      steps.result_decompose_steps.length +
+      steps.result_clash_steps.length;
     let all_steps: React.ReactNode[] = [];
     for (let i = 0; i < num_steps; i++) {
+      let step_decompose = steps.result_decompose_steps.find(
+        (step) => step.id == i,
+      );
+      if (step_decompose) {
+        all_steps.push(<DecomposeStep step={step_decompose} solution={steps} />);
+      }
+      let step_clash = steps.result_clash_steps.find((step) => step.id == i);
+      if (step_clash) {
+        all_steps.push(<ClashStep step={step_clash} />);
+      }
       let step_accumulate = steps.result_accumulate_steps.find(
         (step) => step.id == i,
       );
       if (step_accumulate) {
-        all_steps.push(<AccumulateStep step={step_accumulate} />);
+        all_steps.push(<AccumulateStep step={step_accumulate} solution={steps} />);
       }
       let step_remove = steps.result_remove_steps.find((step) => step.id == i);
       if (step_remove) {
-        all_steps.push(<RemoveStep step={step_remove} />);
+        all_steps.push(<RemoveStep step={step_remove} solution={steps} />);
       }
       let step_substitute = steps.result_substitute_steps.find(
         (step) => step.id == i,
       );
       if (step_substitute) {
-        all_steps.push(<SubstituteStep step={step_substitute} />);
+        all_steps.push(<SubstituteStep step={step_substitute} solution={steps} />);
       }
     }
     return all_steps;
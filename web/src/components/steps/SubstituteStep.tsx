@@ -8,21 +8,23 @@ import {
   Title,
 } from "@mantine/core";
 import { IconArrowRight } from "@tabler/icons-react";
-import { ResultSubstituteStepTS } from "FMFP";
+import { ResultSubstituteStepTS, SolutionTS } from "FMFP";
 import { MathJax } from "better-react-mathjax";
 
 type SubstituteStepProps = {
   step: ResultSubstituteStepTS;
+  solution: SolutionTS;
 };
 
-const SubstituteStep = ({ step }: SubstituteStepProps) => {
+const SubstituteStep = ({ step, solution }: SubstituteStepProps) => {
+  const rules_available = solution.rules_before(step.id);
   return (
     <Stack w={"100%"}>
       <Title order={3}>Substitute Step</Title>
       <SimpleGrid cols={3} w={"100%"}>
         <Stack>
           <Text>Rules Available</Text>
-          {step.rules_available.map((rule, index) => {
+          {rules_available.map((rule, index) => {
             if (rule == step.rule_used) {
               return (
                 <MathJax style={{ color: "green" }}>